@@ -0,0 +1,35 @@
+-- Index plein texte des notes (titre + contenu)
+
+-- La table garde sa propre copie du texte : les notes ont une clé TEXT, leur
+-- rowid implicite n'est donc pas stable face à un VACUUM.
+CREATE VIRTUAL TABLE notes_fts USING fts5(
+    note_id UNINDEXED,
+    title,
+    content,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO notes_fts (note_id, title, content)
+    SELECT id, title, COALESCE(content, '') FROM notes;
+
+-- Triggers de synchronisation avec la table notes
+CREATE TRIGGER notes_fts_insert
+    AFTER INSERT ON notes
+BEGIN
+    INSERT INTO notes_fts (note_id, title, content)
+        VALUES (NEW.id, NEW.title, COALESCE(NEW.content, ''));
+END;
+
+CREATE TRIGGER notes_fts_update
+    AFTER UPDATE OF id, title, content ON notes
+BEGIN
+    DELETE FROM notes_fts WHERE note_id = OLD.id;
+    INSERT INTO notes_fts (note_id, title, content)
+        VALUES (NEW.id, NEW.title, COALESCE(NEW.content, ''));
+END;
+
+CREATE TRIGGER notes_fts_delete
+    AFTER DELETE ON notes
+BEGIN
+    DELETE FROM notes_fts WHERE note_id = OLD.id;
+END;
@@ -0,0 +1,20 @@
+-- Historique des révisions de notes
+
+CREATE TABLE note_revisions (
+    id TEXT PRIMARY KEY,
+    note_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT,
+    -- 'create', 'edit' ou 'restore' ; seules les révisions 'edit' sont fusionnées
+    kind TEXT NOT NULL DEFAULT 'edit',
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_note_revisions_note ON note_revisions(note_id, created_at);
+
+-- Révision initiale pour les notes existantes
+INSERT INTO note_revisions (id, note_id, title, content, kind, created_at, updated_at)
+    SELECT lower(hex(randomblob(16))), id, title, content, 'create', updated_at, updated_at
+    FROM notes;
@@ -0,0 +1,19 @@
+-- Corbeille : suppression logique des notes, projets et tags
+
+ALTER TABLE projects ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE notes ADD COLUMN deleted_at TIMESTAMP;
+-- Projet dont la suppression a entraîné celle de la note, pour la restaurer avec lui
+ALTER TABLE notes ADD COLUMN deleted_with_project TEXT;
+ALTER TABLE tags ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX idx_projects_deleted_at ON projects(deleted_at);
+CREATE INDEX idx_notes_deleted_at ON notes(deleted_at);
+CREATE INDEX idx_tags_deleted_at ON tags(deleted_at);
+
+-- Réglages globaux de l'application
+CREATE TABLE app_settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+INSERT INTO app_settings (key, value) VALUES ('trash_retention_days', '30');
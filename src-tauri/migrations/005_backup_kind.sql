@@ -0,0 +1,6 @@
+-- Origine des sauvegardes : 'manual' (envoyée par le frontend), 'snapshot'
+-- (capturée côté Rust) ou 'pre-restore' (filet de sécurité avant restauration)
+
+ALTER TABLE backups ADD COLUMN kind TEXT NOT NULL DEFAULT 'manual';
+
+CREATE INDEX idx_backups_workspace ON backups(workspace_id, created_at);
@@ -0,0 +1,59 @@
+-- Liens entre notes : [[Titre]] (kind 'wiki') et [texte](note:<id>) (kind 'id')
+
+CREATE TABLE note_links (
+    source_id TEXT NOT NULL,
+    -- Ordre du lien dans la note
+    position INTEGER NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('wiki', 'id')),
+    -- Titre pour 'wiki', identifiant de note pour 'id'
+    target TEXT NOT NULL,
+    label TEXT,
+    line INTEGER NOT NULL,
+    -- Ligne contenant le lien, affichée avec les rétroliens
+    context TEXT NOT NULL,
+    PRIMARY KEY (source_id, position),
+    FOREIGN KEY (source_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_note_links_target ON note_links(target COLLATE NOCASE);
+CREATE INDEX idx_notes_title ON notes(title COLLATE NOCASE);
+
+-- Résolution à la lecture : un renommage ou une suppression de note est pris
+-- en compte sans réindexer. À titre égal, la note du même projet puis la plus
+-- ancienne l'emporte. Seuls les liens des notes hors corbeille sont visibles.
+CREATE VIEW resolved_note_links AS
+SELECT
+    l.source_id,
+    l.position,
+    l.kind,
+    l.target,
+    l.label,
+    l.line,
+    l.context,
+    CASE l.kind
+        WHEN 'id' THEN (
+            SELECT n.id FROM notes n
+            WHERE n.id = l.target AND n.deleted_at IS NULL
+        )
+        ELSE COALESCE(
+            (
+                SELECT n.id FROM notes n
+                WHERE n.title = l.target COLLATE NOCASE AND n.deleted_at IS NULL
+                AND n.project_id = src.project_id
+                ORDER BY n.created_at, n.id
+                LIMIT 1
+            ),
+            (
+                SELECT n.id FROM notes n
+                WHERE n.title = l.target COLLATE NOCASE AND n.deleted_at IS NULL
+                ORDER BY n.created_at, n.id
+                LIMIT 1
+            )
+        )
+    END AS target_id
+FROM note_links l
+JOIN notes src ON src.id = l.source_id
+WHERE src.deleted_at IS NULL;
+
+-- Les notes existantes sont indexées au prochain démarrage (analyse Markdown côté Rust)
+INSERT INTO app_settings (key, value) VALUES ('note_links_reindex', 'true');
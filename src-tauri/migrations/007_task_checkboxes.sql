@@ -0,0 +1,13 @@
+-- Tâches liées aux cases à cocher GFM (- [ ] / - [x]) du contenu des notes
+
+-- Position de la case parmi celles de la note et empreinte de son texte ;
+-- NULL pour une tâche créée hors du contenu
+ALTER TABLE tasks ADD COLUMN checkbox_index INTEGER;
+ALTER TABLE tasks ADD COLUMN checkbox_hash TEXT;
+
+-- updated_at sert à détecter les modifications concurrentes d'une tâche ;
+-- l'application le renseigne elle-même, le déclencheur l'écraserait
+DROP TRIGGER IF EXISTS update_task_timestamp;
+
+-- Les cases des notes existantes sont synchronisées au prochain démarrage
+INSERT INTO app_settings (key, value) VALUES ('task_checkboxes_sync', 'true');
@@ -0,0 +1,14 @@
+-- Échéance, priorité, date de complétion et responsable des tâches
+
+-- Jour (AAAA-MM-JJ) ou instant RFC 3339
+ALTER TABLE tasks ADD COLUMN due_date TEXT;
+-- 0 aucune, 1 basse, 2 moyenne, 3 haute
+ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+ALTER TABLE tasks ADD COLUMN assignee TEXT;
+
+-- La date de complétion des tâches déjà terminées est inconnue : on retient
+-- leur dernière modification
+UPDATE tasks SET completed_at = updated_at WHERE completed;
+
+CREATE INDEX idx_tasks_due_date ON tasks(due_date);
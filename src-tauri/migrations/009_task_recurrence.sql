@@ -0,0 +1,9 @@
+-- Tâches récurrentes
+
+-- Règle au format RRULE (sous-ensemble), voir recurrence.rs
+ALTER TABLE tasks ADD COLUMN recurrence TEXT;
+-- Identifiant de la première tâche de la série ; les occurrences terminées
+-- restent comme historique
+ALTER TABLE tasks ADD COLUMN series_id TEXT;
+
+CREATE INDEX idx_tasks_series ON tasks(series_id);
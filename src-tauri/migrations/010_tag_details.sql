@@ -0,0 +1,32 @@
+-- Couleur, description et hiérarchie des tags
+
+ALTER TABLE tags ADD COLUMN color TEXT;
+ALTER TABLE tags ADD COLUMN description TEXT;
+-- Le nom reste le chemin complet (« work/clients/acme ») ; parent_id pointe
+-- vers le tag « work/clients »
+ALTER TABLE tags ADD COLUMN parent_id TEXT REFERENCES tags(id) ON DELETE SET NULL;
+
+-- Noms en double (sans tenir compte de la casse) : les notes passent sur le
+-- tag le plus ancien, les autres sont supprimés
+CREATE TEMP TABLE tag_merges AS
+SELECT t.id AS id, (
+    SELECT k.id FROM tags k
+    WHERE k.name = t.name COLLATE NOCASE AND k.deleted_at IS NULL
+    ORDER BY k.created_at, k.rowid
+    LIMIT 1
+) AS keep_id
+FROM tags t
+WHERE t.deleted_at IS NULL;
+
+DELETE FROM tag_merges WHERE id = keep_id;
+INSERT OR IGNORE INTO note_tags (note_id, tag_id)
+    SELECT nt.note_id, m.keep_id FROM note_tags nt JOIN tag_merges m ON m.id = nt.tag_id;
+DELETE FROM note_tags WHERE tag_id IN (SELECT id FROM tag_merges);
+DELETE FROM tags WHERE id IN (SELECT id FROM tag_merges);
+DROP TABLE tag_merges;
+
+CREATE UNIQUE INDEX idx_tags_name ON tags(name COLLATE NOCASE) WHERE deleted_at IS NULL;
+CREATE INDEX idx_tags_parent ON tags(parent_id);
+
+-- Les tags parents manquants sont créés au prochain démarrage
+INSERT INTO app_settings (key, value) VALUES ('tag_hierarchy_rebuild', 'true');
@@ -0,0 +1,18 @@
+-- Description, couleur, icône, ordre et archivage des projets
+
+ALTER TABLE projects ADD COLUMN description TEXT;
+-- #rrggbb
+ALTER TABLE projects ADD COLUMN color TEXT;
+-- Emoji ou nom d'icône, au choix de l'interface
+ALTER TABLE projects ADD COLUMN icon TEXT;
+ALTER TABLE projects ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE projects ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+
+-- Les projets existants gardent l'ordre de création
+UPDATE projects SET position = (
+    SELECT COUNT(*) FROM projects p
+    WHERE p.created_at < projects.created_at
+       OR (p.created_at = projects.created_at AND p.rowid < projects.rowid)
+);
+
+CREATE INDEX idx_projects_position ON projects(position);
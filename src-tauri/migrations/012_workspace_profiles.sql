@@ -0,0 +1,31 @@
+-- Profils des espaces de travail, jusqu'ici gardés par l'interface dans le
+-- localStorage
+
+ALTER TABLE workspaces ADD COLUMN path TEXT;
+ALTER TABLE workspaces ADD COLUMN current_profile_id TEXT;
+
+CREATE TABLE workspace_profiles (
+    id TEXT PRIMARY KEY,
+    workspace_id TEXT NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    -- Surcharges du thème de l'application (AppThemeOptions), en JSON
+    theme TEXT NOT NULL DEFAULT '{}',
+    -- Réglages de l'éditeur en JSON ; les champs absents prennent leur
+    -- valeur par défaut
+    settings TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_workspace_profiles_workspace ON workspace_profiles(workspace_id, position);
+
+-- Chaque espace de travail a au moins un profil
+INSERT INTO workspace_profiles (id, workspace_id, name, created_at, updated_at)
+SELECT lower(hex(randomblob(16))), id, 'Default',
+       strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+FROM workspaces;
+
+UPDATE workspaces SET current_profile_id = (
+    SELECT p.id FROM workspace_profiles p WHERE p.workspace_id = workspaces.id
+);
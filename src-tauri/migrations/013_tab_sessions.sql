@@ -0,0 +1,30 @@
+-- Onglets liés à une note, un seul onglet actif par espace de travail et
+-- pile des onglets fermés
+
+-- Un onglet lié à une note affiche son contenu au lieu d'en garder une copie
+ALTER TABLE tabs ADD COLUMN note_id TEXT REFERENCES notes(id) ON DELETE SET NULL;
+
+-- Seul le premier onglet actif de chaque espace de travail le reste
+UPDATE tabs SET is_active = 0
+WHERE is_active AND id <> (
+    SELECT t.id FROM tabs t
+    WHERE t.workspace_id = tabs.workspace_id AND t.is_active
+    ORDER BY t.position, t.rowid LIMIT 1
+);
+
+CREATE UNIQUE INDEX idx_tabs_active ON tabs(workspace_id) WHERE is_active;
+
+CREATE TABLE closed_tabs (
+    tab_id TEXT NOT NULL,
+    workspace_id TEXT NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    content TEXT,
+    type TEXT NOT NULL,
+    note_id TEXT REFERENCES notes(id) ON DELETE SET NULL,
+    -- Position au moment de la fermeture, pour rouvrir l'onglet à sa place
+    position INTEGER NOT NULL,
+    closed_at TEXT NOT NULL
+);
+
+-- Le dernier onglet fermé est celui de plus grand rowid
+CREATE INDEX idx_closed_tabs_workspace ON closed_tabs(workspace_id);
@@ -0,0 +1,6 @@
+-- Espaces de travail stockés dans leur propre base SQLite
+
+-- Fichier de la base de l'espace de travail ; NULL s'il vit dans la base
+-- principale. Dans la base principale, la ligne ne sert alors que de
+-- registre : onglets, réglages, profils et sauvegardes sont dans ce fichier.
+ALTER TABLE workspaces ADD COLUMN database_path TEXT;
@@ -0,0 +1,40 @@
+-- Les clés étrangères sont appliquées depuis la corbeille : la purge compte
+-- sur les ON DELETE CASCADE pour supprimer tâches, étiquettes, révisions et
+-- liens des notes. Les bases écrites avant n'étaient pas vérifiées ; on
+-- répare ici les lignes orphelines pour que PRAGMA foreign_key_check passe.
+
+-- Les notes dont le projet n'existe plus sont gardées dans un projet à part
+INSERT OR IGNORE INTO projects (id, name, created_at, updated_at, position)
+SELECT 'recovered-notes', 'Notes récupérées',
+       strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), strftime('%Y-%m-%dT%H:%M:%SZ', 'now'),
+       (SELECT COALESCE(MAX(position) + 1, 0) FROM projects)
+WHERE EXISTS (
+    SELECT 1 FROM notes n WHERE NOT EXISTS (SELECT 1 FROM projects p WHERE p.id = n.project_id)
+);
+UPDATE notes SET project_id = 'recovered-notes'
+WHERE NOT EXISTS (SELECT 1 FROM projects p WHERE p.id = notes.project_id);
+
+-- Ce qui dépend d'une note disparue n'a plus de sens
+DELETE FROM tasks WHERE NOT EXISTS (SELECT 1 FROM notes n WHERE n.id = tasks.note_id);
+DELETE FROM note_tags WHERE NOT EXISTS (SELECT 1 FROM notes n WHERE n.id = note_tags.note_id)
+                         OR NOT EXISTS (SELECT 1 FROM tags t WHERE t.id = note_tags.tag_id);
+DELETE FROM note_revisions WHERE NOT EXISTS (SELECT 1 FROM notes n WHERE n.id = note_revisions.note_id);
+DELETE FROM note_links WHERE NOT EXISTS (SELECT 1 FROM notes n WHERE n.id = note_links.source_id);
+
+-- Références facultatives : on les oublie
+UPDATE tags SET parent_id = NULL
+WHERE parent_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM tags p WHERE p.id = tags.parent_id);
+UPDATE tabs SET note_id = NULL
+WHERE note_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM notes n WHERE n.id = tabs.note_id);
+UPDATE closed_tabs SET note_id = NULL
+WHERE note_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM notes n WHERE n.id = closed_tabs.note_id);
+
+-- Données d'un espace de travail supprimé
+DELETE FROM tabs WHERE NOT EXISTS (SELECT 1 FROM workspaces w WHERE w.id = tabs.workspace_id);
+DELETE FROM closed_tabs WHERE NOT EXISTS (SELECT 1 FROM workspaces w WHERE w.id = closed_tabs.workspace_id);
+DELETE FROM workspace_settings
+WHERE workspace_id IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM workspaces w WHERE w.id = workspace_settings.workspace_id);
+DELETE FROM workspace_profiles
+WHERE NOT EXISTS (SELECT 1 FROM workspaces w WHERE w.id = workspace_profiles.workspace_id);
+DELETE FROM backups WHERE NOT EXISTS (SELECT 1 FROM workspaces w WHERE w.id = backups.workspace_id);
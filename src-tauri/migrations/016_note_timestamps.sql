@@ -0,0 +1,4 @@
+-- updated_at des notes est renseigné par l'application au format RFC 3339 ;
+-- le déclencheur le remplaçait par CURRENT_TIMESTAMP après chaque mise à
+-- jour, y compris la mise à la corbeille et la réécriture des liens
+DROP TRIGGER IF EXISTS update_note_timestamp;
@@ -0,0 +1,157 @@
+-- Base de données de test figée au schéma v1, utilisée par les tests de migration
+
+-- Création des tables pour la base de données
+
+-- Table des espaces de travail
+CREATE TABLE IF NOT EXISTS workspaces (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    theme_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Table des paramètres des espaces de travail
+CREATE TABLE IF NOT EXISTS workspace_settings (
+    workspace_id TEXT PRIMARY KEY,
+    dark_mode BOOLEAN DEFAULT FALSE,
+    split_view BOOLEAN DEFAULT FALSE,
+    privacy_level TEXT DEFAULT 'high',
+    auto_save BOOLEAN DEFAULT TRUE,
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id)
+);
+
+-- Table des thèmes personnalisés
+CREATE TABLE IF NOT EXISTS custom_themes (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    type TEXT NOT NULL,
+    primary_color TEXT NOT NULL,
+    secondary_color TEXT NOT NULL,
+    background_color TEXT NOT NULL,
+    surface_color TEXT NOT NULL,
+    text_color TEXT NOT NULL,
+    accent_color TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Table des onglets
+CREATE TABLE IF NOT EXISTS tabs (
+    id TEXT PRIMARY KEY,
+    workspace_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT,
+    type TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    is_active BOOLEAN DEFAULT FALSE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id)
+);
+
+-- Table de sauvegarde
+CREATE TABLE IF NOT EXISTS backups (
+    id TEXT PRIMARY KEY,
+    workspace_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id)
+);
+
+-- Table des projets
+CREATE TABLE IF NOT EXISTS projects (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Table des notes
+CREATE TABLE IF NOT EXISTS notes (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    content TEXT,
+    project_id TEXT NOT NULL,
+    is_pinned BOOLEAN DEFAULT FALSE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+-- Table des tags
+CREATE TABLE IF NOT EXISTS tags (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Table des tâches
+CREATE TABLE IF NOT EXISTS tasks (
+    id TEXT PRIMARY KEY,
+    content TEXT NOT NULL,
+    completed BOOLEAN DEFAULT FALSE,
+    note_id TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+);
+
+-- Table de liaison notes-tags
+CREATE TABLE IF NOT EXISTS note_tags (
+    note_id TEXT NOT NULL,
+    tag_id TEXT NOT NULL,
+    PRIMARY KEY (note_id, tag_id),
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+-- Triggers pour la mise à jour automatique des timestamps
+CREATE TRIGGER IF NOT EXISTS update_workspace_timestamp 
+    AFTER UPDATE ON workspaces
+BEGIN
+    UPDATE workspaces SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_tab_timestamp
+    AFTER UPDATE ON tabs
+BEGIN
+    UPDATE tabs SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_project_timestamp 
+    AFTER UPDATE ON projects
+BEGIN
+    UPDATE projects SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_note_timestamp
+    AFTER UPDATE ON notes
+BEGIN
+    UPDATE notes SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_task_timestamp
+    AFTER UPDATE ON tasks
+BEGIN
+    UPDATE tasks SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- Données de test
+INSERT INTO workspaces (id, name, type, theme_id) VALUES ('ws-1', 'Personnel', 'personal', 'gruvbox');
+INSERT INTO workspace_settings (workspace_id, dark_mode, split_view, privacy_level, auto_save)
+    VALUES ('ws-1', TRUE, TRUE, 'high', TRUE);
+INSERT INTO tabs (id, workspace_id, title, content, type, position, is_active)
+    VALUES ('tab-1', 'ws-1', 'Welcome', '# Welcome', 'markdown', 0, TRUE);
+INSERT INTO backups (id, workspace_id, data) VALUES ('backup-1', 'ws-1', '{"tabs":[]}');
+INSERT INTO projects (id, name, created_at, updated_at)
+    VALUES ('project-1', 'Inbox', '2024-11-01T09:00:00+00:00', '2024-11-01T09:00:00+00:00');
+INSERT INTO notes (id, title, content, project_id, is_pinned, created_at, updated_at)
+    VALUES ('note-1', 'Welcome', '# Welcome\n\n- [ ] Try the editor', 'project-1', TRUE,
+            '2024-11-01T09:00:00+00:00', '2024-11-01T09:00:00+00:00');
+INSERT INTO tags (id, name, created_at) VALUES ('tag-1', 'getting-started', '2024-11-01T09:00:00+00:00');
+INSERT INTO note_tags (note_id, tag_id) VALUES ('note-1', 'tag-1');
+INSERT INTO tasks (id, content, completed, note_id, created_at, updated_at)
+    VALUES ('task-1', 'Try the editor', FALSE, 'note-1', '2024-11-01T09:00:00+00:00', '2024-11-01T09:00:00+00:00');
+
+PRAGMA user_version = 1;
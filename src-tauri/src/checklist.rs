@@ -0,0 +1,330 @@
+use std::ops::Range;
+
+/// A GFM task list item: `- [ ] text`, `* [x] text`, `1. [ ] text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkbox {
+    /// 1-based line number.
+    pub line: usize,
+    pub checked: bool,
+    pub text: String,
+    /// Byte offset of the character between the brackets.
+    pub mark: usize,
+    /// Byte range of the text after the brackets, trimmed.
+    pub text_span: Range<usize>,
+    /// Byte range of the whole line, newline included.
+    pub line_span: Range<usize>,
+}
+
+impl Checkbox {
+    pub fn hash(&self) -> String {
+        text_hash(&self.text)
+    }
+}
+
+/// Task list items of `content`, ignoring anything inside fenced code blocks.
+pub fn checkboxes(content: &str) -> Vec<Checkbox> {
+    let mut found = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len();
+
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = match fence {
+                Some(open) if open == marker => None,
+                None => Some(marker),
+                other => other,
+            };
+            continue;
+        }
+        if fence.is_some() {
+            continue;
+        }
+
+        let indent = line.len() - trimmed.len();
+        if let Some((checked, mark, text)) = parse_checkbox(trimmed.trim_end_matches(['\r', '\n'])) {
+            let text_start = line_start + indent + text.start;
+            found.push(Checkbox {
+                line: index + 1,
+                checked,
+                text: trimmed[text.clone()].to_string(),
+                mark: line_start + indent + mark,
+                text_span: text_start..text_start + text.len(),
+                line_span: line_start..offset,
+            });
+        }
+    }
+    found
+}
+
+/// Returns whether the box is ticked, the offset of the mark and the range
+/// of the trimmed text, relative to `line`.
+fn parse_checkbox(line: &str) -> Option<(bool, usize, Range<usize>)> {
+    let marker_len = if line.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = line.find(|c: char| !c.is_ascii_digit())?;
+        if digits == 0 || !line[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let rest = line[marker_len..].strip_prefix(' ')?;
+    let open = line.len() - rest.trim_start().len();
+
+    let checked = match line.get(open..open + 3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let after = &line[open + 3..];
+    if !(after.is_empty() || after.starts_with(' ')) {
+        return None;
+    }
+    let text_start = open + 3 + (after.len() - after.trim_start().len());
+    let text_end = open + 3 + after.trim_end().len();
+    Some((checked, open + 1, text_start..text_end.max(text_start)))
+}
+
+/// Identity of a checkbox's text that survives whitespace changes. FNV-1a,
+/// so it stays the same across builds.
+pub fn text_hash(text: &str) -> String {
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in normalized.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// A task as the sync sees it. `index` and `hash` are set for tasks bound to
+/// a checkbox: its position among the note's checkboxes and its text hash.
+#[derive(Debug, Clone)]
+pub struct SyncedTask {
+    pub id: String,
+    pub content: String,
+    pub completed: bool,
+    pub index: Option<usize>,
+    pub hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskChange {
+    /// Bind or re-bind an existing task, taking the checkbox's state.
+    Update { id: String, index: usize, hash: String, content: String, completed: bool },
+    Insert { index: usize, hash: String, content: String, completed: bool },
+    /// The task's checkbox is gone from the note.
+    Delete { id: String },
+}
+
+/// Matches the note's checkboxes to its tasks and lists the changes that
+/// bring the tasks in line. A checkbox keeps its task when its text is
+/// unchanged wherever it moved (an unchanged position wins between equal
+/// texts), then when its text changed but its position did not. Remaining
+/// checkboxes adopt unbound tasks with the same text, so tasks created
+/// before syncing existed are not duplicated, and otherwise become new
+/// tasks. Bound tasks left over lost their checkbox.
+pub fn plan_sync(checkboxes: &[Checkbox], tasks: &[SyncedTask]) -> Vec<TaskChange> {
+    let hashes: Vec<String> = checkboxes.iter().map(Checkbox::hash).collect();
+    let mut owner: Vec<Option<usize>> = vec![None; checkboxes.len()];
+    let mut taken = vec![false; tasks.len()];
+
+    let mut claim = |owner: &mut Vec<Option<usize>>, i: usize, pick: &dyn Fn(&SyncedTask) -> bool| {
+        if let Some(t) = (0..tasks.len()).find(|&t| !taken[t] && pick(&tasks[t])) {
+            taken[t] = true;
+            owner[i] = Some(t);
+        }
+    };
+
+    for (i, hash) in hashes.iter().enumerate() {
+        claim(&mut owner, i, &|t| t.index == Some(i) && t.hash.as_ref() == Some(hash));
+    }
+    for (i, hash) in hashes.iter().enumerate() {
+        if owner[i].is_none() {
+            claim(&mut owner, i, &|t| t.hash.as_ref() == Some(hash));
+        }
+    }
+    for i in 0..checkboxes.len() {
+        if owner[i].is_none() {
+            claim(&mut owner, i, &|t| t.hash.is_some() && t.index == Some(i));
+        }
+    }
+    for (i, hash) in hashes.iter().enumerate() {
+        if owner[i].is_none() {
+            claim(&mut owner, i, &|t| t.hash.is_none() && text_hash(&t.content) == *hash);
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (i, (checkbox, hash)) in checkboxes.iter().zip(hashes).enumerate() {
+        match owner[i].map(|t| &tasks[t]) {
+            Some(task)
+                if task.index == Some(i)
+                    && task.hash.as_ref() == Some(&hash)
+                    && task.content == checkbox.text
+                    && task.completed == checkbox.checked => {}
+            Some(task) => changes.push(TaskChange::Update {
+                id: task.id.clone(),
+                index: i,
+                hash,
+                content: checkbox.text.clone(),
+                completed: checkbox.checked,
+            }),
+            None => changes.push(TaskChange::Insert {
+                index: i,
+                hash,
+                content: checkbox.text.clone(),
+                completed: checkbox.checked,
+            }),
+        }
+    }
+    for (t, task) in tasks.iter().enumerate() {
+        if !taken[t] && task.hash.is_some() {
+            changes.push(TaskChange::Delete { id: task.id.clone() });
+        }
+    }
+    changes
+}
+
+/// Position of the checkbox bound at `index` with `hash`, or failing that of
+/// the only checkbox with that text.
+pub fn find(checkboxes: &[Checkbox], index: usize, hash: &str) -> Option<usize> {
+    if checkboxes.get(index).is_some_and(|c| c.hash() == hash) {
+        return Some(index);
+    }
+    let mut same_text = checkboxes.iter().enumerate().filter(|(_, c)| c.hash() == hash);
+    match (same_text.next(), same_text.next()) {
+        (Some((position, _)), None) => Some(position),
+        _ => None,
+    }
+}
+
+/// `content` with `checkbox` ticked or not and its text replaced. Line
+/// breaks in `text` would end the list item, so they become spaces.
+pub fn rewrite(content: &str, checkbox: &Checkbox, checked: bool, text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut out = String::with_capacity(content.len() + text.len());
+    out.push_str(&content[..checkbox.mark]);
+    out.push(if checked { 'x' } else { ' ' });
+    out.push_str(&content[checkbox.mark + 1..checkbox.text_span.start]);
+    if !text.is_empty() && !out.ends_with(' ') {
+        // "- [ ]" had nothing after it
+        out.push(' ');
+    }
+    out.push_str(&text);
+    out.push_str(&content[checkbox.text_span.end..]);
+    out
+}
+
+/// `content` without the line holding `checkbox`.
+pub fn remove(content: &str, checkbox: &Checkbox) -> String {
+    let mut out = content[..checkbox.line_span.start].to_string();
+    out.push_str(&content[checkbox.line_span.end..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boxes(content: &str) -> Vec<(usize, bool, String)> {
+        checkboxes(content).into_iter().map(|c| (c.line, c.checked, c.text)).collect()
+    }
+
+    fn task(id: &str, content: &str, index: Option<usize>) -> SyncedTask {
+        SyncedTask {
+            id: id.into(),
+            content: content.into(),
+            completed: false,
+            index,
+            hash: index.map(|_| text_hash(content)),
+        }
+    }
+
+    #[test]
+    fn finds_checkboxes_outside_code() {
+        let body = "- [ ] one\n  * [x] two \r\n3. [X] three\n```\n- [ ] code\n```\n- [] no\n- [ ]tight\n+ [ ]";
+        assert_eq!(
+            boxes(body),
+            vec![
+                (1, false, "one".into()),
+                (2, true, "two".into()),
+                (3, true, "three".into()),
+                (9, false, "".into()),
+            ]
+        );
+        let found = checkboxes(body);
+        assert_eq!(&body[found[1].mark..found[1].mark + 1], "x");
+        assert_eq!(&body[found[1].text_span.clone()], "two");
+        assert_eq!(&body[found[1].line_span.clone()], "  * [x] two \r\n");
+    }
+
+    #[test]
+    fn hashes_ignore_whitespace() {
+        assert_eq!(text_hash("Buy  milk "), text_hash("Buy milk"));
+        assert_ne!(text_hash("Buy milk"), text_hash("buy milk"));
+    }
+
+    #[test]
+    fn unchanged_note_needs_no_changes() {
+        let content = "- [ ] a\n- [ ] b";
+        let tasks = vec![task("1", "a", Some(0)), task("2", "b", Some(1))];
+        assert!(plan_sync(&checkboxes(content), &tasks).is_empty());
+    }
+
+    #[test]
+    fn follows_moves_edits_and_removals() {
+        let tasks = vec![
+            task("1", "a", Some(0)),
+            task("2", "b", Some(1)),
+            task("3", "c", Some(2)),
+            task("4", "d", Some(3)),
+        ];
+        // a and b swapped, b ticked, c edited in place, d removed
+        let content = "- [x] b\n- [ ] a\n- [ ] c!";
+        assert_eq!(
+            plan_sync(&checkboxes(content), &tasks),
+            vec![
+                TaskChange::Update { id: "2".into(), index: 0, hash: text_hash("b"), content: "b".into(), completed: true },
+                TaskChange::Update { id: "1".into(), index: 1, hash: text_hash("a"), content: "a".into(), completed: false },
+                TaskChange::Update { id: "3".into(), index: 2, hash: text_hash("c!"), content: "c!".into(), completed: false },
+                TaskChange::Delete { id: "4".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn adopts_unbound_tasks_and_leaves_the_rest() {
+        let tasks = vec![task("1", "write docs", None), task("2", "standalone", None)];
+        let changes = plan_sync(&checkboxes("- [ ] write docs\n- [x] ship"), &tasks);
+        assert_eq!(
+            changes,
+            vec![
+                TaskChange::Update {
+                    id: "1".into(),
+                    index: 0,
+                    hash: text_hash("write docs"),
+                    content: "write docs".into(),
+                    completed: false
+                },
+                TaskChange::Insert { index: 1, hash: text_hash("ship"), content: "ship".into(), completed: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn rewrites_and_removes_checkboxes() {
+        let content = "# List\n- [ ] old text\n- [ ]\nend";
+        let found = checkboxes(content);
+        assert_eq!(rewrite(content, &found[0], true, "new\ntext"), "# List\n- [x] new text\n- [ ]\nend");
+        assert_eq!(rewrite(content, &found[1], false, "filled"), "# List\n- [ ] old text\n- [ ] filled\nend");
+        assert_eq!(remove(content, &found[0]), "# List\n- [ ]\nend");
+
+        assert_eq!(find(&found, 5, &text_hash("old text")), Some(0));
+        assert!(find(&found, 0, &text_hash("missing")).is_none());
+    }
+}
@@ -1,57 +1,350 @@
-use crate::db::{DbState, Tab, Workspace, WorkspaceSettings, CustomTheme, Note, Project, Tag, Task};
-use tauri::State;
+use crate::db::{DbState, Tab, ClosedTab, Workspace, WorkspaceProfile, WorkspaceSettings, CustomTheme, Note, Project, ProjectSummary, Tag, Task, TaskListItem, SearchHit, NoteRevision, NoteRevisionSummary, TrashItem, TrashKind, Backup, BackupInfo, NoteLink, RenamePreview};
+use crate::diff::DiffLine;
+use crate::error::AppError;
+use crate::graph::{GraphData, GraphQuery};
+use crate::ics::{IcsExport, IcsExportOptions};
+use crate::markdown::{ExportManifest, ImportOptions, ImportReport};
+use crate::profiles::LegacyImportReport;
+use crate::scheduler::{self, BackupRun, BackupSchedule, BackupScheduler, BackupTrigger};
+use crate::snapshot::{AutoBackupSettings, SnapshotInfo, SnapshotPolicy};
+use crate::tasks::TaskQuery;
+use crate::theme_import::EditorThemeImport;
+use crate::themes::{SavedTheme, ThemeExport, ThemeImport};
+use std::path::PathBuf;
+use tauri::{AppHandle, Builder, Runtime, State};
 
+/// Defines `register_commands` and, for the tests, `COMMAND_NAMES` from one
+/// list of commands.
+macro_rules! commands {
+    ($($command:ident),* $(,)?) => {
+        /// Registers every command exposed to the frontend. Shared by `main`
+        /// and the tests so the two can't drift apart.
+        pub fn register_commands<R: Runtime>(builder: Builder<R>) -> Builder<R> {
+            builder.invoke_handler(tauri::generate_handler![$($command),*])
+        }
+
+        /// Names of the commands `register_commands` registers.
+        #[cfg(test)]
+        const COMMAND_NAMES: &[&str] = &[$(stringify!($command)),*];
+    };
+}
+
+commands![
+    init_database,
+    create_workspace,
+    get_workspaces,
+    rename_workspace,
+    delete_workspace,
+    open_workspace_database,
+    switch_workspace,
+    get_active_workspace,
+    save_workspace_settings,
+    create_workspace_profile,
+    get_workspace_profiles,
+    update_workspace_profile,
+    duplicate_workspace_profile,
+    delete_workspace_profile,
+    switch_workspace_profile,
+    migrate_local_workspaces,
+    create_custom_theme,
+    get_custom_themes,
+    get_custom_theme,
+    update_custom_theme,
+    delete_custom_theme,
+    export_custom_themes,
+    import_custom_themes,
+    import_editor_theme,
+    create_tab,
+    get_workspace_tabs,
+    create_backup,
+    create_workspace_backup,
+    list_backups,
+    get_backup,
+    restore_backup,
+    delete_backup,
+    prune_backups,
+    update_tab,
+    reorder_tabs,
+    activate_tab,
+    update_tab_active_state,
+    delete_tab,
+    get_closed_tabs,
+    reopen_closed_tab,
+    create_note,
+    get_note,
+    get_notes,
+    get_notes_by_tag,
+    update_note,
+    delete_note,
+    get_note_revisions,
+    get_note_revision,
+    diff_note_revisions,
+    restore_note_revision,
+    create_project,
+    get_project,
+    get_projects,
+    update_project,
+    reorder_projects,
+    archive_project,
+    unarchive_project,
+    delete_project,
+    create_tag,
+    get_tags,
+    update_tag,
+    delete_tag,
+    rename_tag,
+    move_tag,
+    merge_tags,
+    create_task,
+    get_tasks,
+    get_all_tasks,
+    query_tasks,
+    get_task_occurrences,
+    export_tasks_ics,
+    update_task,
+    delete_task,
+    add_tag_to_note,
+    remove_tag_from_note,
+    get_note_tags,
+    search_notes,
+    get_trash,
+    restore_from_trash,
+    empty_trash,
+    get_trash_retention_days,
+    set_trash_retention_days,
+    create_snapshot,
+    list_snapshots,
+    verify_snapshot,
+    delete_snapshot,
+    get_snapshot_policy,
+    set_snapshot_policy,
+    get_backup_schedule,
+    set_backup_schedule,
+    run_backup_now,
+    export_project,
+    import_markdown,
+    get_outgoing_links,
+    get_backlinks,
+    get_unresolved_links,
+    get_orphan_notes,
+    preview_note_rename,
+    get_graph,
+];
+
+/// The database is opened and migrated in `main`; this only reports the
+/// schema version so the frontend can confirm the backend is ready.
+#[tauri::command]
+pub async fn init_database(
+    db: State<'_, DbState>,
+) -> Result<u32, AppError> {
+    db.schema_version()
+}
+
+/// With `database_dir`, the workspace lives in its own database there.
 #[tauri::command]
 pub async fn create_workspace(
     workspace: Workspace,
+    database_dir: Option<PathBuf>,
     db: State<'_, DbState>,
-) -> Result<(), String> {
-    db.create_workspace(&workspace)
-        .map_err(|e| e.to_string())
+) -> Result<(), AppError> {
+    db.create_workspace(&workspace, database_dir.as_deref())
 }
 
 #[tauri::command]
 pub async fn get_workspaces(
     db: State<'_, DbState>,
-) -> Result<Vec<Workspace>, String> {
+) -> Result<Vec<Workspace>, AppError> {
     db.get_workspaces()
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_workspace(
+    id: String,
+    name: String,
+    db: State<'_, DbState>,
+) -> Result<Workspace, AppError> {
+    db.rename_workspace(&id, &name)
+}
+
+#[tauri::command]
+pub async fn delete_workspace(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<Option<PathBuf>, AppError> {
+    db.delete_workspace(&id)
+}
+
+#[tauri::command]
+pub async fn open_workspace_database(
+    dir: PathBuf,
+    db: State<'_, DbState>,
+) -> Result<Workspace, AppError> {
+    db.open_workspace_database(&dir)
+}
+
+#[tauri::command]
+pub async fn switch_workspace(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<Workspace, AppError> {
+    db.switch_workspace(&id)
+}
+
+#[tauri::command]
+pub async fn get_active_workspace(
+    db: State<'_, DbState>,
+) -> Result<Option<Workspace>, AppError> {
+    db.get_active_workspace()
 }
 
 #[tauri::command]
 pub async fn save_workspace_settings(
     settings: WorkspaceSettings,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.save_workspace_settings(&settings)
-        .map_err(|e| e.to_string())
+}
+
+// Workspace Profiles
+#[tauri::command]
+pub async fn create_workspace_profile(
+    profile: WorkspaceProfile,
+    db: State<'_, DbState>,
+) -> Result<WorkspaceProfile, AppError> {
+    db.create_workspace_profile(&profile)
+}
+
+#[tauri::command]
+pub async fn get_workspace_profiles(
+    workspace_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<WorkspaceProfile>, AppError> {
+    db.get_workspace_profiles(&workspace_id)
+}
+
+#[tauri::command]
+pub async fn update_workspace_profile(
+    profile: WorkspaceProfile,
+    db: State<'_, DbState>,
+) -> Result<WorkspaceProfile, AppError> {
+    db.update_workspace_profile(&profile)
+}
+
+#[tauri::command]
+pub async fn duplicate_workspace_profile(
+    id: String,
+    name: String,
+    db: State<'_, DbState>,
+) -> Result<WorkspaceProfile, AppError> {
+    db.duplicate_workspace_profile(&id, &name)
+}
+
+#[tauri::command]
+pub async fn delete_workspace_profile(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.delete_workspace_profile(&id)
+}
+
+#[tauri::command]
+pub async fn switch_workspace_profile(
+    workspace_id: String,
+    profile_id: String,
+    db: State<'_, DbState>,
+) -> Result<WorkspaceProfile, AppError> {
+    db.switch_workspace_profile(&workspace_id, &profile_id)
+}
+
+/// `json` is the value of the `markdownEditor.workspaces` localStorage key.
+#[tauri::command]
+pub async fn migrate_local_workspaces(
+    json: String,
+    db: State<'_, DbState>,
+) -> Result<LegacyImportReport, AppError> {
+    db.migrate_local_workspaces(&json)
 }
 
 #[tauri::command]
 pub async fn create_custom_theme(
     theme: CustomTheme,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<SavedTheme, AppError> {
     db.create_custom_theme(&theme)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_custom_themes(
+    db: State<'_, DbState>,
+) -> Result<Vec<CustomTheme>, AppError> {
+    db.get_custom_themes()
+}
+
+#[tauri::command]
+pub async fn get_custom_theme(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<CustomTheme, AppError> {
+    db.get_custom_theme(&id)
+}
+
+#[tauri::command]
+pub async fn update_custom_theme(
+    theme: CustomTheme,
+    db: State<'_, DbState>,
+) -> Result<SavedTheme, AppError> {
+    db.update_custom_theme(&theme)
+}
+
+#[tauri::command]
+pub async fn delete_custom_theme(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.delete_custom_theme(&id)
+}
+
+/// Exports the themes in `ids`, or all of them when omitted.
+#[tauri::command]
+pub async fn export_custom_themes(
+    path: PathBuf,
+    ids: Option<Vec<String>>,
+    db: State<'_, DbState>,
+) -> Result<ThemeExport, AppError> {
+    db.export_custom_themes(&path, ids.as_deref())
+}
+
+#[tauri::command]
+pub async fn import_custom_themes(
+    path: PathBuf,
+    db: State<'_, DbState>,
+) -> Result<ThemeImport, AppError> {
+    db.import_custom_themes(&path)
+}
+
+/// `path` is a VS Code theme (`.json`) or a Base16 scheme (`.yaml`).
+#[tauri::command]
+pub async fn import_editor_theme(
+    path: PathBuf,
+    db: State<'_, DbState>,
+) -> Result<EditorThemeImport, AppError> {
+    db.import_editor_theme(&path)
 }
 
 #[tauri::command]
 pub async fn create_tab(
     tab: Tab,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.create_tab(&tab)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_workspace_tabs(
     workspace_id: String,
     db: State<'_, DbState>,
-) -> Result<Vec<Tab>, String> {
+) -> Result<Vec<Tab>, AppError> {
     db.get_workspace_tabs(&workspace_id)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -59,9 +352,82 @@ pub async fn create_backup(
     workspace_id: String,
     data: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.create_backup(&workspace_id, &data)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_workspace_backup(
+    workspace_id: String,
+    db: State<'_, DbState>,
+) -> Result<BackupInfo, AppError> {
+    db.create_workspace_backup(&workspace_id)
+}
+
+#[tauri::command]
+pub async fn list_backups(
+    workspace_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<BackupInfo>, AppError> {
+    db.list_backups(&workspace_id)
+}
+
+#[tauri::command]
+pub async fn get_backup(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<Backup, AppError> {
+    db.get_backup(&id)
+}
+
+#[tauri::command]
+pub async fn restore_backup(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<BackupInfo, AppError> {
+    db.restore_backup(&id)
+}
+
+#[tauri::command]
+pub async fn delete_backup(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.delete_backup(&id)
+}
+
+#[tauri::command]
+pub async fn prune_backups(
+    workspace_id: String,
+    keep: u32,
+    db: State<'_, DbState>,
+) -> Result<usize, AppError> {
+    db.prune_backups(&workspace_id, keep)
+}
+
+#[tauri::command]
+pub async fn update_tab(
+    tab: Tab,
+    db: State<'_, DbState>,
+) -> Result<Tab, AppError> {
+    db.update_tab(&tab)
+}
+
+#[tauri::command]
+pub async fn reorder_tabs(
+    workspace_id: String,
+    ids: Vec<String>,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.reorder_tabs(&workspace_id, &ids)
+}
+
+#[tauri::command]
+pub async fn activate_tab(
+    tab_id: String,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.activate_tab(&tab_id)
 }
 
 #[tauri::command]
@@ -69,18 +435,32 @@ pub async fn update_tab_active_state(
     tab_id: String,
     is_active: bool,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.update_tab_active_state(&tab_id, is_active)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn delete_tab(
     tab_id: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.delete_tab(&tab_id)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_closed_tabs(
+    workspace_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<ClosedTab>, AppError> {
+    db.get_closed_tabs(&workspace_id)
+}
+
+#[tauri::command]
+pub async fn reopen_closed_tab(
+    workspace_id: String,
+    db: State<'_, DbState>,
+) -> Result<Option<Tab>, AppError> {
+    db.reopen_closed_tab(&workspace_id)
 }
 
 // Notes
@@ -88,36 +468,83 @@ pub async fn delete_tab(
 pub async fn create_note(
     note: Note,
     db: State<'_, DbState>,
-) -> Result<Note, String> {
+) -> Result<Note, AppError> {
     db.create_note(&note)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_note(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<Note, AppError> {
+    db.get_note(&id)
 }
 
 #[tauri::command]
 pub async fn get_notes(
     project_id: String,
     db: State<'_, DbState>,
-) -> Result<Vec<Note>, String> {
+) -> Result<Vec<Note>, AppError> {
     db.get_notes(&project_id)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_notes_by_tag(
+    tag_id: String,
+    include_descendants: Option<bool>,
+    db: State<'_, DbState>,
+) -> Result<Vec<Note>, AppError> {
+    db.get_notes_by_tag(&tag_id, include_descendants.unwrap_or(false))
 }
 
 #[tauri::command]
 pub async fn update_note(
     note: Note,
     db: State<'_, DbState>,
-) -> Result<Note, String> {
+) -> Result<Note, AppError> {
     db.update_note(&note)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn delete_note(
     id: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.delete_note(&id)
-        .map_err(|e| e.to_string())
+}
+
+// Note Revisions
+#[tauri::command]
+pub async fn get_note_revisions(
+    note_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<NoteRevisionSummary>, AppError> {
+    db.get_note_revisions(&note_id)
+}
+
+#[tauri::command]
+pub async fn get_note_revision(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<NoteRevision, AppError> {
+    db.get_note_revision(&id)
+}
+
+#[tauri::command]
+pub async fn diff_note_revisions(
+    from_id: String,
+    to_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<DiffLine>, AppError> {
+    db.diff_note_revisions(&from_id, &to_id)
+}
+
+#[tauri::command]
+pub async fn restore_note_revision(
+    revision_id: String,
+    db: State<'_, DbState>,
+) -> Result<Note, AppError> {
+    db.restore_note_revision(&revision_id)
 }
 
 // Projects
@@ -125,35 +552,64 @@ pub async fn delete_note(
 pub async fn create_project(
     project: Project,
     db: State<'_, DbState>,
-) -> Result<Project, String> {
+) -> Result<Project, AppError> {
     db.create_project(&project)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_project(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<Project, AppError> {
+    db.get_project(&id)
 }
 
 #[tauri::command]
 pub async fn get_projects(
+    include_archived: Option<bool>,
     db: State<'_, DbState>,
-) -> Result<Vec<Project>, String> {
-    db.get_projects()
-        .map_err(|e| e.to_string())
+) -> Result<Vec<ProjectSummary>, AppError> {
+    db.get_projects(include_archived.unwrap_or(false))
 }
 
 #[tauri::command]
 pub async fn update_project(
     project: Project,
     db: State<'_, DbState>,
-) -> Result<Project, String> {
+) -> Result<Project, AppError> {
     db.update_project(&project)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reorder_projects(
+    ids: Vec<String>,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.reorder_projects(&ids)
+}
+
+#[tauri::command]
+pub async fn archive_project(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<Project, AppError> {
+    db.archive_project(&id)
+}
+
+#[tauri::command]
+pub async fn unarchive_project(
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<Project, AppError> {
+    db.unarchive_project(&id)
 }
 
 #[tauri::command]
 pub async fn delete_project(
     id: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.delete_project(&id)
-        .map_err(|e| e.to_string())
 }
 
 // Tags
@@ -161,26 +617,58 @@ pub async fn delete_project(
 pub async fn create_tag(
     tag: Tag,
     db: State<'_, DbState>,
-) -> Result<Tag, String> {
+) -> Result<Tag, AppError> {
     db.create_tag(&tag)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_tags(
     db: State<'_, DbState>,
-) -> Result<Vec<Tag>, String> {
+) -> Result<Vec<Tag>, AppError> {
     db.get_tags()
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_tag(
+    tag: Tag,
+    db: State<'_, DbState>,
+) -> Result<Tag, AppError> {
+    db.update_tag(&tag)
 }
 
 #[tauri::command]
 pub async fn delete_tag(
     id: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.delete_tag(&id)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_tag(
+    id: String,
+    name: String,
+    db: State<'_, DbState>,
+) -> Result<Tag, AppError> {
+    db.rename_tag(&id, &name)
+}
+
+#[tauri::command]
+pub async fn move_tag(
+    id: String,
+    parent_id: Option<String>,
+    db: State<'_, DbState>,
+) -> Result<Tag, AppError> {
+    db.move_tag(&id, parent_id.as_deref())
+}
+
+#[tauri::command]
+pub async fn merge_tags(
+    source_id: String,
+    target_id: String,
+    db: State<'_, DbState>,
+) -> Result<Tag, AppError> {
+    db.merge_tags(&source_id, &target_id)
 }
 
 // Tasks
@@ -188,36 +676,55 @@ pub async fn delete_tag(
 pub async fn create_task(
     task: Task,
     db: State<'_, DbState>,
-) -> Result<Task, String> {
+) -> Result<Task, AppError> {
     db.create_task(&task)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_tasks(
     note_id: String,
     db: State<'_, DbState>,
-) -> Result<Vec<Task>, String> {
+) -> Result<Vec<Task>, AppError> {
     db.get_tasks(&note_id)
-        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_all_tasks(
+    db: State<'_, DbState>,
+) -> Result<Vec<Task>, AppError> {
+    db.get_all_tasks()
+}
+
+#[tauri::command]
+pub async fn query_tasks(
+    query: TaskQuery,
+    db: State<'_, DbState>,
+) -> Result<Vec<TaskListItem>, AppError> {
+    db.query_tasks(&query)
+}
+
+#[tauri::command]
+pub async fn get_task_occurrences(
+    task_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<Task>, AppError> {
+    db.get_task_occurrences(&task_id)
 }
 
 #[tauri::command]
 pub async fn update_task(
     task: Task,
     db: State<'_, DbState>,
-) -> Result<Task, String> {
+) -> Result<Task, AppError> {
     db.update_task(&task)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn delete_task(
     id: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.delete_task(&id)
-        .map_err(|e| e.to_string())
 }
 
 // Note Tags
@@ -226,9 +733,8 @@ pub async fn add_tag_to_note(
     note_id: String,
     tag_id: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.add_tag_to_note(&note_id, &tag_id)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -236,16 +742,549 @@ pub async fn remove_tag_from_note(
     note_id: String,
     tag_id: String,
     db: State<'_, DbState>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     db.remove_tag_from_note(&note_id, &tag_id)
-        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_note_tags(
     note_id: String,
     db: State<'_, DbState>,
-) -> Result<Vec<Tag>, String> {
+) -> Result<Vec<Tag>, AppError> {
     db.get_note_tags(&note_id)
-        .map_err(|e| e.to_string())
+}
+
+// Search
+#[tauri::command]
+pub async fn search_notes(
+    query: String,
+    project_id: Option<String>,
+    tag_id: Option<String>,
+    limit: Option<u32>,
+    db: State<'_, DbState>,
+) -> Result<Vec<SearchHit>, AppError> {
+    db.search_notes(&query, project_id.as_deref(), tag_id.as_deref(), limit.unwrap_or(50))
+}
+
+// Trash
+#[tauri::command]
+pub async fn get_trash(
+    db: State<'_, DbState>,
+) -> Result<Vec<TrashItem>, AppError> {
+    db.get_trash()
+}
+
+#[tauri::command]
+pub async fn restore_from_trash(
+    kind: TrashKind,
+    id: String,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.restore_from_trash(kind, &id)
+}
+
+#[tauri::command]
+pub async fn empty_trash(
+    db: State<'_, DbState>,
+) -> Result<usize, AppError> {
+    db.empty_trash()
+}
+
+#[tauri::command]
+pub async fn get_trash_retention_days(
+    db: State<'_, DbState>,
+) -> Result<u32, AppError> {
+    db.get_trash_retention_days()
+}
+
+#[tauri::command]
+pub async fn set_trash_retention_days(
+    days: u32,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.set_trash_retention_days(days)
+}
+
+// Snapshots
+#[tauri::command]
+pub async fn create_snapshot(
+    db: State<'_, DbState>,
+) -> Result<SnapshotInfo, AppError> {
+    db.create_snapshot()
+}
+
+#[tauri::command]
+pub async fn list_snapshots(
+    db: State<'_, DbState>,
+) -> Result<Vec<SnapshotInfo>, AppError> {
+    db.list_snapshots()
+}
+
+#[tauri::command]
+pub async fn verify_snapshot(
+    file_name: String,
+    db: State<'_, DbState>,
+) -> Result<SnapshotInfo, AppError> {
+    db.verify_snapshot(&file_name)
+}
+
+#[tauri::command]
+pub async fn delete_snapshot(
+    file_name: String,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.delete_snapshot(&file_name)
+}
+
+#[tauri::command]
+pub async fn get_snapshot_policy(
+    db: State<'_, DbState>,
+) -> Result<SnapshotPolicy, AppError> {
+    db.get_snapshot_policy()
+}
+
+#[tauri::command]
+pub async fn set_snapshot_policy(
+    policy: SnapshotPolicy,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    db.set_snapshot_policy(&policy)
+}
+
+// Backup Scheduler
+#[tauri::command]
+pub async fn get_backup_schedule(
+    db: State<'_, DbState>,
+    scheduler: State<'_, BackupScheduler>,
+) -> Result<BackupSchedule, AppError> {
+    Ok(scheduler.schedule(db.get_auto_backup_settings()?))
+}
+
+#[tauri::command]
+pub async fn set_backup_schedule(
+    settings: AutoBackupSettings,
+    db: State<'_, DbState>,
+    scheduler: State<'_, BackupScheduler>,
+) -> Result<(), AppError> {
+    db.set_auto_backup_settings(&settings)?;
+    scheduler.reschedule();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn run_backup_now<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<BackupRun, AppError> {
+    Ok(scheduler::run(&app, BackupTrigger::Manual))
+}
+
+// Markdown Export
+#[tauri::command]
+pub async fn export_project(
+    project_id: String,
+    directory: PathBuf,
+    db: State<'_, DbState>,
+) -> Result<ExportManifest, AppError> {
+    db.export_project(&project_id, &directory)
+}
+
+// Calendar Export
+#[tauri::command]
+pub async fn export_tasks_ics(
+    path: PathBuf,
+    options: IcsExportOptions,
+    db: State<'_, DbState>,
+) -> Result<IcsExport, AppError> {
+    db.export_tasks_ics(&path, &options)
+}
+
+// Markdown Import
+#[tauri::command]
+pub async fn import_markdown(
+    directory: PathBuf,
+    options: ImportOptions,
+    db: State<'_, DbState>,
+) -> Result<ImportReport, AppError> {
+    db.import_markdown(&directory, &options)
+}
+
+// Links
+#[tauri::command]
+pub async fn get_outgoing_links(
+    note_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<NoteLink>, AppError> {
+    db.get_outgoing_links(&note_id)
+}
+
+#[tauri::command]
+pub async fn get_backlinks(
+    note_id: String,
+    db: State<'_, DbState>,
+) -> Result<Vec<NoteLink>, AppError> {
+    db.get_backlinks(&note_id)
+}
+
+#[tauri::command]
+pub async fn get_unresolved_links(
+    project_id: Option<String>,
+    db: State<'_, DbState>,
+) -> Result<Vec<NoteLink>, AppError> {
+    db.get_unresolved_links(project_id.as_deref())
+}
+
+#[tauri::command]
+pub async fn get_orphan_notes(
+    project_id: Option<String>,
+    db: State<'_, DbState>,
+) -> Result<Vec<Note>, AppError> {
+    db.get_orphan_notes(project_id.as_deref())
+}
+
+#[tauri::command]
+pub async fn preview_note_rename(
+    note_id: String,
+    new_title: String,
+    db: State<'_, DbState>,
+) -> Result<RenamePreview, AppError> {
+    db.preview_note_rename(&note_id, &new_title)
+}
+
+// Graph
+#[tauri::command]
+pub async fn get_graph(
+    query: GraphQuery,
+    db: State<'_, DbState>,
+) -> Result<GraphData, AppError> {
+    db.get_graph(&query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::cell::RefCell;
+    use std::collections::BTreeSet;
+    use tauri::api::ipc::CallbackFn;
+    use tauri::test::{get_ipc_response, mock_builder, mock_context, noop_assets, MockRuntime, INVOKE_KEY};
+    use tauri::{InvokePayload, Manager, Window};
+    use tempfile::TempDir;
+
+    /// A mock app over a database in `dir`, which is removed when the app
+    /// is dropped. Files the tests write go there too.
+    struct MockApp {
+        window: Window<MockRuntime>,
+        dir: TempDir,
+        invoked: RefCell<BTreeSet<String>>,
+    }
+
+    fn mock_app() -> MockApp {
+        let dir = tempfile::tempdir().expect("failed to create test directory");
+        let db = DbState::new(dir.path().join("data")).expect("failed to open test database");
+        let builder = mock_builder().manage(db).manage(BackupScheduler::default());
+        let app = register_commands(builder)
+            .build(mock_context(noop_assets()))
+            .expect("failed to build mock app");
+        MockApp {
+            window: app.get_window("main").unwrap(),
+            dir,
+            invoked: RefCell::default(),
+        }
+    }
+
+    fn invoke(app: &MockApp, cmd: &str, args: Value) -> Result<Value, Value> {
+        app.invoked.borrow_mut().insert(cmd.to_string());
+        get_ipc_response(
+            &app.window,
+            InvokePayload {
+                cmd: cmd.into(),
+                tauri_module: None,
+                callback: CallbackFn(0),
+                error: CallbackFn(1),
+                inner: args,
+                invoke_key: Some(INVOKE_KEY.into()),
+            },
+        )
+    }
+
+    fn ok(app: &MockApp, cmd: &str, args: Value) -> Value {
+        invoke(app, cmd, args).unwrap_or_else(|e| panic!("{} failed: {}", cmd, e))
+    }
+
+    #[test]
+    fn every_command_is_invocable() {
+        let w = mock_app();
+
+        assert!(ok(&w, "init_database", json!({})).as_u64().unwrap() > 0);
+
+        // Workspaces and tabs
+        ok(&w, "create_workspace", json!({ "workspace": {
+            "id": "ws-1", "name": "Personal", "type_": "personal", "theme_id": "gruvbox"
+        }}));
+        assert_eq!(ok(&w, "get_workspaces", json!({})).as_array().unwrap().len(), 1);
+        ok(&w, "save_workspace_settings", json!({ "settings": {
+            "workspace_id": "ws-1", "dark_mode": true, "split_view": false,
+            "privacy_level": "high", "auto_save": true
+        }}));
+        let default_profile = ok(&w, "get_workspace_profiles", json!({ "workspaceId": "ws-1" }))[0].clone();
+        let writing = ok(&w, "duplicate_workspace_profile", json!({ "id": default_profile["id"], "name": "Writing" }));
+        let mut edited = writing.clone();
+        edited["settings"]["tab_size"] = json!(4);
+        assert_eq!(ok(&w, "update_workspace_profile", json!({ "profile": edited }))["settings"]["tab_size"], json!(4));
+        ok(&w, "switch_workspace_profile", json!({ "workspaceId": "ws-1", "profileId": writing["id"] }));
+        assert_eq!(ok(&w, "get_workspaces", json!({}))[0]["current_profile_id"], writing["id"]);
+        ok(&w, "create_workspace_profile", json!({ "profile": {
+            "id": "profile-1", "workspace_id": "ws-1", "name": "Focus", "theme": { "isDarkMode": true }
+        }}));
+        ok(&w, "delete_workspace_profile", json!({ "id": "profile-1" }));
+        let legacy = json!([{ "id": "ws-legacy", "name": "Old", "path": "", "currentProfileId": "p", "profiles": [] }]);
+        let report = ok(&w, "migrate_local_workspaces", json!({ "json": legacy.to_string() }));
+        assert_eq!(report["workspaces"], json!(1));
+        ok(&w, "create_custom_theme", json!({ "theme": {
+            "id": "theme-1", "name": "Dark", "type_": "dark",
+            "primary_color": "#fabd2f", "secondary_color": "#83a598",
+            "background_color": "#282828", "surface_color": "#3c3836",
+            "text_color": "#ebdbb2", "accent_color": "#fe8019"
+        }}));
+        let mut faded = ok(&w, "get_custom_theme", json!({ "id": "theme-1" }));
+        faded["text_color"] = json!("rgb(80, 73, 69)");
+        let saved = ok(&w, "update_custom_theme", json!({ "theme": faded }));
+        assert_eq!(saved["warnings"][0]["background"], "background_color");
+        let themes_path = w.dir.path().join("themes.json");
+        assert_eq!(ok(&w, "export_custom_themes", json!({ "path": themes_path }))["themes"], json!(1));
+        let imported = ok(&w, "import_custom_themes", json!({ "path": themes_path }));
+        assert_ne!(imported["themes"][0]["id"], "theme-1");
+        assert_eq!(ok(&w, "get_custom_themes", json!({})).as_array().unwrap().len(), 2);
+        ok(&w, "delete_custom_theme", json!({ "id": imported["themes"][0]["id"] }));
+        let vs_code_path = w.dir.path().join("night.json");
+        std::fs::write(&vs_code_path, r##"{ "name": "Night", "colors": { "editor.background": "#101010" } }"##).unwrap();
+        let night = ok(&w, "import_editor_theme", json!({ "path": vs_code_path }));
+        assert_eq!(night["format"], "vs_code");
+        assert_eq!(night["sources"][0]["key"], "editor.background");
+        ok(&w, "delete_custom_theme", json!({ "id": night["id"] }));
+        ok(&w, "create_tab", json!({ "tab": {
+            "id": "tab-1", "workspace_id": "ws-1", "title": "Draft", "content": null,
+            "type_": "markdown", "position": 0, "is_active": false
+        }}));
+        ok(&w, "update_tab_active_state", json!({ "tabId": "tab-1", "isActive": true }));
+        let tabs = ok(&w, "get_workspace_tabs", json!({ "workspaceId": "ws-1" }));
+        assert_eq!(tabs[0]["is_active"], json!(true));
+        let edited = ok(&w, "update_tab", json!({ "tab": {
+            "id": "tab-1", "workspace_id": "ws-1", "title": "Notes", "content": "draft",
+            "type_": "markdown", "position": 0, "is_active": true
+        }}));
+        assert_eq!(edited["content"], "draft");
+        ok(&w, "reorder_tabs", json!({ "workspaceId": "ws-1", "ids": ["tab-1"] }));
+        ok(&w, "activate_tab", json!({ "tabId": "tab-1" }));
+        ok(&w, "delete_tab", json!({ "tabId": "tab-1" }));
+        assert_eq!(ok(&w, "get_closed_tabs", json!({ "workspaceId": "ws-1" }))[0]["title"], "Notes");
+        assert_eq!(ok(&w, "reopen_closed_tab", json!({ "workspaceId": "ws-1" }))["id"], "tab-1");
+        ok(&w, "delete_tab", json!({ "tabId": "tab-1" }));
+        ok(&w, "create_backup", json!({ "workspaceId": "ws-1", "data": "{}" }));
+        let snapshot = ok(&w, "create_workspace_backup", json!({ "workspaceId": "ws-1" }));
+        assert_eq!(ok(&w, "list_backups", json!({ "workspaceId": "ws-1" })).as_array().unwrap().len(), 2);
+        assert!(ok(&w, "get_backup", json!({ "id": snapshot["id"] }))["snapshot"].is_object());
+        let safety = ok(&w, "restore_backup", json!({ "id": snapshot["id"] }));
+        assert_eq!(safety["kind"], "pre-restore");
+        ok(&w, "delete_backup", json!({ "id": safety["id"] }));
+        assert_eq!(ok(&w, "prune_backups", json!({ "workspaceId": "ws-1", "keep": 1 })), json!(1));
+
+        // A workspace in its own database
+        let own_dir = w.dir.path().join("client");
+        ok(&w, "create_workspace", json!({ "workspace": {
+            "id": "ws-2", "name": "Client", "type_": "work", "theme_id": "light"
+        }, "databaseDir": own_dir }));
+        assert_eq!(ok(&w, "switch_workspace", json!({ "id": "ws-2" }))["name"], "Client");
+        assert_eq!(ok(&w, "get_active_workspace", json!({}))["id"], "ws-2");
+        assert_eq!(ok(&w, "rename_workspace", json!({ "id": "ws-2", "name": "Clients" }))["name"], "Clients");
+        let kept = ok(&w, "delete_workspace", json!({ "id": "ws-2" }));
+        assert_eq!(kept, json!(own_dir.join("workspace.db")));
+        assert!(ok(&w, "get_active_workspace", json!({})).is_null());
+        assert_eq!(ok(&w, "open_workspace_database", json!({ "dir": own_dir }))["name"], "Clients");
+        ok(&w, "delete_workspace", json!({ "id": "ws-2" }));
+        assert_eq!(ok(&w, "get_workspaces", json!({})).as_array().unwrap().len(), 1);
+
+        // Projects, notes and revisions
+        ok(&w, "create_project", json!({ "project": {
+            "id": "project-1", "name": "Inbox", "created_at": "", "updated_at": ""
+        }}));
+        assert_eq!(ok(&w, "get_project", json!({ "id": "project-1" }))["name"], "Inbox");
+        ok(&w, "update_project", json!({ "project": {
+            "id": "project-1", "name": "Work", "created_at": "", "updated_at": ""
+        }}));
+        assert_eq!(ok(&w, "get_projects", json!({}))[0]["name"], "Work");
+        ok(&w, "reorder_projects", json!({ "ids": ["project-1"] }));
+        assert_eq!(ok(&w, "archive_project", json!({ "id": "project-1" }))["archived"], json!(true));
+        assert!(ok(&w, "get_projects", json!({})).as_array().unwrap().is_empty());
+        assert_eq!(ok(&w, "get_projects", json!({ "includeArchived": true }))[0]["note_count"], json!(0));
+        ok(&w, "unarchive_project", json!({ "id": "project-1" }));
+
+        let note = json!({
+            "id": "note-1", "title": "Plan", "content": "first draft",
+            "project_id": "project-1", "created_at": "", "updated_at": "", "is_pinned": false
+        });
+        ok(&w, "create_note", json!({ "note": note }));
+        let mut edited = note.clone();
+        edited["content"] = json!("second draft");
+        ok(&w, "update_note", json!({ "note": edited }));
+        assert_eq!(ok(&w, "get_note", json!({ "id": "note-1" }))["content"], "second draft");
+        assert_eq!(ok(&w, "get_notes", json!({ "projectId": "project-1" })).as_array().unwrap().len(), 1);
+
+        let revisions = ok(&w, "get_note_revisions", json!({ "noteId": "note-1" }));
+        let newest = revisions[0]["id"].clone();
+        let oldest = revisions[1]["id"].clone();
+        assert_eq!(ok(&w, "get_note_revision", json!({ "id": oldest }))["content"], "first draft");
+        let diff = ok(&w, "diff_note_revisions", json!({ "fromId": oldest, "toId": newest }));
+        assert_eq!(diff.as_array().unwrap().len(), 2);
+        let restored = ok(&w, "restore_note_revision", json!({ "revisionId": oldest }));
+        assert_eq!(restored["content"], "first draft");
+
+        let hits = ok(&w, "search_notes", json!({ "query": "draft" }));
+        assert_eq!(hits[0]["note_id"], "note-1");
+
+        // Links
+        let mut linked = note.clone();
+        linked["content"] = json!("see [[plan]] and [[Missing]]");
+        ok(&w, "update_note", json!({ "note": linked }));
+        assert_eq!(ok(&w, "get_outgoing_links", json!({ "noteId": "note-1" })).as_array().unwrap().len(), 2);
+        assert_eq!(ok(&w, "get_backlinks", json!({ "noteId": "note-1" }))[0]["context"], "see [[plan]] and [[Missing]]");
+        assert_eq!(ok(&w, "get_unresolved_links", json!({ "projectId": "project-1" }))[0]["target"], "Missing");
+        assert_eq!(ok(&w, "get_orphan_notes", json!({}))[0]["id"], "note-1");
+        let rename = ok(&w, "preview_note_rename", json!({ "noteId": "note-1", "newTitle": "Roadmap" }));
+        assert_eq!(rename["old_title"], "Plan");
+        assert!(rename["notes"].as_array().unwrap().is_empty());
+        let graph = ok(&w, "get_graph", json!({ "query": { "focus_note_id": "note-1", "depth": 1 } }));
+        assert_eq!(graph["nodes"][0]["id"], "note:note-1");
+        assert_eq!(graph["edges"].as_array().unwrap().len(), 2);
+
+        // Tags
+        ok(&w, "create_tag", json!({ "tag": { "id": "tag-1", "name": "todo", "created_at": "" } }));
+        ok(&w, "update_tag", json!({ "tag": { "id": "tag-1", "name": "later", "created_at": "" } }));
+        assert_eq!(ok(&w, "get_tags", json!({}))[0]["name"], "later");
+        ok(&w, "add_tag_to_note", json!({ "noteId": "note-1", "tagId": "tag-1" }));
+        assert_eq!(ok(&w, "get_note_tags", json!({ "noteId": "note-1" }))[0]["id"], "tag-1");
+        assert_eq!(ok(&w, "get_notes_by_tag", json!({ "tagId": "tag-1" }))[0]["id"], "note-1");
+        ok(&w, "create_tag", json!({ "tag": { "id": "tag-2", "name": "done", "created_at": "" } }));
+        assert_eq!(ok(&w, "rename_tag", json!({ "id": "tag-2", "name": "done/2024" }))["name"], "done/2024");
+        let moved = ok(&w, "move_tag", json!({ "id": "tag-2", "parentId": "tag-1" }));
+        assert_eq!(moved["name"], "later/2024");
+        assert_eq!(moved["parent_id"], "tag-1");
+        ok(&w, "merge_tags", json!({ "sourceId": "tag-2", "targetId": "tag-1" }));
+        let by_tree = ok(&w, "get_notes_by_tag", json!({ "tagId": "tag-1", "includeDescendants": true }));
+        assert_eq!(by_tree[0]["id"], "note-1");
+        ok(&w, "remove_tag_from_note", json!({ "noteId": "note-1", "tagId": "tag-1" }));
+
+        // Tasks
+        let task = json!({
+            "id": "task-1", "content": "Ship it", "completed": false,
+            "note_id": "note-1", "created_at": "", "updated_at": ""
+        });
+        let mut done = ok(&w, "create_task", json!({ "task": task }));
+        done["completed"] = json!(true);
+        let mut dated = ok(&w, "update_task", json!({ "task": done }));
+        assert_eq!(ok(&w, "get_tasks", json!({ "noteId": "note-1" }))[0]["completed"], json!(true));
+        assert_eq!(ok(&w, "get_all_tasks", json!({})).as_array().unwrap().len(), 1);
+        dated["completed"] = json!(false);
+        dated["due_date"] = json!("2000-01-01");
+        dated["priority"] = json!("high");
+        let mut weekly = ok(&w, "update_task", json!({ "task": dated }));
+        let overdue = ok(&w, "query_tasks", json!({ "query": {
+            "status": "open", "due": "overdue", "project_id": "project-1", "sort": "priority"
+        }}));
+        assert_eq!(overdue[0]["note_title"], "Plan");
+        assert_eq!(overdue[0]["priority"], "high");
+        weekly["recurrence"] = json!("FREQ=WEEKLY");
+        let mut weekly = ok(&w, "update_task", json!({ "task": weekly }));
+        weekly["completed"] = json!(true);
+        ok(&w, "update_task", json!({ "task": weekly }));
+        let occurrences = ok(&w, "get_task_occurrences", json!({ "taskId": "task-1" }));
+        assert_eq!(occurrences.as_array().unwrap().len(), 2);
+        assert_eq!(occurrences[1]["completed"], json!(false));
+        let ics_path = w.dir.path().join("tasks.ics");
+        let calendar = ok(&w, "export_tasks_ics", json!({ "path": ics_path, "options": { "open_only": true } }));
+        assert_eq!(calendar["tasks"], json!(1));
+        assert!(std::fs::read_to_string(&ics_path).unwrap().contains("BEGIN:VTODO"));
+        ok(&w, "delete_task", json!({ "id": "task-1" }));
+        let mut checklist = note.clone();
+        checklist["content"] = json!("- [ ] Draft\n- [ ] Review");
+        ok(&w, "update_note", json!({ "note": checklist }));
+        let mut draft = ok(&w, "get_tasks", json!({ "noteId": "note-1" }))[0].clone();
+        draft["completed"] = json!(true);
+        ok(&w, "update_task", json!({ "task": draft }));
+        assert_eq!(ok(&w, "get_note", json!({ "id": "note-1" }))["content"], "- [x] Draft\n- [ ] Review");
+        assert_eq!(invoke(&w, "update_task", json!({ "task": draft })).unwrap_err()["code"], "Conflict");
+
+        // Markdown export
+        let export_dir = w.dir.path().join("export");
+        let manifest = ok(&w, "export_project", json!({ "projectId": "project-1", "directory": export_dir }));
+        assert_eq!(manifest["notes"][0]["file"], "plan.md");
+        assert!(export_dir.join("manifest.json").exists());
+
+        // Markdown import
+        let preview = ok(&w, "import_markdown", json!({
+            "directory": export_dir, "options": { "folders": "tags", "dry_run": true }
+        }));
+        assert_eq!(preview["notes"][0]["title"], "Plan");
+        assert_ne!(preview["notes"][0]["note_id"], "note-1");
+        assert_eq!(ok(&w, "get_projects", json!({})).as_array().unwrap().len(), 1);
+
+        // Trash
+        ok(&w, "set_trash_retention_days", json!({ "days": 7 }));
+        assert_eq!(ok(&w, "get_trash_retention_days", json!({})), json!(7));
+        ok(&w, "delete_tag", json!({ "id": "tag-1" }));
+        ok(&w, "delete_note", json!({ "id": "note-1" }));
+        ok(&w, "delete_project", json!({ "id": "project-1" }));
+        assert_eq!(ok(&w, "get_trash", json!({})).as_array().unwrap().len(), 3);
+        ok(&w, "restore_from_trash", json!({ "kind": "tag", "id": "tag-1" }));
+        assert_eq!(ok(&w, "empty_trash", json!({})), json!(2));
+
+        // Snapshots
+        ok(&w, "set_snapshot_policy", json!({ "policy": {
+            "keep_daily": 3, "keep_weekly": 2, "compress": true
+        }}));
+        assert_eq!(ok(&w, "get_snapshot_policy", json!({}))["keep_daily"], json!(3));
+        let snapshot = ok(&w, "create_snapshot", json!({}));
+        assert_eq!(snapshot["compressed"], json!(true));
+        assert_eq!(ok(&w, "list_snapshots", json!({})).as_array().unwrap().len(), 1);
+        ok(&w, "verify_snapshot", json!({ "fileName": snapshot["file_name"] }));
+        ok(&w, "delete_snapshot", json!({ "fileName": snapshot["file_name"] }));
+
+        // Backup scheduler
+        ok(&w, "set_backup_schedule", json!({ "settings": { "enabled": true, "interval_minutes": 30 } }));
+        assert_eq!(ok(&w, "get_backup_schedule", json!({}))["interval_minutes"], json!(30));
+        let run = ok(&w, "run_backup_now", json!({}));
+        assert_eq!(run["outcome"], "succeeded");
+        assert_eq!(ok(&w, "get_backup_schedule", json!({}))["last_run"]["trigger"], "manual");
+        assert!(w.dir.path().join("data/snapshots").is_dir());
+
+        let invoked = w.invoked.borrow();
+        let missed: Vec<&str> = COMMAND_NAMES.iter().copied().filter(|cmd| !invoked.contains(*cmd)).collect();
+        assert!(missed.is_empty(), "commands not covered: {:?}", missed);
+    }
+
+    #[test]
+    fn errors_carry_stable_codes() {
+        let w = mock_app();
+
+        let missing = invoke(&w, "get_note", json!({ "id": "nope" })).unwrap_err();
+        assert_eq!(missing["code"], "NotFound");
+        assert_eq!(missing["details"], json!({ "entity": "note", "id": "nope" }));
+
+        let project = json!({ "project": {
+            "id": "project-1", "name": "Inbox", "created_at": "", "updated_at": ""
+        }});
+        ok(&w, "create_project", project.clone());
+        assert_eq!(invoke(&w, "create_project", project).unwrap_err()["code"], "Conflict");
+
+        let unnamed = json!({ "tag": { "id": "tag-1", "name": " ", "created_at": "" } });
+        assert_eq!(invoke(&w, "create_tag", unnamed).unwrap_err()["code"], "Validation");
+
+        assert_eq!(invoke(&w, "delete_task", json!({ "id": "nope" })).unwrap_err()["code"], "NotFound");
+    }
+
+    #[test]
+    fn unknown_command_is_rejected() {
+        let w = mock_app();
+        assert!(invoke(&w, "init_db", json!({})).is_err());
+    }
 }
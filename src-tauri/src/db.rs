@@ -1,19 +1,70 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::fs;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, Utc};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::checklist::{self, SyncedTask, TaskChange};
+use crate::diff::{self, DiffLine};
+use crate::error::{expect_changed, AppError, Result};
+use crate::graph::{self, GraphData, GraphInput, GraphQuery};
+use crate::ics::{self, IcsExport, IcsExportOptions};
+use crate::links::{self, LinkKind};
+use crate::markdown::{
+    self, ExportManifest, ExportNote, FolderMapping, ImportError, ImportOptions, ImportReport,
+    ImportedNote, ParsedNote,
+};
+use crate::migrations;
+use crate::profiles::{self, LegacyImportReport, ProfileSettings};
+use crate::search::{self, MatchOffset, MATCH_CLOSE, MATCH_OPEN};
+use crate::snapshot::{self, AutoBackupSettings, SnapshotInfo, SnapshotPolicy};
+use crate::theme_import::{self, EditorThemeImport};
+use crate::themes::{self, SavedTheme, ThemeExport, ThemeFile, ThemeImport, ThemeImportError};
+use crate::recurrence::Recurrence;
+use crate::tasks::{self, Due, TaskPriority, TaskQuery, TaskStatus};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workspace {
     pub id: String,
     pub name: String,
     pub type_: String,
     pub theme_id: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Set by the backend; changed with `switch_workspace_profile`.
+    #[serde(default)]
+    pub current_profile_id: Option<String>,
+    /// SQLite file of a workspace kept in its own database. Set by the
+    /// backend; `None` for workspaces in the main database.
+    #[serde(default)]
+    pub database_path: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A named set of theme overrides and editor settings. A workspace always
+/// has at least one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceProfile {
+    pub id: String,
+    pub workspace_id: String,
+    pub name: String,
+    /// Overrides of the app theme (`AppThemeOptions` fields), kept as the UI
+    /// sends them.
+    #[serde(default)]
+    pub theme: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub settings: ProfileSettings,
+    /// Set by the backend.
+    #[serde(default)]
+    pub position: i64,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkspaceSettings {
     pub workspace_id: String,
     pub dark_mode: bool,
@@ -22,7 +73,7 @@ pub struct WorkspaceSettings {
     pub auto_save: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomTheme {
     pub id: String,
     pub name: String,
@@ -35,7 +86,7 @@ pub struct CustomTheme {
     pub accent_color: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tab {
     pub id: String,
     pub workspace_id: String,
@@ -44,9 +95,26 @@ pub struct Tab {
     pub type_: String,
     pub position: i32,
     pub is_active: bool,
+    /// Note shown in the tab. Its content is read from the note, and
+    /// `content` is not stored.
+    #[serde(default)]
+    pub note_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A tab on a workspace's reopen stack, most recently closed first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedTab {
+    pub tab_id: String,
+    pub workspace_id: String,
+    pub title: String,
+    pub content: Option<String>,
+    pub type_: String,
+    pub note_id: Option<String>,
+    pub position: i32,
+    pub closed_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
     pub id: String,
     pub title: String,
@@ -57,22 +125,58 @@ pub struct Note {
     pub is_pinned: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
     pub id: String,
     pub name: String,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Any color `themes::normalize_color` accepts, such as `#3b82f6`.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Emoji or icon name, as the UI chooses.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Place in the project list; set by `create_project` and
+    /// `reorder_projects` only.
+    #[serde(default)]
+    pub position: i64,
+    /// Set by `archive_project` and `unarchive_project` only.
+    #[serde(default)]
+    pub archived: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A project as listed by `get_projects`, with counts of its live notes and
+/// their tasks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectSummary {
+    #[serde(flatten)]
+    pub project: Project,
+    pub note_count: i64,
+    pub task_count: i64,
+    pub open_task_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
     pub id: String,
+    /// Full path; `work/clients/acme` sits under `work/clients`. Unique
+    /// among live tags regardless of case.
     pub name: String,
     pub created_at: String,
+    /// Any color `themes::normalize_color` accepts, such as `#3b82f6`.
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Derived from the name by the backend.
+    #[serde(default)]
+    pub parent_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: String,
     pub content: String,
@@ -80,6 +184,166 @@ pub struct Task {
     pub note_id: String,
     pub created_at: String,
     pub updated_at: String,
+    /// A day (`2024-11-23`) or an RFC 3339 timestamp.
+    #[serde(default)]
+    pub due_date: Option<String>,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    /// Set when the task gets completed, cleared when it is reopened.
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    /// RRULE such as `FREQ=WEEKLY;BYDAY=MO`; see `Recurrence`.
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    /// Id of the first task of a recurring series, shared by its occurrences.
+    /// Set by the backend.
+    #[serde(default)]
+    pub series_id: Option<String>,
+}
+
+/// A task listed across notes, with where it comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskListItem {
+    #[serde(flatten)]
+    pub task: Task,
+    pub note_title: String,
+    pub project_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteRevision {
+    pub id: String,
+    pub note_id: String,
+    pub title: String,
+    pub content: String,
+    pub kind: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteRevisionSummary {
+    pub id: String,
+    pub note_id: String,
+    pub title: String,
+    pub content_length: usize,
+    pub kind: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub id: String,
+    pub workspace_id: String,
+    pub kind: String,
+    pub size_bytes: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backup {
+    #[serde(flatten)]
+    pub info: BackupInfo,
+    pub data: String,
+    /// Parsed contents when `data` is a workspace snapshot; backups posted by
+    /// the frontend in another shape can be listed and downloaded but not
+    /// restored.
+    pub snapshot: Option<WorkspaceSnapshot>,
+}
+
+/// Restorable contents of a backup: everything scoped to one workspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot {
+    pub format_version: u32,
+    pub workspace: Workspace,
+    pub settings: Option<WorkspaceSettings>,
+    pub tabs: Vec<Tab>,
+    /// Missing from version 1 snapshots, whose restore leaves the current
+    /// profiles alone.
+    #[serde(default)]
+    pub profiles: Vec<WorkspaceProfile>,
+}
+
+const WORKSPACE_SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashKind {
+    Note,
+    Project,
+    Tag,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub id: String,
+    pub kind: TrashKind,
+    pub name: String,
+    pub project_id: Option<String>,
+    pub deleted_at: String,
+    /// Notes that went to the trash along with a project and will come back
+    /// with it.
+    pub cascaded_notes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub note_id: String,
+    pub project_id: String,
+    pub title: String,
+    /// Excerpt of the content around the best match, HTML-escaped with
+    /// matches wrapped in `<mark>`.
+    pub snippet: String,
+    /// Relevance, higher is better (negated FTS5 bm25, title weighted 10x).
+    pub score: f64,
+    pub title_matches: Vec<MatchOffset>,
+    pub content_matches: Vec<MatchOffset>,
+}
+
+/// Links in one note that a rename re-points, with each changed line
+/// before and after.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkRewrite {
+    pub note_id: String,
+    pub title: String,
+    pub lines: Vec<RewrittenLine>,
+    #[serde(skip)]
+    content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewrittenLine {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenamePreview {
+    pub note_id: String,
+    pub old_title: String,
+    pub new_title: String,
+    pub notes: Vec<LinkRewrite>,
+}
+
+/// A link as written in `source_id`, with the note it currently resolves
+/// to, if any.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteLink {
+    pub source_id: String,
+    pub source_title: String,
+    pub kind: LinkKind,
+    /// Title for wiki links, note id for id links, as written.
+    pub target: String,
+    pub label: Option<String>,
+    pub line: i64,
+    /// The line holding the link, trimmed.
+    pub context: String,
+    pub target_id: Option<String>,
+    pub target_title: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -89,51 +353,547 @@ pub struct NoteTag {
 }
 
 pub struct DbState {
+    /// The active database: the main one, or the own database of the
+    /// workspace last switched to.
     pub connection: Mutex<Connection>,
+    pub data_dir: PathBuf,
+    /// Locked before `connection` when both are needed.
+    databases: Mutex<Databases>,
+}
+
+/// Which database `connection` is, and the others kept open. Databases are
+/// keyed by the id of the workspace owning them, `None` for the main one.
+struct Databases {
+    active: Option<String>,
+    /// Directory of the active database, where its snapshots go.
+    active_dir: PathBuf,
+    /// Workspace last switched to.
+    workspace_id: Option<String>,
+    /// Databases switched away from. The main database is here whenever it
+    /// is not active.
+    idle: HashMap<Option<String>, Connection>,
+}
+
+/// File name of a workspace's own database in the directory chosen for it.
+pub const WORKSPACE_DATABASE_FILE: &str = "workspace.db";
+
+/// Saves landing within this long of a revision's creation are folded into
+/// it, so autosave does not produce a revision per keystroke pause.
+const REVISION_COALESCE_WINDOW_SECS: i64 = 5 * 60;
+
+const TRASH_RETENTION_KEY: &str = "trash_retention_days";
+const SNAPSHOT_KEEP_DAILY_KEY: &str = "snapshot_keep_daily";
+const SNAPSHOT_KEEP_WEEKLY_KEY: &str = "snapshot_keep_weekly";
+const SNAPSHOT_COMPRESS_KEY: &str = "snapshot_compress";
+const AUTO_BACKUP_ENABLED_KEY: &str = "auto_backup_enabled";
+const AUTO_BACKUP_INTERVAL_KEY: &str = "auto_backup_interval_minutes";
+/// Set by the migration creating `note_links`; existing notes are indexed on
+/// the next start since links are parsed in Rust.
+const NOTE_LINKS_REINDEX_KEY: &str = "note_links_reindex";
+/// Set by the migration binding tasks to checkboxes, for the same reason.
+const TASK_CHECKBOXES_SYNC_KEY: &str = "task_checkboxes_sync";
+/// Set by the migration adding tag hierarchy; parents of existing `a/b`
+/// tags are created on the next start.
+const TAG_HIERARCHY_KEY: &str = "tag_hierarchy_rebuild";
+
+/// Set once the workspaces the UI kept in localStorage have been imported.
+const LOCAL_WORKSPACES_MIGRATED_KEY: &str = "local_workspaces_migrated";
+const DEFAULT_PROFILE_NAME: &str = "Default";
+
+/// Closed tabs kept per workspace for reopening.
+const CLOSED_TAB_LIMIT: u32 = 20;
+
+const WORKSPACE_COLUMNS: &str =
+    "w.id, w.name, w.type, w.theme_id, w.path, w.current_profile_id, w.database_path";
+const PROFILE_COLUMNS: &str =
+    "p.id, p.workspace_id, p.name, p.theme, p.settings, p.position, p.created_at, p.updated_at";
+
+/// Tabs bound to a note show its content, or none while it is in the trash.
+const TAB_COLUMNS: &str = "t.id, t.workspace_id, t.title, 
+     CASE WHEN t.note_id IS NULL THEN t.content ELSE n.content END, 
+     t.type, t.position, t.is_active, t.note_id 
+     FROM tabs t 
+     LEFT JOIN notes n ON n.id = t.note_id AND n.deleted_at IS NULL";
+
+const CLOSED_TAB_COLUMNS: &str =
+    "tab_id, workspace_id, title, content, type, note_id, position, closed_at";
+
+const THEME_COLUMNS: &str = "id, name, type, primary_color, secondary_color, background_color, 
+     surface_color, text_color, accent_color";
+
+const PROJECT_COLUMNS: &str =
+    "p.id, p.name, p.created_at, p.updated_at, p.description, p.color, p.icon, p.position, p.archived";
+
+const TAG_COLUMNS: &str = "t.id, t.name, t.created_at, t.color, t.description, t.parent_id";
+
+/// The tag and everything below it, as `subtree(id)`; bind the tag id as ?1.
+const TAG_SUBTREE: &str = "WITH RECURSIVE subtree(id) AS (
+        SELECT ?1 
+        UNION ALL 
+        SELECT c.id FROM tags c INNER JOIN subtree s ON c.parent_id = s.id
+     )";
+
+const TASK_COLUMNS: &str = "t.id, t.content, t.completed, t.note_id, t.created_at, t.updated_at, 
+     t.due_date, t.priority, t.completed_at, t.assignee, t.recurrence, t.series_id";
+
+const LINK_COLUMNS: &str = "r.source_id, s.title, r.kind, r.target, r.label, r.line, r.context, 
+     r.target_id, t.title 
+     FROM resolved_note_links r 
+     INNER JOIN notes s ON s.id = r.source_id 
+     LEFT JOIN notes t ON t.id = r.target_id";
+
+/// Timestamps are written as RFC 3339 by the app, but rows touched by the
+/// `update_*_timestamp` triggers carry SQLite's `CURRENT_TIMESTAMP` format.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|t| t.and_utc())
+        })
+}
+
+/// Snapshots the given note state as a revision of `kind` ("create", "edit"
+/// or "restore"). An edit landing while the latest revision is an edit still
+/// inside the coalescing window overwrites it instead, so the state before a
+/// burst of autosaves always survives in the previous revision. Nothing is
+/// recorded if the state is unchanged.
+fn record_revision(
+    conn: &Connection,
+    note_id: &str,
+    title: &str,
+    content: &str,
+    kind: &str,
+) -> Result<()> {
+    let now = Utc::now();
+    let latest: Option<(String, String, String, String, String)> = conn
+        .query_row(
+            "SELECT id, title, COALESCE(content, ''), kind, created_at FROM note_revisions
+             WHERE note_id = ? ORDER BY created_at DESC, rowid DESC LIMIT 1",
+            [note_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
+        .optional()?;
+
+    if let Some((id, latest_title, latest_content, latest_kind, created_at)) = latest {
+        if latest_title == title && latest_content == content {
+            return Ok(());
+        }
+        let in_window = parse_timestamp(&created_at)
+            .map(|t| now - t < Duration::seconds(REVISION_COALESCE_WINDOW_SECS))
+            .unwrap_or(false);
+        if kind == "edit" && latest_kind == "edit" && in_window {
+            conn.execute(
+                "UPDATE note_revisions SET title = ?1, content = ?2, updated_at = ?3 WHERE id = ?4",
+                (title, content, now.to_rfc3339(), &id),
+            )?;
+            return Ok(());
+        }
+    }
+
+    let now = now.to_rfc3339();
+    conn.execute(
+        "INSERT INTO note_revisions (id, note_id, title, content, kind, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        (uuid::Uuid::new_v4().to_string(), note_id, title, content, kind, &now, &now),
+    )?;
+    Ok(())
 }
 
 impl DbState {
     pub fn new(app_dir: PathBuf) -> Result<Self> {
-        fs::create_dir_all(&app_dir)?;
-        let db_path = app_dir.join("app.db");
-        let conn = Connection::open(db_path)?;
-        
-        // Initialize database with schema
-        let init_sql = include_str!("../migrations/init.sql");
-        conn.execute_batch(init_sql)?;
-        
-        Ok(DbState {
+        let conn = open_database(&app_dir.join("app.db"))?;
+        let db = DbState {
             connection: Mutex::new(conn),
-        })
+            databases: Mutex::new(Databases {
+                active: None,
+                active_dir: app_dir.clone(),
+                workspace_id: None,
+                idle: HashMap::new(),
+            }),
+            data_dir: app_dir,
+        };
+        db.run_startup_tasks()?;
+        Ok(db)
+    }
+
+    /// Upkeep done on the active database after it is opened.
+    fn run_startup_tasks(&self) -> Result<()> {
+        self.purge_expired_trash()?;
+        self.reindex_links_if_needed()?;
+        self.sync_task_checkboxes_if_needed()?;
+        self.rebuild_tag_hierarchy_if_needed()
+    }
+
+    /// Runs `f` on the main database, which lists every workspace whether
+    /// or not it is the active one.
+    fn with_main<T>(
+        &self,
+        databases: &mut Databases,
+        f: impl FnOnce(&mut Connection) -> Result<T>,
+    ) -> Result<T> {
+        match databases.active {
+            None => f(&mut self.connection.lock().unwrap()),
+            Some(_) => f(databases.idle.get_mut(&None).expect("the main database stays open")),
+        }
+    }
+
+    pub fn schema_version(&self) -> Result<u32> {
+        let conn = self.connection.lock().unwrap();
+        Ok(migrations::current_version(&conn)?)
     }
 
     // Workspaces
-    pub fn create_workspace(&self, workspace: &Workspace) -> Result<()> {
+    /// Creates the workspace with a default profile as its current one.
+    /// With `database_dir`, the workspace gets its own database in that
+    /// directory, and the main database only lists it.
+    pub fn create_workspace(&self, workspace: &Workspace, database_dir: Option<&Path>) -> Result<()> {
+        let mut databases = self.databases.lock().unwrap();
+        let Some(dir) = database_dir else {
+            let workspace = Workspace { database_path: None, ..workspace.clone() };
+            return self.with_main(&mut databases, |conn| insert_workspace(conn, &workspace));
+        };
+
+        let path = dir.join(WORKSPACE_DATABASE_FILE);
+        if path.exists() {
+            return Err(AppError::Conflict {
+                message: format!("{} already holds a workspace database; open it instead", dir.display()),
+            });
+        }
+        let workspace = Workspace {
+            database_path: Some(path.to_string_lossy().into_owned()),
+            current_profile_id: None,
+            ..workspace.clone()
+        };
+        self.with_main(&mut databases, |conn| {
+            ensure_workspace_id_free(conn, &workspace.id)?;
+            insert_workspace(&mut open_database(&path)?, &workspace)?;
+            register_workspace(conn, &workspace)
+        })
+    }
+
+    pub fn get_workspaces(&self) -> Result<Vec<Workspace>> {
+        let mut databases = self.databases.lock().unwrap();
+        self.with_main(&mut databases, |conn| {
+            let mut stmt = conn.prepare(&format!("SELECT {} FROM workspaces w", WORKSPACE_COLUMNS))?;
+            let workspaces = stmt
+                .query_map([], workspace_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(workspaces)
+        })
+    }
+
+    /// Renames the workspace. Its own database, if it has one, gets the new
+    /// name now if it is active and otherwise when next switched to.
+    pub fn rename_workspace(&self, id: &str, name: &str) -> Result<Workspace> {
+        validate_name("name", name)?;
+        let mut databases = self.databases.lock().unwrap();
+        let workspace = self.with_main(&mut databases, |conn| {
+            let changed = conn.execute("UPDATE workspaces SET name = ?1 WHERE id = ?2", (name, id))?;
+            expect_changed(changed, "workspace", id)?;
+            query_workspace(conn, id)
+        })?;
+        if databases.active.as_deref() == Some(id) {
+            let conn = self.connection.lock().unwrap();
+            conn.execute("UPDATE workspaces SET name = ?1 WHERE id = ?2", (name, id))?;
+        }
+        Ok(workspace)
+    }
+
+    /// Deletes the workspace with its tabs, settings, profiles and backups.
+    /// A workspace with its own database is closed and removed from the
+    /// list instead, and the path of its database is returned: the file
+    /// stays in its directory and can be opened again with
+    /// `open_workspace_database`, or removed by the caller.
+    pub fn delete_workspace(&self, id: &str) -> Result<Option<PathBuf>> {
+        let mut databases = self.databases.lock().unwrap();
+        let workspace = self.with_main(&mut databases, |conn| query_workspace(conn, id))?;
+        if workspace.database_path.is_some() {
+            let key = Some(id.to_string());
+            if databases.active == key {
+                let main = databases.idle.remove(&None).expect("the main database stays open");
+                *self.connection.lock().unwrap() = main;
+                databases.active = None;
+                databases.active_dir = self.data_dir.clone();
+            }
+            databases.idle.remove(&key);
+        }
+        if databases.workspace_id.as_deref() == Some(id) {
+            databases.workspace_id = None;
+        }
+
+        self.with_main(&mut databases, |conn| {
+            let tx = conn.transaction()?;
+            for table in ["closed_tabs", "tabs", "workspace_settings", "workspace_profiles", "backups"] {
+                tx.execute(&format!("DELETE FROM {} WHERE workspace_id = ?", table), [id])?;
+            }
+            tx.execute("DELETE FROM workspaces WHERE id = ?", [id])?;
+            tx.commit()?;
+            Ok(())
+        })?;
+        Ok(workspace.database_path.map(PathBuf::from))
+    }
+
+    /// Adds the workspace whose own database is in `dir` to the list, such
+    /// as one deleted earlier or moved to another directory.
+    pub fn open_workspace_database(&self, dir: &Path) -> Result<Workspace> {
+        let path = dir.join(WORKSPACE_DATABASE_FILE);
+        if !path.is_file() {
+            return Err(AppError::validation(
+                "dir",
+                format!("{} holds no workspace database", dir.display()),
+            ));
+        }
+        let mut databases = self.databases.lock().unwrap();
+        let mut workspace = open_database(&path)?
+            .query_row(
+                &format!("SELECT {} FROM workspaces w ORDER BY w.rowid LIMIT 1", WORKSPACE_COLUMNS),
+                [],
+                workspace_from_row,
+            )
+            .optional()?
+            .ok_or_else(|| AppError::validation("dir", format!("{} holds no workspace", path.display())))?;
+        workspace.database_path = Some(path.to_string_lossy().into_owned());
+        workspace.current_profile_id = None;
+
+        self.with_main(&mut databases, |conn| {
+            ensure_workspace_id_free(conn, &workspace.id)?;
+            register_workspace(conn, &workspace)?;
+            query_workspace(conn, &workspace.id)
+        })
+    }
+
+    /// Makes the workspace's database the active one, which every command
+    /// but the workspace list ones works on: its own database if it has
+    /// one, otherwise the main database. Databases switched away from stay
+    /// open.
+    pub fn switch_workspace(&self, id: &str) -> Result<Workspace> {
+        let mut databases = self.databases.lock().unwrap();
+        let registered = self.with_main(&mut databases, |conn| query_workspace(conn, id))?;
+        let key = registered.database_path.as_ref().map(|_| id.to_string());
+        let mut opened = false;
+        if key != databases.active {
+            let next = match (databases.idle.remove(&key), &registered.database_path) {
+                (Some(conn), _) => conn,
+                (None, Some(path)) => {
+                    // Opening a missing file would create an empty database
+                    if !Path::new(path).is_file() {
+                        return Err(AppError::not_found("workspace database", path.as_str()));
+                    }
+                    opened = true;
+                    open_database(Path::new(path))?
+                }
+                (None, None) => unreachable!("the main database stays open"),
+            };
+            let previous = std::mem::replace(&mut *self.connection.lock().unwrap(), next);
+            let active = std::mem::replace(&mut databases.active, key);
+            databases.idle.insert(active, previous);
+            databases.active_dir = match &registered.database_path {
+                Some(path) => Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default(),
+                None => self.data_dir.clone(),
+            };
+        }
+        databases.workspace_id = Some(id.to_string());
+
+        let mut conn = self.connection.lock().unwrap();
+        if registered.database_path.is_some() {
+            sync_workspace_row(&mut conn, &registered)?;
+        }
+        drop(conn);
+        if opened {
+            self.run_startup_tasks()?;
+        }
         let conn = self.connection.lock().unwrap();
-        conn.execute(
-            "INSERT INTO workspaces (id, name, type, theme_id) VALUES (?1, ?2, ?3, ?4)",
-            (&workspace.id, &workspace.name, &workspace.type_, &workspace.theme_id),
+        query_workspace(&conn, id)
+    }
+
+    /// The workspace last switched to, `None` before any switch.
+    pub fn get_active_workspace(&self) -> Result<Option<Workspace>> {
+        let databases = self.databases.lock().unwrap();
+        let Some(id) = &databases.workspace_id else {
+            return Ok(None);
+        };
+        let conn = self.connection.lock().unwrap();
+        query_workspace(&conn, id).map(Some)
+    }
+
+    // Workspace Profiles
+    /// Adds a profile at the end of the workspace's list.
+    pub fn create_workspace_profile(&self, profile: &WorkspaceProfile) -> Result<WorkspaceProfile> {
+        validate_name("name", &profile.name)?;
+        profile.settings.validate()?;
+        let conn = self.connection.lock().unwrap();
+        query_workspace(&conn, &profile.workspace_id)?;
+        let id = insert_profile(&conn, profile)?;
+        query_profile(&conn, &id)
+    }
+
+    pub fn get_workspace_profiles(&self, workspace_id: &str) -> Result<Vec<WorkspaceProfile>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM workspace_profiles p WHERE p.workspace_id = ? ORDER BY p.position",
+            PROFILE_COLUMNS
+        ))?;
+        let profiles = stmt
+            .query_map([workspace_id], profile_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(profiles)
+    }
+
+    /// Saves the profile's name, theme and settings.
+    pub fn update_workspace_profile(&self, profile: &WorkspaceProfile) -> Result<WorkspaceProfile> {
+        validate_name("name", &profile.name)?;
+        profile.settings.validate()?;
+        let conn = self.connection.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE workspace_profiles SET name = ?1, theme = ?2, settings = ?3, updated_at = ?4 
+             WHERE id = ?5",
+            (
+                &profile.name,
+                to_json(&profile.theme)?,
+                to_json(&profile.settings)?,
+                Utc::now().to_rfc3339(),
+                &profile.id,
+            ),
+        )?;
+        expect_changed(changed, "workspace profile", &profile.id)?;
+        query_profile(&conn, &profile.id)
+    }
+
+    /// Copies the profile under `name`, at the end of the workspace's list.
+    pub fn duplicate_workspace_profile(&self, id: &str, name: &str) -> Result<WorkspaceProfile> {
+        validate_name("name", name)?;
+        let conn = self.connection.lock().unwrap();
+        let source = query_profile(&conn, id)?;
+        let copy = WorkspaceProfile {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            ..source
+        };
+        let id = insert_profile(&conn, &copy)?;
+        query_profile(&conn, &id)
+    }
+
+    /// Deletes the profile unless it is the workspace's last one. If it was
+    /// the current profile, the first remaining one takes over.
+    pub fn delete_workspace_profile(&self, id: &str) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let profile = query_profile(&tx, id)?;
+        let count: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM workspace_profiles WHERE workspace_id = ?",
+            [&profile.workspace_id],
+            |row| row.get(0),
         )?;
+        if count <= 1 {
+            return Err(AppError::Conflict {
+                message: "a workspace keeps at least one profile".into(),
+            });
+        }
+
+        tx.execute("DELETE FROM workspace_profiles WHERE id = ?", [id])?;
+        tx.execute(
+            "UPDATE workspaces SET current_profile_id = (
+                SELECT p.id FROM workspace_profiles p 
+                WHERE p.workspace_id = workspaces.id ORDER BY p.position LIMIT 1
+             ) 
+             WHERE id = ?1 AND current_profile_id = ?2",
+            (&profile.workspace_id, id),
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn get_workspaces(&self) -> Result<Vec<Workspace>> {
+    /// Makes `profile_id` the workspace's current profile and returns it.
+    pub fn switch_workspace_profile(&self, workspace_id: &str, profile_id: &str) -> Result<WorkspaceProfile> {
         let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, type, theme_id FROM workspaces")?;
-        let workspace_iter = stmt.query_map([], |row| {
-            Ok(Workspace {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                type_: row.get(2)?,
-                theme_id: row.get(3)?,
-            })
-        })?;
+        let profile = query_profile(&conn, profile_id)?;
+        if profile.workspace_id != workspace_id {
+            return Err(AppError::not_found("workspace profile", profile_id));
+        }
+        let changed = conn.execute(
+            "UPDATE workspaces SET current_profile_id = ?1 WHERE id = ?2",
+            (profile_id, workspace_id),
+        )?;
+        expect_changed(changed, "workspace", workspace_id)?;
+        Ok(profile)
+    }
+
+    /// Imports the workspaces and profiles the UI kept in localStorage. Runs
+    /// once; later calls report `already_migrated` and read nothing.
+    /// Workspaces whose id is taken are skipped, and profile ids that clash
+    /// with another workspace's are replaced.
+    pub fn migrate_local_workspaces(&self, json: &str) -> Result<LegacyImportReport> {
+        let mut conn = self.connection.lock().unwrap();
+        if read_setting(&conn, LOCAL_WORKSPACES_MIGRATED_KEY, false)? {
+            return Ok(LegacyImportReport {
+                already_migrated: true,
+                ..Default::default()
+            });
+        }
+        let workspaces = profiles::parse_legacy(json)?;
 
-        let mut workspaces = Vec::new();
-        for workspace in workspace_iter {
-            workspaces.push(workspace?);
+        let tx = conn.transaction()?;
+        let mut report = LegacyImportReport::default();
+        for legacy in workspaces {
+            if legacy.name.trim().is_empty() || query_workspace(&tx, &legacy.id).is_ok() {
+                report.skipped.push(legacy.id);
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO workspaces (id, name, type, theme_id, path) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    &legacy.id,
+                    &legacy.name,
+                    profiles::LEGACY_WORKSPACE_TYPE,
+                    profiles::LEGACY_WORKSPACE_THEME,
+                    legacy.path.as_deref().filter(|p| !p.is_empty()),
+                ),
+            )?;
+
+            let mut current = None;
+            let mut legacy_profiles = legacy.profiles;
+            if legacy_profiles.is_empty() {
+                legacy_profiles.push(profiles::LegacyProfile {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: DEFAULT_PROFILE_NAME.to_string(),
+                    theme: Default::default(),
+                    settings: Default::default(),
+                });
+            }
+            for profile in legacy_profiles {
+                let taken = query_profile(&tx, &profile.id).is_ok();
+                let id = insert_profile(
+                    &tx,
+                    &WorkspaceProfile {
+                        id: if taken { uuid::Uuid::new_v4().to_string() } else { profile.id.clone() },
+                        workspace_id: legacy.id.clone(),
+                        name: Some(profile.name)
+                            .filter(|n| !n.trim().is_empty())
+                            .unwrap_or_else(|| DEFAULT_PROFILE_NAME.to_string()),
+                        theme: profile.theme,
+                        settings: profile.settings,
+                        position: 0,
+                        created_at: String::new(),
+                        updated_at: String::new(),
+                    },
+                )?;
+                if current.is_none() || legacy.current_profile_id.as_ref() == Some(&profile.id) {
+                    current = Some(id);
+                }
+                report.profiles += 1;
+            }
+            tx.execute(
+                "UPDATE workspaces SET current_profile_id = ?1 WHERE id = ?2",
+                (current, &legacy.id),
+            )?;
+            report.workspaces += 1;
         }
-        Ok(workspaces)
+        write_setting(&tx, LOCAL_WORKSPACES_MIGRATED_KEY, "true")?;
+        tx.commit()?;
+        Ok(report)
     }
 
     // Workspace Settings
@@ -155,15 +915,38 @@ impl DbState {
     }
 
     // Custom Themes
-    pub fn create_custom_theme(&self, theme: &CustomTheme) -> Result<()> {
+    pub fn create_custom_theme(&self, theme: &CustomTheme) -> Result<SavedTheme> {
+        let theme = normalize_theme(theme)?;
         let conn = self.connection.lock().unwrap();
-        conn.execute(
-            "INSERT INTO custom_themes 
-            (id, name, type, primary_color, secondary_color, background_color, 
-            surface_color, text_color, accent_color) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        insert_theme(&conn, &theme)?;
+        Ok(saved_theme(theme))
+    }
+
+    pub fn get_custom_themes(&self) -> Result<Vec<CustomTheme>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM custom_themes ORDER BY name COLLATE NOCASE, created_at",
+            THEME_COLUMNS
+        ))?;
+        let themes = stmt
+            .query_map([], theme_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(themes)
+    }
+
+    pub fn get_custom_theme(&self, id: &str) -> Result<CustomTheme> {
+        let conn = self.connection.lock().unwrap();
+        query_theme(&conn, id)
+    }
+
+    pub fn update_custom_theme(&self, theme: &CustomTheme) -> Result<SavedTheme> {
+        let theme = normalize_theme(theme)?;
+        let conn = self.connection.lock().unwrap();
+        let changed = conn.execute(
+            "UPDATE custom_themes SET name = ?1, type = ?2, primary_color = ?3, secondary_color = ?4, 
+             background_color = ?5, surface_color = ?6, text_color = ?7, accent_color = ?8 
+             WHERE id = ?9",
             (
-                &theme.id,
                 &theme.name,
                 &theme.type_,
                 &theme.primary_color,
@@ -172,77 +955,491 @@ impl DbState {
                 &theme.surface_color,
                 &theme.text_color,
                 &theme.accent_color,
+                &theme.id,
             ),
         )?;
-        Ok(())
+        expect_changed(changed, "theme", &theme.id)?;
+        Ok(saved_theme(theme))
+    }
+
+    pub fn delete_custom_theme(&self, id: &str) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+        let changed = conn.execute("DELETE FROM custom_themes WHERE id = ?", [id])?;
+        expect_changed(changed, "theme", id)
+    }
+
+    /// Writes the themes in `ids`, or all of them, to `path` as a theme file.
+    pub fn export_custom_themes(&self, path: &Path, ids: Option<&[String]>) -> Result<ThemeExport> {
+        let themes = match ids {
+            Some(ids) => ids.iter().map(|id| self.get_custom_theme(id)).collect::<Result<Vec<_>>>()?,
+            None => self.get_custom_themes()?,
+        };
+        let file = ThemeFile {
+            format: themes::THEME_FILE_FORMAT.to_string(),
+            format_version: themes::THEME_FILE_VERSION,
+            themes,
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| AppError::validation("themes", e.to_string()))?;
+        fs::write(path, json)?;
+        Ok(ThemeExport {
+            path: path.to_path_buf(),
+            themes: file.themes.len(),
+        })
+    }
+
+    /// Adds the themes of a file written by `export_custom_themes`. Themes
+    /// whose id is taken get a new one, so importing never overwrites;
+    /// invalid themes are reported and skipped.
+    pub fn import_custom_themes(&self, path: &Path) -> Result<ThemeImport> {
+        let json = fs::read_to_string(path)?;
+        let file: ThemeFile = serde_json::from_str(&json)
+            .ok()
+            .filter(|file: &ThemeFile| file.format == themes::THEME_FILE_FORMAT)
+            .ok_or_else(|| AppError::validation("path", "not a theme file"))?;
+        if file.format_version > themes::THEME_FILE_VERSION {
+            return Err(AppError::validation(
+                "format_version",
+                format!("unsupported theme file version {}", file.format_version),
+            ));
+        }
+
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut report = ThemeImport::default();
+        for theme in file.themes {
+            let mut theme = match normalize_theme(&theme) {
+                Ok(theme) => theme,
+                Err(e) => {
+                    report.errors.push(ThemeImportError { name: theme.name, message: e.to_string() });
+                    continue;
+                }
+            };
+            if query_theme(&tx, &theme.id).is_ok() {
+                theme.id = uuid::Uuid::new_v4().to_string();
+            }
+            insert_theme(&tx, &theme)?;
+            report.themes.push(saved_theme(theme));
+        }
+        tx.commit()?;
+        Ok(report)
+    }
+
+    /// Creates a custom theme from a VS Code color theme or a Base16
+    /// scheme, reporting which of its keys each color came from.
+    pub fn import_editor_theme(&self, path: &Path) -> Result<EditorThemeImport> {
+        let text = fs::read_to_string(path)?;
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let stem = path.file_stem().and_then(|n| n.to_str()).unwrap_or("Imported theme");
+        let converted = theme_import::convert(file_name, &text, stem)?;
+
+        let theme = normalize_theme(&converted.theme)?;
+        let conn = self.connection.lock().unwrap();
+        insert_theme(&conn, &theme)?;
+        Ok(EditorThemeImport {
+            saved: saved_theme(theme),
+            format: converted.format,
+            sources: converted.sources,
+        })
     }
 
     // Tabs
+    /// A tab created active becomes the only active tab of its workspace.
     pub fn create_tab(&self, tab: &Tab) -> Result<()> {
-        let conn = self.connection.lock().unwrap();
-        conn.execute(
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        if let Some(note_id) = &tab.note_id {
+            ensure_live_note(&tx, note_id)?;
+        }
+        if tab.is_active {
+            clear_active_tab(&tx, &tab.workspace_id)?;
+        }
+        tx.execute(
             "INSERT INTO tabs 
-            (id, workspace_id, title, content, type, position, is_active) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (id, workspace_id, title, content, type, position, is_active, note_id) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             (
                 &tab.id,
                 &tab.workspace_id,
                 &tab.title,
-                &tab.content,
+                tab.content.as_ref().filter(|_| tab.note_id.is_none()),
                 &tab.type_,
                 &tab.position,
                 &tab.is_active,
+                &tab.note_id,
             ),
         )?;
+        tx.commit()?;
         Ok(())
     }
 
     pub fn get_workspace_tabs(&self, workspace_id: &str) -> Result<Vec<Tab>> {
         let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, workspace_id, title, content, type, position, is_active 
-            FROM tabs WHERE workspace_id = ? ORDER BY position"
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} WHERE t.workspace_id = ? ORDER BY t.position, t.rowid",
+            TAB_COLUMNS
+        ))?;
+        let tabs = stmt
+            .query_map([workspace_id], tab_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tabs)
+    }
+
+    /// Saves the tab's title, type, content and note. Binding a note drops
+    /// the stored content. Position and active state are changed with
+    /// `reorder_tabs` and `activate_tab`.
+    pub fn update_tab(&self, tab: &Tab) -> Result<Tab> {
+        let conn = self.connection.lock().unwrap();
+        if let Some(note_id) = &tab.note_id {
+            ensure_live_note(&conn, note_id)?;
+        }
+        let changed = conn.execute(
+            "UPDATE tabs SET title = ?1, type = ?2, content = ?3, note_id = ?4 WHERE id = ?5",
+            (
+                &tab.title,
+                &tab.type_,
+                tab.content.as_ref().filter(|_| tab.note_id.is_none()),
+                &tab.note_id,
+                &tab.id,
+            ),
         )?;
-        
-        let tab_iter = stmt.query_map([workspace_id], |row| {
-            Ok(Tab {
-                id: row.get(0)?,
-                workspace_id: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-                type_: row.get(4)?,
-                position: row.get(5)?,
-                is_active: row.get(6)?,
-            })
-        })?;
+        expect_changed(changed, "tab", &tab.id)?;
+
+        query_tab(&conn, &tab.id)
+    }
 
-        let mut tabs = Vec::new();
-        for tab in tab_iter {
-            tabs.push(tab?);
+    /// Puts the workspace's tabs in `ids` first, in that order, and the
+    /// others after them in their current order. Positions are renumbered
+    /// from 0 in one transaction.
+    pub fn reorder_tabs(&self, workspace_id: &str, ids: &[String]) -> Result<()> {
+        ensure_distinct_ids(ids)?;
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut order = tab_ids(&tx, workspace_id)?;
+        for id in ids {
+            if !order.contains(id) {
+                return Err(AppError::not_found("tab", id));
+            }
         }
-        Ok(tabs)
+        order.retain(|id| !ids.contains(id));
+
+        let order: Vec<String> = ids.iter().chain(&order).cloned().collect();
+        renumber_tabs(&tx, &order)?;
+        tx.commit()?;
+        Ok(())
     }
 
-    // Backup
-    pub fn create_backup(&self, workspace_id: &str, data: &str) -> Result<()> {
+    /// Makes the tab the only active tab of its workspace.
+    pub fn activate_tab(&self, tab_id: &str) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        set_active_tab(&tx, tab_id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Activating a tab deactivates the other tabs of its workspace.
+    pub fn update_tab_active_state(&self, tab_id: &str, is_active: bool) -> Result<()> {
+        if is_active {
+            return self.activate_tab(tab_id);
+        }
         let conn = self.connection.lock().unwrap();
-        conn.execute(
-            "INSERT INTO backups (id, workspace_id, data) VALUES (?1, ?2, ?3)",
-            (&uuid::Uuid::new_v4().to_string(), workspace_id, data),
+        let changed = conn.execute(
+            "UPDATE tabs SET is_active = ?1 WHERE id = ?2",
+            (is_active, tab_id),
+        )?;
+        expect_changed(changed, "tab", tab_id)
+    }
+
+    /// Closes the tab: it goes on its workspace's reopen stack and the tabs
+    /// after it move up. Closing the active tab activates the one taking its
+    /// place, or the one before it when it was last.
+    pub fn delete_tab(&self, tab_id: &str) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let tab = query_tab(&tx, tab_id)?;
+        let mut order = tab_ids(&tx, &tab.workspace_id)?;
+        let index = order.iter().position(|id| id == tab_id).unwrap_or(order.len());
+
+        // Stored content, not the bound note's
+        tx.execute(
+            "INSERT INTO closed_tabs 
+             (tab_id, workspace_id, title, content, type, note_id, position, closed_at) 
+             SELECT id, workspace_id, title, content, type, note_id, ?2, ?3 FROM tabs WHERE id = ?1",
+            (tab_id, index as i64, Utc::now().to_rfc3339()),
+        )?;
+        tx.execute(
+            "DELETE FROM closed_tabs WHERE workspace_id = ?1 AND rowid NOT IN (
+                SELECT rowid FROM closed_tabs WHERE workspace_id = ?1 
+                ORDER BY rowid DESC LIMIT ?2
+             )",
+            (&tab.workspace_id, CLOSED_TAB_LIMIT),
         )?;
+        tx.execute("DELETE FROM tabs WHERE id = ?", [tab_id])?;
+
+        order.retain(|id| id != tab_id);
+        renumber_tabs(&tx, &order)?;
+        if tab.is_active {
+            if let Some(next) = order.get(index.min(order.len().saturating_sub(1))) {
+                set_active_tab(&tx, next)?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
+    pub fn get_closed_tabs(&self, workspace_id: &str) -> Result<Vec<ClosedTab>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM closed_tabs WHERE workspace_id = ? ORDER BY rowid DESC",
+            CLOSED_TAB_COLUMNS
+        ))?;
+        let tabs = stmt
+            .query_map([workspace_id], closed_tab_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tabs)
+    }
+
+    /// Reopens the workspace's most recently closed tab, active and at the
+    /// place it was closed from. `None` when there is nothing to reopen.
+    pub fn reopen_closed_tab(&self, workspace_id: &str) -> Result<Option<Tab>> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let closed = tx
+            .query_row(
+                &format!(
+                    "SELECT {}, rowid FROM closed_tabs WHERE workspace_id = ? 
+                     ORDER BY rowid DESC LIMIT 1",
+                    CLOSED_TAB_COLUMNS
+                ),
+                [workspace_id],
+                |row| Ok((row.get::<_, i64>(8)?, closed_tab_from_row(row)?)),
+            )
+            .optional()?;
+        let Some((rowid, closed)) = closed else {
+            return Ok(None);
+        };
+        tx.execute("DELETE FROM closed_tabs WHERE rowid = ?", [rowid])?;
+
+        let id = free_id(&tx, "tabs", Some(closed.tab_id))?;
+        let mut order = tab_ids(&tx, workspace_id)?;
+        let index = usize::try_from(closed.position).unwrap_or(0).min(order.len());
+        clear_active_tab(&tx, workspace_id)?;
+        tx.execute(
+            "INSERT INTO tabs 
+            (id, workspace_id, title, content, type, position, is_active, note_id) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7)",
+            (
+                &id,
+                workspace_id,
+                &closed.title,
+                &closed.content,
+                &closed.type_,
+                index as i64,
+                &closed.note_id,
+            ),
+        )?;
+        order.insert(index, id.clone());
+        renumber_tabs(&tx, &order)?;
+
+        let tab = query_tab(&tx, &id)?;
+        tx.commit()?;
+        Ok(Some(tab))
+    }
+
+    // Backup
+    pub fn create_backup(&self, workspace_id: &str, data: &str) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+        conn.execute(
+            "INSERT INTO backups (id, workspace_id, data) VALUES (?1, ?2, ?3)",
+            (&uuid::Uuid::new_v4().to_string(), workspace_id, data),
+        )?;
+        Ok(())
+    }
+
+    /// Captures the workspace, its settings, tabs and profiles as a
+    /// restorable backup.
+    pub fn create_workspace_backup(&self, workspace_id: &str) -> Result<BackupInfo> {
+        let conn = self.connection.lock().unwrap();
+        let snapshot = read_workspace_snapshot(&conn, workspace_id)?;
+        let id = insert_backup(&conn, workspace_id, &snapshot, "snapshot")?;
+        query_backup_info(&conn, &id)
+    }
+
+    pub fn list_backups(&self, workspace_id: &str) -> Result<Vec<BackupInfo>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, workspace_id, kind, length(CAST(data AS BLOB)), created_at 
+             FROM backups WHERE workspace_id = ? ORDER BY created_at DESC, rowid DESC"
+        )?;
+
+        let backups = stmt.query_map([workspace_id], |row| {
+            Ok(BackupInfo {
+                id: row.get(0)?,
+                workspace_id: row.get(1)?,
+                kind: row.get(2)?,
+                size_bytes: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(backups)
+    }
+
+    pub fn get_backup(&self, id: &str) -> Result<Backup> {
+        let conn = self.connection.lock().unwrap();
+        let info = query_backup_info(&conn, id)?;
+        let data: String = conn
+            .query_row("SELECT data FROM backups WHERE id = ?", [id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AppError::not_found("backup", id))?;
+        let snapshot = serde_json::from_str(&data).ok();
+        Ok(Backup { info, data, snapshot })
+    }
+
+    /// Replaces the workspace's row, settings, tabs and profiles with the
+    /// backup's contents in one transaction. The current state is first saved as a
+    /// "pre-restore" backup, whose info is returned so the restore can be
+    /// undone. Only the workspace the backup was taken of is restored.
+    pub fn restore_backup(&self, id: &str) -> Result<BackupInfo> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let (workspace_id, data): (String, String) = tx
+            .query_row("SELECT workspace_id, data FROM backups WHERE id = ?", [id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?
+            .ok_or_else(|| AppError::not_found("backup", id))?;
+        let snapshot: WorkspaceSnapshot = serde_json::from_str(&data)
+            .map_err(|_| AppError::validation("data", "backup is not a workspace snapshot"))?;
+        if snapshot.format_version > WORKSPACE_SNAPSHOT_VERSION {
+            return Err(AppError::validation(
+                "format_version",
+                format!("unsupported snapshot version {}", snapshot.format_version),
+            ));
+        }
+        if snapshot.workspace.id != workspace_id {
+            return Err(AppError::validation(
+                "workspace",
+                format!("backup of {} holds workspace {}", workspace_id, snapshot.workspace.id),
+            ));
+        }
+        let workspace_id = workspace_id.as_str();
+
+        let current = read_workspace_snapshot(&tx, workspace_id)?;
+        let safety_id = insert_backup(&tx, workspace_id, &current, "pre-restore")?;
+
+        let workspace = &snapshot.workspace;
+        tx.execute(
+            "UPDATE workspaces SET name = ?1, type = ?2, theme_id = ?3 WHERE id = ?4",
+            (&workspace.name, &workspace.type_, &workspace.theme_id, &workspace.id),
+        )?;
+        if snapshot.format_version >= 2 {
+            tx.execute("DELETE FROM workspace_profiles WHERE workspace_id = ?", [workspace_id])?;
+            for profile in &snapshot.profiles {
+                tx.execute(
+                    "INSERT INTO workspace_profiles 
+                     (id, workspace_id, name, position, theme, settings, created_at, updated_at) 
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    (
+                        &profile.id,
+                        workspace_id,
+                        &profile.name,
+                        profile.position,
+                        to_json(&profile.theme)?,
+                        to_json(&profile.settings)?,
+                        &profile.created_at,
+                        &profile.updated_at,
+                    ),
+                )?;
+            }
+            tx.execute(
+                "UPDATE workspaces SET path = ?1, current_profile_id = ?2 WHERE id = ?3",
+                (&workspace.path, &workspace.current_profile_id, workspace_id),
+            )?;
+        }
+
+        tx.execute("DELETE FROM workspace_settings WHERE workspace_id = ?", [workspace_id])?;
+        if let Some(settings) = &snapshot.settings {
+            tx.execute(
+                "INSERT INTO workspace_settings 
+                (workspace_id, dark_mode, split_view, privacy_level, auto_save) 
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    workspace_id,
+                    &settings.dark_mode,
+                    &settings.split_view,
+                    &settings.privacy_level,
+                    &settings.auto_save,
+                ),
+            )?;
+        }
+
+        // Older snapshots may have several active tabs; the first one stays
+        // active. Tabs whose note is gone keep the content they were saved
+        // with.
+        let active = snapshot.tabs.iter().find(|tab| tab.is_active).map(|tab| &tab.id);
+        tx.execute("DELETE FROM tabs WHERE workspace_id = ?", [workspace_id])?;
+        for tab in &snapshot.tabs {
+            tx.execute(
+                "INSERT INTO tabs 
+                (id, workspace_id, title, content, type, position, is_active, note_id) 
+                VALUES (?1, ?2, ?3, 
+                        CASE WHEN ?8 IN (SELECT id FROM notes) THEN NULL ELSE ?4 END, 
+                        ?5, ?6, ?7, (SELECT id FROM notes WHERE id = ?8))",
+                (
+                    &tab.id,
+                    workspace_id,
+                    &tab.title,
+                    &tab.content,
+                    &tab.type_,
+                    &tab.position,
+                    active == Some(&tab.id),
+                    &tab.note_id,
+                ),
+            )?;
+        }
+
+        let safety = query_backup_info(&tx, &safety_id)?;
+        tx.commit()?;
+        Ok(safety)
+    }
+
+    pub fn delete_backup(&self, id: &str) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+        let changed = conn.execute("DELETE FROM backups WHERE id = ?", [id])?;
+        expect_changed(changed, "backup", id)
+    }
+
+    /// Deletes all but the `keep` most recent backups of a workspace and
+    /// returns how many were removed.
+    pub fn prune_backups(&self, workspace_id: &str, keep: u32) -> Result<usize> {
+        let conn = self.connection.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM backups WHERE workspace_id = ?1 AND id NOT IN (
+                SELECT id FROM backups WHERE workspace_id = ?1 
+                ORDER BY created_at DESC, rowid DESC LIMIT ?2
+             )",
+            (workspace_id, keep),
+        )?;
+        Ok(removed)
+    }
+
     // Notes
+    /// Creates the note in its project, which must not be in the trash.
     pub fn create_note(&self, note: &Note) -> Result<Note> {
-        let conn = self.connection.lock().unwrap();
+        let mut conn = self.connection.lock().unwrap();
         let now = Utc::now().to_rfc3339();
         
         let mut note = note.clone();
         note.created_at = now.clone();
         note.updated_at = now;
 
-        conn.execute(
+        let tx = conn.transaction()?;
+        query_project(&tx, &note.project_id)?;
+        tx.execute(
             "INSERT INTO notes (id, title, content, project_id, created_at, updated_at, is_pinned) 
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             (
@@ -255,6 +1452,9 @@ impl DbState {
                 &note.is_pinned,
             ),
         )?;
+        record_revision(&tx, &note.id, &note.title, &note.content, "create")?;
+        index_content(&tx, &note.id, &note.content)?;
+        tx.commit()?;
 
         Ok(note)
     }
@@ -263,7 +1463,7 @@ impl DbState {
         let conn = self.connection.lock().unwrap();
         let mut stmt = conn.prepare(
             "SELECT id, title, content, project_id, created_at, updated_at, is_pinned 
-             FROM notes WHERE project_id = ?"
+             FROM notes WHERE project_id = ? AND deleted_at IS NULL"
         )?;
         
         let notes = stmt.query_map([project_id], |row| {
@@ -277,19 +1477,83 @@ impl DbState {
                 is_pinned: row.get(6)?,
             })
         })?
-        .collect::<Result<Vec<_>>>()?;
+        .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(notes)
     }
 
-    pub fn update_note(&self, note: &Note) -> Result<Note> {
+    pub fn get_note(&self, id: &str) -> Result<Note> {
+        let conn = self.connection.lock().unwrap();
+        conn.query_row(
+            "SELECT id, title, content, project_id, created_at, updated_at, is_pinned 
+             FROM notes WHERE id = ? AND deleted_at IS NULL",
+            [id],
+            |row| {
+                Ok(Note {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    content: row.get(2)?,
+                    project_id: row.get(3)?,
+                    created_at: row.get(4)?,
+                    updated_at: row.get(5)?,
+                    is_pinned: row.get(6)?,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("note", id))
+    }
+
+    /// Notes tagged `tag_id`, or with `include_descendants` also those
+    /// tagged with any tag below it.
+    pub fn get_notes_by_tag(&self, tag_id: &str, include_descendants: bool) -> Result<Vec<Note>> {
         let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} SELECT n.id, n.title, n.content, n.project_id, n.created_at, n.updated_at, n.is_pinned 
+             FROM notes n 
+             WHERE n.deleted_at IS NULL AND EXISTS (
+                SELECT 1 FROM note_tags nt 
+                WHERE nt.note_id = n.id AND (nt.tag_id = ?1 OR (?2 AND nt.tag_id IN subtree))
+             )",
+            TAG_SUBTREE
+        ))?;
+        
+        let notes = stmt.query_map((tag_id, include_descendants), |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get(2)?,
+                project_id: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+                is_pinned: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(notes)
+    }
+
+    /// Saves the note. When the title changes, wiki links to the old title
+    /// that resolved to this note are rewritten to the new one in the same
+    /// transaction, including links the note makes to itself. Notes in the
+    /// trash are not found.
+    pub fn update_note(&self, note: &Note) -> Result<Note> {
+        let mut conn = self.connection.lock().unwrap();
         let now = Utc::now().to_rfc3339();
         
         let mut updated_note = note.clone();
-        updated_note.updated_at = now;
+        updated_note.updated_at = now.clone();
 
-        conn.execute(
+        let tx = conn.transaction()?;
+        let old_title = live_note_title(&tx, &updated_note.id)?;
+        let rewrites = plan_link_rewrites(&tx, &updated_note.id, &old_title, &updated_note.title)?;
+        if renames(&old_title, &updated_note.title) {
+            updated_note.content =
+                links::rewrite_wiki_target(&updated_note.content, &old_title, &updated_note.title).0;
+        }
+
+        let changed = tx.execute(
             "UPDATE notes SET title = ?1, content = ?2, is_pinned = ?3, updated_at = ?4 
              WHERE id = ?5",
             (
@@ -300,117 +1564,394 @@ impl DbState {
                 &updated_note.id,
             ),
         )?;
+        expect_changed(changed, "note", &updated_note.id)?;
+        record_revision(&tx, &updated_note.id, &updated_note.title, &updated_note.content, "edit")?;
+        index_content(&tx, &updated_note.id, &updated_note.content)?;
+        apply_link_rewrites(&tx, &rewrites, &now)?;
+        tx.commit()?;
 
         Ok(updated_note)
     }
 
     pub fn delete_note(&self, id: &str) -> Result<()> {
         let conn = self.connection.lock().unwrap();
-        conn.execute("DELETE FROM notes WHERE id = ?", [id])?;
-        Ok(())
+        let changed = conn.execute(
+            "UPDATE notes SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            (Utc::now().to_rfc3339(), id),
+        )?;
+        expect_changed(changed, "note", id)
+    }
+
+    // Note Revisions
+    pub fn get_note_revisions(&self, note_id: &str) -> Result<Vec<NoteRevisionSummary>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, title, length(COALESCE(content, '')), kind, created_at, updated_at 
+             FROM note_revisions WHERE note_id = ? ORDER BY created_at DESC, rowid DESC"
+        )?;
+        
+        let revisions = stmt.query_map([note_id], |row| {
+            Ok(NoteRevisionSummary {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                title: row.get(2)?,
+                content_length: row.get(3)?,
+                kind: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(revisions)
+    }
+
+    pub fn get_note_revision(&self, id: &str) -> Result<NoteRevision> {
+        let conn = self.connection.lock().unwrap();
+        Self::query_note_revision(&conn, id)
+    }
+
+    fn query_note_revision(conn: &Connection, id: &str) -> Result<NoteRevision> {
+        conn.query_row(
+            "SELECT id, note_id, title, COALESCE(content, ''), kind, created_at, updated_at 
+             FROM note_revisions WHERE id = ?",
+            [id],
+            |row| {
+                Ok(NoteRevision {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    title: row.get(2)?,
+                    content: row.get(3)?,
+                    kind: row.get(4)?,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("revision", id))
+    }
+
+    pub fn diff_note_revisions(&self, from_id: &str, to_id: &str) -> Result<Vec<DiffLine>> {
+        let conn = self.connection.lock().unwrap();
+        let from = Self::query_note_revision(&conn, from_id)?;
+        let to = Self::query_note_revision(&conn, to_id)?;
+        Ok(diff::diff_lines(&from.content, &to.content))
+    }
+
+    /// Makes a revision the note's current content. The restore is itself
+    /// recorded as a fresh revision so it can be undone the same way. Notes
+    /// in the trash have to be restored from it first.
+    pub fn restore_note_revision(&self, revision_id: &str) -> Result<Note> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let revision = Self::query_note_revision(&tx, revision_id)?;
+        let now = Utc::now().to_rfc3339();
+        let old_title = live_note_title(&tx, &revision.note_id)?;
+        let rewrites = plan_link_rewrites(&tx, &revision.note_id, &old_title, &revision.title)?;
+
+        tx.execute(
+            "UPDATE notes SET title = ?1, content = ?2, updated_at = ?3 WHERE id = ?4",
+            (&revision.title, &revision.content, &now, &revision.note_id),
+        )?;
+        record_revision(&tx, &revision.note_id, &revision.title, &revision.content, "restore")?;
+        index_content(&tx, &revision.note_id, &revision.content)?;
+        apply_link_rewrites(&tx, &rewrites, &now)?;
+
+        let note = tx.query_row(
+            "SELECT id, title, content, project_id, created_at, updated_at, is_pinned 
+             FROM notes WHERE id = ?",
+            [&revision.note_id],
+            |row| {
+                Ok(Note {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    content: row.get(2)?,
+                    project_id: row.get(3)?,
+                    created_at: row.get(4)?,
+                    updated_at: row.get(5)?,
+                    is_pinned: row.get(6)?,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("note", &revision.note_id))?;
+        tx.commit()?;
+
+        Ok(note)
     }
 
     // Projects
+    /// Creates the project at the end of the list. New projects are never
+    /// archived; `archive_project` does that.
     pub fn create_project(&self, project: &Project) -> Result<Project> {
+        validate_name("name", &project.name)?;
         let conn = self.connection.lock().unwrap();
         let now = Utc::now().to_rfc3339();
         
-        let mut project = project.clone();
+        let mut project = normalize_project(project)?;
         project.created_at = now.clone();
         project.updated_at = now;
+        project.position = next_project_position(&conn)?;
+        project.archived = false;
 
         conn.execute(
-            "INSERT INTO projects (id, name, created_at, updated_at) 
-             VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO projects (id, name, created_at, updated_at, description, color, icon, position, archived) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, FALSE)",
             (
                 &project.id,
                 &project.name,
                 &project.created_at,
                 &project.updated_at,
+                &project.description,
+                &project.color,
+                &project.icon,
+                project.position,
             ),
         )?;
 
         Ok(project)
     }
 
-    pub fn get_projects(&self) -> Result<Vec<Project>> {
+    /// Projects in list order, without archived ones unless
+    /// `include_archived`.
+    pub fn get_projects(&self, include_archived: bool) -> Result<Vec<ProjectSummary>> {
         let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, created_at, updated_at FROM projects")?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, 
+                (SELECT COUNT(*) FROM notes n WHERE n.project_id = p.id AND n.deleted_at IS NULL), 
+                (SELECT COUNT(*) FROM tasks t INNER JOIN notes n ON n.id = t.note_id 
+                 WHERE n.project_id = p.id AND n.deleted_at IS NULL), 
+                (SELECT COUNT(*) FROM tasks t INNER JOIN notes n ON n.id = t.note_id 
+                 WHERE n.project_id = p.id AND n.deleted_at IS NULL AND NOT t.completed) 
+             FROM projects p 
+             WHERE p.deleted_at IS NULL AND (?1 OR NOT p.archived) 
+             ORDER BY p.position, p.created_at",
+            PROJECT_COLUMNS
+        ))?;
         
-        let projects = stmt.query_map([], |row| {
-            Ok(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get(2)?,
-                updated_at: row.get(3)?,
-            })
-        })?
-        .collect::<Result<Vec<_>>>()?;
+        let projects = stmt
+            .query_map([include_archived], |row| {
+                Ok(ProjectSummary {
+                    project: project_from_row(row)?,
+                    note_count: row.get(9)?,
+                    task_count: row.get(10)?,
+                    open_task_count: row.get(11)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(projects)
     }
 
+    pub fn get_project(&self, id: &str) -> Result<Project> {
+        let conn = self.connection.lock().unwrap();
+        query_project(&conn, id)
+    }
+
+    /// Saves the name, description, color and icon. The position and the
+    /// archived flag have their own commands and are left as they are.
     pub fn update_project(&self, project: &Project) -> Result<Project> {
+        validate_name("name", &project.name)?;
         let conn = self.connection.lock().unwrap();
         let now = Utc::now().to_rfc3339();
         
-        let mut updated_project = project.clone();
-        updated_project.updated_at = now;
+        let project = normalize_project(project)?;
 
-        conn.execute(
-            "UPDATE projects SET name = ?1, updated_at = ?2 WHERE id = ?3",
+        let changed = conn.execute(
+            "UPDATE projects SET name = ?1, updated_at = ?2, description = ?3, color = ?4, icon = ?5 
+             WHERE id = ?6 AND deleted_at IS NULL",
             (
-                &updated_project.name,
-                &updated_project.updated_at,
-                &updated_project.id,
+                &project.name,
+                &now,
+                &project.description,
+                &project.color,
+                &project.icon,
+                &project.id,
             ),
         )?;
+        expect_changed(changed, "project", &project.id)?;
 
-        Ok(updated_project)
+        query_project(&conn, &project.id)
     }
 
-    pub fn delete_project(&self, id: &str) -> Result<()> {
+    /// Puts the projects in `ids` first, in that order; the others follow
+    /// in their current order.
+    pub fn reorder_projects(&self, ids: &[String]) -> Result<()> {
+        ensure_distinct_ids(ids)?;
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut order: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM projects WHERE deleted_at IS NULL ORDER BY position, created_at",
+            )?;
+            let rows = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+        for id in ids {
+            if !order.contains(id) {
+                return Err(AppError::not_found("project", id));
+            }
+        }
+        order.retain(|id| !ids.contains(id));
+
+        for (position, id) in ids.iter().chain(&order).enumerate() {
+            tx.execute(
+                "UPDATE projects SET position = ?1 WHERE id = ?2",
+                (position as i64, id),
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn archive_project(&self, id: &str) -> Result<Project> {
+        self.set_project_archived(id, true)
+    }
+
+    pub fn unarchive_project(&self, id: &str) -> Result<Project> {
+        self.set_project_archived(id, false)
+    }
+
+    fn set_project_archived(&self, id: &str, archived: bool) -> Result<Project> {
         let conn = self.connection.lock().unwrap();
-        conn.execute("DELETE FROM projects WHERE id = ?", [id])?;
+        let changed = conn.execute(
+            "UPDATE projects SET archived = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
+            (archived, Utc::now().to_rfc3339(), id),
+        )?;
+        expect_changed(changed, "project", id)?;
+        query_project(&conn, id)
+    }
+
+    pub fn delete_project(&self, id: &str) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        let tx = conn.transaction()?;
+        let changed = tx.execute(
+            "UPDATE projects SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            (&now, id),
+        )?;
+        expect_changed(changed, "project", id)?;
+        tx.execute(
+            "UPDATE notes SET deleted_at = ?1, deleted_with_project = ?2 
+             WHERE project_id = ?2 AND deleted_at IS NULL",
+            (&now, id),
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
     // Tags
+    /// Creates the tag, and its missing parents when the name is a path.
     pub fn create_tag(&self, tag: &Tag) -> Result<Tag> {
-        let conn = self.connection.lock().unwrap();
+        let mut conn = self.connection.lock().unwrap();
         let now = Utc::now().to_rfc3339();
         
         let mut tag = tag.clone();
+        tag.name = tag_path(&tag.name)?;
+        tag.color = optional_color("color", tag.color.as_deref())?;
         tag.created_at = now;
 
-        conn.execute(
-            "INSERT INTO tags (id, name, created_at) VALUES (?1, ?2, ?3)",
-            (&tag.id, &tag.name, &tag.created_at),
+        let tx = conn.transaction()?;
+        ensure_tag_name_free(&tx, &tag.name, None)?;
+        tag.parent_id = tag_parent(&tx, &tag.name)?;
+        tx.execute(
+            "INSERT INTO tags (id, name, created_at, color, description, parent_id) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (&tag.id, &tag.name, &tag.created_at, &tag.color, &tag.description, &tag.parent_id),
         )?;
+        tx.commit()?;
 
         Ok(tag)
     }
 
     pub fn get_tags(&self) -> Result<Vec<Tag>> {
         let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, created_at FROM tags")?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tags t WHERE t.deleted_at IS NULL ORDER BY t.name COLLATE NOCASE",
+            TAG_COLUMNS
+        ))?;
         
-        let tags = stmt.query_map([], |row| {
-            Ok(Tag {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get(2)?,
-            })
-        })?
-        .collect::<Result<Vec<_>>>()?;
+        let tags = stmt
+            .query_map([], tag_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(tags)
     }
 
+    /// Saves the tag's color and description, and renames it like
+    /// `rename_tag` if the name changed.
+    pub fn update_tag(&self, tag: &Tag) -> Result<Tag> {
+        let mut conn = self.connection.lock().unwrap();
+        let color = optional_color("color", tag.color.as_deref())?;
+        let tx = conn.transaction()?;
+        let changed = tx.execute(
+            "UPDATE tags SET color = ?1, description = ?2 WHERE id = ?3 AND deleted_at IS NULL",
+            (&color, &tag.description, &tag.id),
+        )?;
+        expect_changed(changed, "tag", &tag.id)?;
+        rename_tag_subtree(&tx, &tag.id, &tag_path(&tag.name)?)?;
+        let tag = query_tag(&tx, &tag.id)?;
+        tx.commit()?;
+
+        Ok(tag)
+    }
+
+    /// Renames the tag and rewrites the paths of the tags below it. A new
+    /// path under another parent moves the tag there.
+    pub fn rename_tag(&self, id: &str, name: &str) -> Result<Tag> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        rename_tag_subtree(&tx, id, &tag_path(name)?)?;
+        let tag = query_tag(&tx, id)?;
+        tx.commit()?;
+        Ok(tag)
+    }
+
+    /// Moves the tag, with the tags below it, under `parent_id`, or to the
+    /// top level when `None`.
+    pub fn move_tag(&self, id: &str, parent_id: Option<&str>) -> Result<Tag> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let tag = query_tag(&tx, id)?;
+        let leaf = tag.name.rsplit('/').next().unwrap_or_default();
+        let path = match parent_id {
+            Some(parent_id) => format!("{}/{}", query_tag(&tx, parent_id)?.name, leaf),
+            None => leaf.to_string(),
+        };
+        rename_tag_subtree(&tx, id, &path)?;
+        let tag = query_tag(&tx, id)?;
+        tx.commit()?;
+        Ok(tag)
+    }
+
+    /// Folds `source_id` into `target_id`: its notes get the target tag, the
+    /// tags below it move under the target (merging with same-named ones
+    /// there), and the source tag is deleted. The target keeps its color and
+    /// description, taking the source's where it has none.
+    pub fn merge_tags(&self, source_id: &str, target_id: &str) -> Result<Tag> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        merge_tag(&tx, source_id, target_id)?;
+        let tag = query_tag(&tx, target_id)?;
+        tx.commit()?;
+        Ok(tag)
+    }
+
+    /// Moves the tag and the tags below it to the trash.
     pub fn delete_tag(&self, id: &str) -> Result<()> {
         let conn = self.connection.lock().unwrap();
-        conn.execute("DELETE FROM tags WHERE id = ?", [id])?;
-        Ok(())
+        let changed = conn.execute(
+            &format!(
+                "{} UPDATE tags SET deleted_at = ?2 WHERE id IN subtree AND deleted_at IS NULL",
+                TAG_SUBTREE
+            ),
+            (id, Utc::now().to_rfc3339()),
+        )?;
+        expect_changed(changed, "tag", id)
     }
 
     // Tasks
@@ -418,13 +1959,16 @@ impl DbState {
         let conn = self.connection.lock().unwrap();
         let now = Utc::now().to_rfc3339();
         
-        let mut task = task.clone();
+        let mut task = normalize_task(task)?;
         task.created_at = now.clone();
-        task.updated_at = now;
+        task.updated_at = now.clone();
+        task.completed_at = task.completed.then_some(now);
+        task.series_id = None;
 
         conn.execute(
-            "INSERT INTO tasks (id, content, completed, note_id, created_at, updated_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO tasks (id, content, completed, note_id, created_at, updated_at, 
+             due_date, priority, completed_at, assignee, recurrence) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             (
                 &task.id,
                 &task.content,
@@ -432,6 +1976,11 @@ impl DbState {
                 &task.note_id,
                 &task.created_at,
                 &task.updated_at,
+                &task.due_date,
+                task.priority.rank(),
+                &task.completed_at,
+                &task.assignee,
+                &task.recurrence,
             ),
         )?;
 
@@ -440,55 +1989,204 @@ impl DbState {
 
     pub fn get_tasks(&self, note_id: &str) -> Result<Vec<Task>> {
         let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, content, completed, note_id, created_at, updated_at 
-             FROM tasks WHERE note_id = ?"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tasks t WHERE t.note_id = ? 
+             ORDER BY t.checkbox_index IS NULL, t.checkbox_index, t.created_at, t.rowid",
+            TASK_COLUMNS
+        ))?;
         
-        let tasks = stmt.query_map([note_id], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                completed: row.get(2)?,
-                note_id: row.get(3)?,
-                created_at: row.get(4)?,
-                updated_at: row.get(5)?,
-            })
-        })?
-        .collect::<Result<Vec<_>>>()?;
+        let tasks = stmt
+            .query_map([note_id], task_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(tasks)
     }
 
-    pub fn update_task(&self, task: &Task) -> Result<Task> {
+    pub fn get_all_tasks(&self) -> Result<Vec<Task>> {
         let conn = self.connection.lock().unwrap();
-        let now = Utc::now().to_rfc3339();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tasks t 
+             INNER JOIN notes n ON n.id = t.note_id 
+             WHERE n.deleted_at IS NULL 
+             ORDER BY t.created_at",
+            TASK_COLUMNS
+        ))?;
         
-        let mut updated_task = task.clone();
-        updated_task.updated_at = now;
+        let tasks = stmt
+            .query_map([], task_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
 
-        conn.execute(
-            "UPDATE tasks SET content = ?1, completed = ?2, updated_at = ?3 WHERE id = ?4",
+        Ok(tasks)
+    }
+
+    /// Tasks of all notes outside the trash matching `query`. Due ranges are
+    /// evaluated in the local time zone.
+    pub fn query_tasks(&self, query: &TaskQuery) -> Result<Vec<TaskListItem>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, n.title, n.project_id FROM tasks t 
+             INNER JOIN notes n ON n.id = t.note_id 
+             WHERE n.deleted_at IS NULL 
+             AND (?1 IS NULL OR t.completed = ?1) 
+             AND (?2 IS NULL OR n.project_id = ?2) 
+             AND (?3 IS NULL OR EXISTS (
+                SELECT 1 FROM note_tags nt WHERE nt.note_id = n.id AND nt.tag_id = ?3
+             )) 
+             ORDER BY t.created_at, t.rowid",
+            TASK_COLUMNS
+        ))?;
+        let completed = match query.status {
+            TaskStatus::All => None,
+            TaskStatus::Open => Some(false),
+            TaskStatus::Completed => Some(true),
+        };
+        let mut items = stmt
+            .query_map((completed, &query.project_id, &query.tag_id), |row| {
+                Ok(TaskListItem {
+                    task: task_from_row(row)?,
+                    note_title: row.get(12)?,
+                    project_id: row.get(13)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let now = Local::now();
+        if let Some(range) = query.due {
+            items.retain(|item| tasks::in_range(item.task.due_date.as_deref(), item.task.completed, range, &now));
+        }
+        items.sort_by(|a, b| {
+            tasks::compare(
+                query.sort,
+                (a.task.due_date.as_deref(), a.task.priority),
+                (b.task.due_date.as_deref(), b.task.priority),
+                &Local,
+            )
+        });
+        Ok(items)
+    }
+
+    /// Every occurrence of the recurring series `task_id` belongs to, oldest
+    /// first; just the task itself if it does not repeat.
+    pub fn get_task_occurrences(&self, task_id: &str) -> Result<Vec<Task>> {
+        let conn = self.connection.lock().unwrap();
+        let series: String = conn
+            .query_row("SELECT COALESCE(series_id, id) FROM tasks WHERE id = ?", [task_id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AppError::not_found("task", task_id))?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tasks t WHERE COALESCE(t.series_id, t.id) = ? ORDER BY t.created_at, t.rowid",
+            TASK_COLUMNS
+        ))?;
+        let tasks = stmt
+            .query_map([series], task_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tasks)
+    }
+
+    /// Saves the task. A task bound to a checkbox also ticks, unticks or
+    /// rewrites that checkbox in the note, unless the note is in the trash.
+    /// `updated_at` must be the value the task was read with: when it no
+    /// longer matches the stored one, the task changed since, typically
+    /// through an edit of its note, and the update is refused.
+    /// Completing a recurring task adds its next occurrence as a new task.
+    pub fn update_task(&self, task: &Task) -> Result<Task> {
+        let mut conn = self.connection.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        if task.updated_at.is_empty() {
+            return Err(AppError::validation("updated_at", "the task's last known update time is required"));
+        }
+
+        let tx = conn.transaction()?;
+        let (note_id, bound) = task_binding(&tx, &task.id)?;
+        let (stored_updated_at, was_completed): (Option<String>, bool) = tx.query_row(
+            "SELECT updated_at, completed FROM tasks WHERE id = ?",
+            [&task.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if stored_updated_at.as_deref() != Some(task.updated_at.as_str()) {
+            return Err(AppError::Conflict {
+                message: format!("task {} changed since it was loaded; reload it and try again", task.id),
+            });
+        }
+
+        let task = normalize_task(task)?;
+        let mut content = task.content.clone();
+        // A note in the trash is left as it is, and the task keeps its
+        // checkbox for when the note comes back
+        let mut binding = bound.as_ref().map(|(index, hash)| (*index as i64, hash.clone()));
+        if let (Some((index, hash)), Some((title, note_content))) = (bound, live_note_text(&tx, &note_id)?) {
+            let checkboxes = checklist::checkboxes(&note_content);
+            let position = checklist::find(&checkboxes, index, &hash).ok_or_else(|| checkbox_conflict(&task.id))?;
+            let rewritten = checklist::rewrite(&note_content, &checkboxes[position], task.completed, &task.content);
+            content = checklist::checkboxes(&rewritten)[position].text.clone();
+            binding = Some((position as i64, checklist::text_hash(&content)));
+
+            if rewritten != note_content {
+                tx.execute(
+                    "UPDATE notes SET content = ?1, updated_at = ?2 WHERE id = ?3",
+                    (&rewritten, &now, &note_id),
+                )?;
+                record_revision(&tx, &note_id, &title, &rewritten, "edit")?;
+                index_links(&tx, &note_id, &rewritten)?;
+            }
+        }
+
+        tx.execute(
+            "UPDATE tasks SET content = ?1, completed = ?2, updated_at = ?3, 
+             completed_at = CASE WHEN ?2 THEN COALESCE(completed_at, ?3) END, 
+             checkbox_index = ?4, checkbox_hash = ?5, 
+             due_date = ?6, priority = ?7, assignee = ?8, recurrence = ?9 WHERE id = ?10",
             (
-                &updated_task.content,
-                &updated_task.completed,
-                &updated_task.updated_at,
-                &updated_task.id,
+                &content,
+                &task.completed,
+                &now,
+                binding.as_ref().map(|(index, _)| index),
+                binding.as_ref().map(|(_, hash)| hash),
+                &task.due_date,
+                task.priority.rank(),
+                &task.assignee,
+                &task.recurrence,
+                &task.id,
             ),
         )?;
+        if task.completed && !was_completed {
+            schedule_next_occurrence(&tx, &task.id)?;
+        }
+        let updated_task = query_task(&tx, &task.id)?;
+        tx.commit()?;
 
         Ok(updated_task)
     }
 
+    /// Deletes the task, and the line of its checkbox if it has one and the
+    /// note is not in the trash.
     pub fn delete_task(&self, id: &str) -> Result<()> {
-        let conn = self.connection.lock().unwrap();
-        conn.execute("DELETE FROM tasks WHERE id = ?", [id])?;
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let (note_id, binding) = task_binding(&tx, id)?;
+        tx.execute("DELETE FROM tasks WHERE id = ?", [id])?;
+        if let (Some((index, hash)), Some((title, content))) = (binding, live_note_text(&tx, &note_id)?) {
+            let checkboxes = checklist::checkboxes(&content);
+            let position = checklist::find(&checkboxes, index, &hash).ok_or_else(|| checkbox_conflict(id))?;
+            let rewritten = checklist::remove(&content, &checkboxes[position]);
+            tx.execute(
+                "UPDATE notes SET content = ?1, updated_at = ?2 WHERE id = ?3",
+                (&rewritten, Utc::now().to_rfc3339(), &note_id),
+            )?;
+            record_revision(&tx, &note_id, &title, &rewritten, "edit")?;
+            index_content(&tx, &note_id, &rewritten)?;
+        }
+        tx.commit()?;
         Ok(())
     }
 
     // Note Tags
+    /// Tags the note. Both have to exist outside the trash.
     pub fn add_tag_to_note(&self, note_id: &str, tag_id: &str) -> Result<()> {
         let conn = self.connection.lock().unwrap();
+        ensure_live_note(&conn, note_id)?;
+        query_tag(&conn, tag_id)?;
         conn.execute(
             "INSERT INTO note_tags (note_id, tag_id) VALUES (?1, ?2)",
             (note_id, tag_id),
@@ -498,31 +2196,2589 @@ impl DbState {
 
     pub fn remove_tag_from_note(&self, note_id: &str, tag_id: &str) -> Result<()> {
         let conn = self.connection.lock().unwrap();
-        conn.execute(
+        let changed = conn.execute(
             "DELETE FROM note_tags WHERE note_id = ?1 AND tag_id = ?2",
             (note_id, tag_id),
         )?;
-        Ok(())
+        expect_changed(changed, "note tag", &format!("{}/{}", note_id, tag_id))
     }
 
     pub fn get_note_tags(&self, note_id: &str) -> Result<Vec<Tag>> {
         let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT t.id, t.name, t.created_at 
-             FROM tags t 
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tags t 
              INNER JOIN note_tags nt ON t.id = nt.tag_id 
-             WHERE nt.note_id = ?"
-        )?;
+             WHERE nt.note_id = ? AND t.deleted_at IS NULL",
+            TAG_COLUMNS
+        ))?;
         
-        let tags = stmt.query_map([note_id], |row| {
-            Ok(Tag {
+        let tags = stmt
+            .query_map([note_id], tag_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(tags)
+    }
+
+    // Search
+    pub fn search_notes(
+        &self,
+        query: &str,
+        project_id: Option<&str>,
+        tag_id: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SearchHit>> {
+        let fts_query = match search::to_fts_query(query) {
+            Some(q) => q,
+            None => return Ok(Vec::new()),
+        };
+
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.project_id,
+                    highlight(notes_fts, 1, ?5, ?6),
+                    highlight(notes_fts, 2, ?5, ?6),
+                    snippet(notes_fts, 2, ?5, ?6, '…', 24),
+                    bm25(notes_fts, 0.0, 10.0, 1.0) AS rank
+             FROM notes_fts
+             INNER JOIN notes n ON n.id = notes_fts.note_id
+             WHERE notes_fts MATCH ?1
+               AND n.deleted_at IS NULL
+               AND (?2 IS NULL OR n.project_id = ?2)
+               AND (?3 IS NULL OR EXISTS (
+                    SELECT 1 FROM note_tags nt WHERE nt.note_id = n.id AND nt.tag_id = ?3))
+             ORDER BY rank
+             LIMIT ?4"
+        )?;
+
+        let hits = stmt.query_map(
+            (
+                &fts_query,
+                project_id,
+                tag_id,
+                limit,
+                MATCH_OPEN.to_string(),
+                MATCH_CLOSE.to_string(),
+            ),
+            |row| {
+                let (title, title_matches) = search::parse_marked(&row.get::<_, String>(2)?);
+                let (_, content_matches) = search::parse_marked(&row.get::<_, String>(3)?);
+                Ok(SearchHit {
+                    note_id: row.get(0)?,
+                    project_id: row.get(1)?,
+                    title,
+                    snippet: search::marked_to_html(&row.get::<_, String>(4)?),
+                    score: -row.get::<_, f64>(5)?,
+                    title_matches,
+                    content_matches,
+                })
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(hits)
+    }
+
+    // Trash
+    pub fn get_trash(&self) -> Result<Vec<TrashItem>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, 'note', title, project_id, deleted_at, 0 
+             FROM notes WHERE deleted_at IS NOT NULL AND deleted_with_project IS NULL
+             UNION ALL
+             SELECT p.id, 'project', p.name, NULL, p.deleted_at,
+                    (SELECT COUNT(*) FROM notes n WHERE n.deleted_with_project = p.id)
+             FROM projects p WHERE p.deleted_at IS NOT NULL
+             UNION ALL
+             SELECT id, 'tag', name, NULL, deleted_at, 0 
+             FROM tags WHERE deleted_at IS NOT NULL
+             ORDER BY 5 DESC"
+        )?;
+
+        let items = stmt.query_map([], |row| {
+            let kind = match row.get::<_, String>(1)?.as_str() {
+                "note" => TrashKind::Note,
+                "project" => TrashKind::Project,
+                _ => TrashKind::Tag,
+            };
+            Ok(TrashItem {
                 id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get(2)?,
+                kind,
+                name: row.get(2)?,
+                project_id: row.get(3)?,
+                deleted_at: row.get(4)?,
+                cascaded_notes: row.get(5)?,
             })
         })?
-        .collect::<Result<Vec<_>>>()?;
+        .collect::<rusqlite::Result<Vec<_>>>()?;
 
-        Ok(tags)
+        Ok(items)
+    }
+
+    /// Brings an item back from the trash. Restoring a project also revives
+    /// the notes trashed along with it. Restoring a note whose project is in
+    /// the trash brings back the project alone: its other notes stay in the
+    /// trash as separate items. Tags work the same way with the tags below
+    /// and above them.
+    pub fn restore_from_trash(&self, kind: TrashKind, id: &str) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        match kind {
+            TrashKind::Note => {
+                let project_id: Option<String> = tx
+                    .query_row(
+                        "SELECT p.id FROM notes n 
+                         INNER JOIN projects p ON p.id = n.project_id 
+                         WHERE n.id = ? AND p.deleted_at IS NOT NULL",
+                        [id],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                if let Some(project_id) = project_id {
+                    tx.execute("UPDATE projects SET deleted_at = NULL WHERE id = ?", [&project_id])?;
+                    tx.execute(
+                        "UPDATE notes SET deleted_with_project = NULL WHERE deleted_with_project = ?",
+                        [&project_id],
+                    )?;
+                }
+                let changed = tx.execute(
+                    "UPDATE notes SET deleted_at = NULL, deleted_with_project = NULL 
+                     WHERE id = ? AND deleted_at IS NOT NULL",
+                    [id],
+                )?;
+                expect_changed(changed, "note", id)?;
+            }
+            TrashKind::Project => restore_project(&tx, id)?,
+            TrashKind::Tag => restore_tag(&tx, id)?,
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Permanently deletes everything in the trash. Tasks, tag links and
+    /// revisions of purged notes go with them through their foreign keys.
+    pub fn empty_trash(&self) -> Result<usize> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+        let purged = purge_trash_before(&tx, None)?;
+        tx.commit()?;
+        Ok(purged)
+    }
+
+    pub fn get_trash_retention_days(&self) -> Result<u32> {
+        let conn = self.connection.lock().unwrap();
+        trash_retention_days(&conn)
+    }
+
+    /// `0` keeps trashed items until the trash is emptied by hand.
+    pub fn set_trash_retention_days(&self, days: u32) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+        write_setting(&conn, TRASH_RETENTION_KEY, &days.to_string())
+    }
+
+    /// Run at startup: drops trashed items older than the retention period.
+    pub fn purge_expired_trash(&self) -> Result<usize> {
+        let mut conn = self.connection.lock().unwrap();
+        let days = trash_retention_days(&conn)?;
+        if days == 0 {
+            return Ok(0);
+        }
+
+        let cutoff = (Utc::now() - Duration::days(days as i64)).to_rfc3339();
+        let tx = conn.transaction()?;
+        let purged = purge_trash_before(&tx, Some(&cutoff))?;
+        tx.commit()?;
+        Ok(purged)
+    }
+
+    // Snapshots
+    /// Copies the whole active database to a timestamped file next to it,
+    /// then prunes snapshots the retention policy no longer keeps.
+    pub fn create_snapshot(&self) -> Result<SnapshotInfo> {
+        let dir = self.snapshot_dir();
+        let (info, policy) = {
+            let conn = self.connection.lock().unwrap();
+            let policy = snapshot_policy(&conn)?;
+            (snapshot::create(&conn, &dir, policy.compress)?, policy)
+        };
+        snapshot::apply_retention(&dir, &policy)?;
+        Ok(info)
+    }
+
+    pub fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        snapshot::list(&self.snapshot_dir())
+    }
+
+    pub fn verify_snapshot(&self, file_name: &str) -> Result<SnapshotInfo> {
+        snapshot::verify(&self.snapshot_dir(), file_name)
+    }
+
+    pub fn delete_snapshot(&self, file_name: &str) -> Result<()> {
+        snapshot::delete(&self.snapshot_dir(), file_name)
+    }
+
+    fn snapshot_dir(&self) -> PathBuf {
+        snapshot::snapshot_dir(&self.databases.lock().unwrap().active_dir)
+    }
+
+    pub fn get_snapshot_policy(&self) -> Result<SnapshotPolicy> {
+        let conn = self.connection.lock().unwrap();
+        snapshot_policy(&conn)
+    }
+
+    pub fn set_snapshot_policy(&self, policy: &SnapshotPolicy) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+        write_setting(&conn, SNAPSHOT_KEEP_DAILY_KEY, &policy.keep_daily.to_string())?;
+        write_setting(&conn, SNAPSHOT_KEEP_WEEKLY_KEY, &policy.keep_weekly.to_string())?;
+        write_setting(&conn, SNAPSHOT_COMPRESS_KEY, &policy.compress.to_string())
+    }
+
+    pub fn get_auto_backup_settings(&self) -> Result<AutoBackupSettings> {
+        let conn = self.connection.lock().unwrap();
+        let defaults = AutoBackupSettings::default();
+        Ok(AutoBackupSettings {
+            enabled: read_setting(&conn, AUTO_BACKUP_ENABLED_KEY, defaults.enabled)?,
+            interval_minutes: read_setting(&conn, AUTO_BACKUP_INTERVAL_KEY, defaults.interval_minutes)?,
+        })
+    }
+
+    pub fn set_auto_backup_settings(&self, settings: &AutoBackupSettings) -> Result<()> {
+        if settings.interval_minutes == 0 {
+            return Err(AppError::validation("interval_minutes", "must be at least 1"));
+        }
+        let conn = self.connection.lock().unwrap();
+        write_setting(&conn, AUTO_BACKUP_ENABLED_KEY, &settings.enabled.to_string())?;
+        write_setting(&conn, AUTO_BACKUP_INTERVAL_KEY, &settings.interval_minutes.to_string())
+    }
+
+    /// Whether automatic backups are wanted by at least one workspace.
+    /// Workspaces without saved settings use the schema default (auto-save
+    /// on), and with no workspace at all notes still deserve a backup.
+    pub fn any_workspace_auto_saves(&self) -> Result<bool> {
+        let conn = self.connection.lock().unwrap();
+        let (workspaces, auto_saving): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(COALESCE(s.auto_save, 1)), 0) 
+             FROM workspaces w 
+             LEFT JOIN workspace_settings s ON s.workspace_id = w.id",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(workspaces == 0 || auto_saving > 0)
+    }
+
+    // Links
+    pub fn get_outgoing_links(&self, note_id: &str) -> Result<Vec<NoteLink>> {
+        self.get_note(note_id)?;
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} WHERE r.source_id = ? ORDER BY r.position",
+            LINK_COLUMNS
+        ))?;
+        let links = stmt
+            .query_map([note_id], link_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(links)
+    }
+
+    /// Links from other notes (or the note itself) resolving to `note_id`,
+    /// most recently edited source first.
+    pub fn get_backlinks(&self, note_id: &str) -> Result<Vec<NoteLink>> {
+        let note = self.get_note(note_id)?;
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} 
+             WHERE (r.target = ?1 OR r.target = ?2 COLLATE NOCASE) AND r.target_id = ?1 
+             ORDER BY s.updated_at DESC, r.source_id, r.position",
+            LINK_COLUMNS
+        ))?;
+        let links = stmt
+            .query_map((&note.id, &note.title), link_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(links)
+    }
+
+    pub fn get_unresolved_links(&self, project_id: Option<&str>) -> Result<Vec<NoteLink>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} 
+             WHERE r.target_id IS NULL AND (?1 IS NULL OR s.project_id = ?1) 
+             ORDER BY s.title, r.source_id, r.position",
+            LINK_COLUMNS
+        ))?;
+        let links = stmt
+            .query_map([project_id], link_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(links)
+    }
+
+    /// The notes `update_note` would rewrite if `note_id` were renamed to
+    /// `new_title`, without changing anything.
+    pub fn preview_note_rename(&self, note_id: &str, new_title: &str) -> Result<RenamePreview> {
+        let note = self.get_note(note_id)?;
+        let conn = self.connection.lock().unwrap();
+        let notes = plan_link_rewrites(&conn, note_id, &note.title, new_title)?;
+        Ok(RenamePreview {
+            note_id: note.id,
+            old_title: note.title,
+            new_title: new_title.to_string(),
+            notes,
+        })
+    }
+
+    /// Notes no other note links to.
+    pub fn get_orphan_notes(&self, project_id: Option<&str>) -> Result<Vec<Note>> {
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.title, n.content, n.project_id, n.created_at, n.updated_at, n.is_pinned 
+             FROM notes n 
+             WHERE n.deleted_at IS NULL AND (?1 IS NULL OR n.project_id = ?1) 
+             AND NOT EXISTS (
+                 SELECT 1 FROM resolved_note_links r 
+                 WHERE (r.target = n.id OR r.target = n.title COLLATE NOCASE) 
+                 AND r.target_id = n.id AND r.source_id <> n.id
+             ) 
+             ORDER BY n.title"
+        )?;
+
+        let notes = stmt.query_map([project_id], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get(2)?,
+                project_id: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+                is_pinned: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(notes)
+    }
+
+    // Graph
+    /// Notes, tags and projects with the links and memberships between
+    /// them. Without a project the graph spans the whole workspace database.
+    pub fn get_graph(&self, query: &GraphQuery) -> Result<GraphData> {
+        if let Some(project_id) = &query.project_id {
+            self.get_project(project_id)?;
+        }
+        if let Some(note_id) = &query.focus_note_id {
+            self.get_note(note_id)?;
+        }
+
+        let conn = self.connection.lock().unwrap();
+        let pairs = |sql: &str| -> Result<Vec<(String, String)>> {
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT id, title, project_id FROM notes 
+             WHERE deleted_at IS NULL ORDER BY updated_at DESC, id"
+        )?;
+        let notes = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let input = GraphInput {
+            notes,
+            projects: pairs("SELECT id, name FROM projects WHERE deleted_at IS NULL")?,
+            tags: pairs("SELECT id, name FROM tags WHERE deleted_at IS NULL")?,
+            note_tags: pairs("SELECT note_id, tag_id FROM note_tags")?,
+            links: pairs(
+                "SELECT source_id, target_id FROM resolved_note_links 
+                 WHERE target_id IS NOT NULL ORDER BY source_id, position"
+            )?,
+        };
+        Ok(graph::build(&input, query))
+    }
+
+    fn reindex_links_if_needed(&self) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        if !read_setting(&conn, NOTE_LINKS_REINDEX_KEY, false)? {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+        let notes = {
+            let mut stmt = tx.prepare("SELECT id, COALESCE(content, '') FROM notes")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+        for (id, content) in &notes {
+            index_links(&tx, id, content)?;
+        }
+        write_setting(&tx, NOTE_LINKS_REINDEX_KEY, "false")?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn sync_task_checkboxes_if_needed(&self) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        if !read_setting(&conn, TASK_CHECKBOXES_SYNC_KEY, false)? {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+        let notes = {
+            let mut stmt = tx.prepare("SELECT id, COALESCE(content, '') FROM notes")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+        for (id, content) in &notes {
+            sync_checkbox_tasks(&tx, id, content)?;
+        }
+        write_setting(&tx, TASK_CHECKBOXES_SYNC_KEY, "false")?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn rebuild_tag_hierarchy_if_needed(&self) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        if !read_setting(&conn, TAG_HIERARCHY_KEY, false)? {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+        let tags = {
+            let mut stmt = tx.prepare(
+                "SELECT id, name FROM tags WHERE deleted_at IS NULL AND instr(name, '/') > 0 
+                 ORDER BY length(name)",
+            )?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+        for (id, name) in &tags {
+            let parent_id = tag_parent(&tx, name)?;
+            tx.execute("UPDATE tags SET parent_id = ?1 WHERE id = ?2", (parent_id, id))?;
+        }
+        write_setting(&tx, TAG_HIERARCHY_KEY, "false")?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Markdown Export
+    /// Writes the project's notes to `dir` as Markdown files with front
+    /// matter, plus a manifest for re-importing them.
+    pub fn export_project(&self, project_id: &str, dir: &Path) -> Result<ExportManifest> {
+        let project = self.get_project(project_id)?;
+        let mut notes = self.get_notes(project_id)?;
+        notes.sort_by(|a, b| (&a.created_at, &a.id).cmp(&(&b.created_at, &b.id)));
+
+        let notes = notes
+            .into_iter()
+            .map(|note| {
+                Ok(ExportNote {
+                    tags: self.get_note_tags(&note.id)?,
+                    tasks: self.get_tasks(&note.id)?,
+                    note,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        markdown::export(dir, &project, &notes)
+    }
+
+    // Calendar Export
+    /// Writes the tasks that have a due date to `path` as an iCalendar file.
+    pub fn export_tasks_ics(&self, path: &Path, options: &IcsExportOptions) -> Result<IcsExport> {
+        let query = TaskQuery {
+            status: if options.open_only { TaskStatus::Open } else { TaskStatus::All },
+            project_id: options.project_id.clone(),
+            tag_id: options.tag_id.clone(),
+            ..TaskQuery::default()
+        };
+        let items: Vec<TaskListItem> = self
+            .query_tasks(&query)?
+            .into_iter()
+            .filter(|item| item.task.due_date.is_some())
+            .collect();
+        fs::write(path, ics::render(&items, options.component, Utc::now()))?;
+        Ok(IcsExport {
+            path: path.to_path_buf(),
+            tasks: items.len(),
+        })
+    }
+
+    // Markdown Import
+    /// Imports every Markdown file under `dir` in a single transaction. Each
+    /// note is stored under its own savepoint, so a file that cannot be
+    /// stored is reported and skipped without undoing the rest; a dry run
+    /// builds the same report and then rolls everything back. Ids found in
+    /// the files are kept while they are free, so a folder written by
+    /// `export_project` comes back unchanged.
+    pub fn import_markdown(&self, dir: &Path, options: &ImportOptions) -> Result<ImportReport> {
+        if !dir.is_dir() {
+            return Err(AppError::validation("directory", "not a folder"));
+        }
+        // A manifest.json that is not ours is just another file in the vault.
+        let manifest = markdown::read_manifest(dir).ok().flatten();
+        let files = markdown::scan(dir)?;
+        let root_name = manifest
+            .as_ref()
+            .map(|m| m.project.name.clone())
+            .or_else(|| dir.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "Imported".to_string());
+
+        let mut conn = self.connection.lock().unwrap();
+        let mut tx = conn.transaction()?;
+        let mut import = MarkdownImport {
+            manifest: manifest.as_ref(),
+            folders: options.folders,
+            root_name,
+            projects: HashMap::new(),
+            tags: HashMap::new(),
+            report: ImportReport {
+                dry_run: options.dry_run,
+                ..Default::default()
+            },
+        };
+
+        for scanned in files {
+            let parsed = match scanned.note {
+                Ok(parsed) => parsed,
+                Err(message) => {
+                    import.report.errors.push(ImportError { file: scanned.file, message });
+                    continue;
+                }
+            };
+
+            let mark = (import.report.projects.len(), import.report.tags.len());
+            let savepoint = tx.savepoint()?;
+            match import.note(&savepoint, &scanned.file, &scanned.folders, parsed) {
+                Ok(note) => {
+                    savepoint.commit()?;
+                    import.report.notes.push(note);
+                }
+                Err(e) => {
+                    drop(savepoint);
+                    import.forget_since(mark);
+                    import.report.errors.push(ImportError {
+                        file: scanned.file,
+                        message: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        if !options.dry_run {
+            tx.commit()?;
+        }
+        Ok(import.report)
+    }
+}
+
+/// State of one `import_markdown` run: the projects and tags resolved so
+/// far, keyed by folder path and lowercased tag name.
+struct MarkdownImport<'a> {
+    manifest: Option<&'a ExportManifest>,
+    folders: FolderMapping,
+    root_name: String,
+    projects: HashMap<String, String>,
+    tags: HashMap<String, String>,
+    report: ImportReport,
+}
+
+impl MarkdownImport<'_> {
+    fn note(
+        &mut self,
+        conn: &Connection,
+        file: &str,
+        folders: &[String],
+        parsed: ParsedNote,
+    ) -> Result<ImportedNote> {
+        let project_id = self.project(conn, folders)?;
+        let now = Utc::now().to_rfc3339();
+        let created_at = parsed.created_at.unwrap_or_else(|| now.clone());
+        let updated_at = parsed.updated_at.unwrap_or_else(|| created_at.clone());
+        let id = free_id(conn, "notes", parsed.id)?;
+
+        conn.execute(
+            "INSERT INTO notes (id, title, content, project_id, created_at, updated_at, is_pinned) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (&id, &parsed.title, &parsed.content, &project_id, &created_at, &updated_at, parsed.pinned),
+        )?;
+        record_revision(conn, &id, &parsed.title, &parsed.content, "create")?;
+
+        let mut tag_names = parsed.tags;
+        if self.folders == FolderMapping::Tags && !folders.is_empty() {
+            tag_names.push(folders.join("/"));
+        }
+        let mut seen = std::collections::HashSet::new();
+        tag_names.retain(|name| seen.insert(name.to_lowercase()));
+        for name in &tag_names {
+            let tag_id = self.tag(conn, name)?;
+            conn.execute(
+                "INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?1, ?2)",
+                (&id, &tag_id),
+            )?;
+        }
+
+        for task in &parsed.tasks {
+            let task_created = task
+                .created
+                .as_deref()
+                .and_then(markdown::normalize_timestamp)
+                .unwrap_or_else(|| created_at.clone());
+            let task_updated = task
+                .updated
+                .as_deref()
+                .and_then(markdown::normalize_timestamp)
+                .unwrap_or_else(|| task_created.clone());
+            let completed_at = task
+                .done
+                .as_deref()
+                .and_then(markdown::normalize_timestamp)
+                .or_else(|| task.completed.then(|| task_updated.clone()));
+            conn.execute(
+                "INSERT INTO tasks (id, content, completed, note_id, created_at, updated_at, 
+                 due_date, priority, completed_at, assignee, recurrence) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                (
+                    free_id(conn, "tasks", task.id.clone())?,
+                    &task.content,
+                    task.completed,
+                    &id,
+                    &task_created,
+                    &task_updated,
+                    task.due.as_deref().and_then(tasks::normalize_due),
+                    task.priority.rank(),
+                    completed_at,
+                    &task.assignee,
+                    task.recurrence.as_deref().and_then(|rule| Recurrence::parse(rule).ok()).map(|r| r.to_string()),
+                ),
+            )?;
+        }
+        // Front matter tasks whose checkbox is in the body get bound to it
+        index_content(conn, &id, &parsed.content)?;
+        let task_count: i64 =
+            conn.query_row("SELECT COUNT(*) FROM tasks WHERE note_id = ?", [&id], |row| row.get(0))?;
+
+        Ok(ImportedNote {
+            file: file.to_string(),
+            note_id: id,
+            title: parsed.title,
+            project_id,
+            tags: tag_names,
+            tasks: task_count as usize,
+        })
+    }
+
+    fn project(&mut self, conn: &Connection, folders: &[String]) -> Result<String> {
+        let key = match self.folders {
+            FolderMapping::Projects => folders.join("/"),
+            FolderMapping::Tags => String::new(),
+        };
+        if let Some(id) = self.projects.get(&key) {
+            return Ok(id.clone());
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let exported = self.manifest.filter(|_| key.is_empty()).map(|m| &m.project);
+        let project = Project {
+            id: free_id(conn, "projects", exported.map(|p| p.id.clone()))?,
+            name: if key.is_empty() { self.root_name.clone() } else { key.clone() },
+            created_at: exported.map_or_else(|| now.clone(), |p| p.created_at.clone()),
+            updated_at: exported.map_or(now, |p| p.updated_at.clone()),
+            description: exported.and_then(|p| p.description.clone()),
+            color: exported.and_then(|p| optional_color("color", p.color.as_deref()).ok().flatten()),
+            icon: exported.and_then(|p| p.icon.clone()),
+            position: next_project_position(conn)?,
+            archived: false,
+        };
+        conn.execute(
+            "INSERT INTO projects (id, name, created_at, updated_at, description, color, icon, position) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                &project.id,
+                &project.name,
+                &project.created_at,
+                &project.updated_at,
+                &project.description,
+                &project.color,
+                &project.icon,
+                project.position,
+            ),
+        )?;
+
+        self.projects.insert(key, project.id.clone());
+        self.report.projects.push(project.clone());
+        Ok(project.id)
+    }
+
+    /// Reuses a live tag with the same name, otherwise creates one along
+    /// with its missing parents.
+    fn tag(&mut self, conn: &Connection, name: &str) -> Result<String> {
+        let key = name.to_lowercase();
+        if let Some(id) = self.tags.get(&key) {
+            return Ok(id.clone());
+        }
+
+        let name = tag_path(name)?;
+        if let Some(id) = live_tag_id(conn, &name)? {
+            self.tags.insert(key, id.clone());
+            return Ok(id);
+        }
+
+        let exported = self
+            .manifest
+            .and_then(|m| m.tags.iter().find(|t| t.name.to_lowercase() == key));
+        let tag = Tag {
+            id: free_id(conn, "tags", exported.map(|t| t.id.clone()))?,
+            created_at: exported.map_or_else(|| Utc::now().to_rfc3339(), |t| t.created_at.clone()),
+            color: exported.and_then(|t| optional_color("color", t.color.as_deref()).ok().flatten()),
+            description: exported.and_then(|t| t.description.clone()),
+            parent_id: tag_parent(conn, &name)?,
+            name,
+        };
+        conn.execute(
+            "INSERT INTO tags (id, name, created_at, color, description, parent_id) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (&tag.id, &tag.name, &tag.created_at, &tag.color, &tag.description, &tag.parent_id),
+        )?;
+
+        self.tags.insert(key, tag.id.clone());
+        self.report.tags.push(tag.clone());
+        Ok(tag.id)
+    }
+
+    /// Drops projects and tags created after `mark` from the caches and the
+    /// report once their savepoint has been rolled back.
+    fn forget_since(&mut self, (projects, tags): (usize, usize)) {
+        for project in self.report.projects.drain(projects..) {
+            self.projects.retain(|_, id| *id != project.id);
+        }
+        for tag in self.report.tags.drain(tags..) {
+            self.tags.retain(|_, id| *id != tag.id);
+        }
+    }
+}
+
+/// Replaces the stored links of `note_id` with those found in `content`.
+fn index_links(conn: &Connection, note_id: &str, content: &str) -> Result<()> {
+    conn.execute("DELETE FROM note_links WHERE source_id = ?", [note_id])?;
+    let mut stmt = conn.prepare(
+        "INSERT INTO note_links (source_id, position, kind, target, label, line, context) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for (position, link) in links::parse_links(content).iter().enumerate() {
+        stmt.execute((
+            note_id,
+            position as i64,
+            link.kind.as_str(),
+            &link.target,
+            &link.label,
+            link.line as i64,
+            links::line_text(content, link.line),
+        ))?;
+    }
+    Ok(())
+}
+
+fn task_from_row(row: &rusqlite::Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        completed: row.get(2)?,
+        note_id: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+        due_date: row.get(6)?,
+        priority: TaskPriority::from_rank(row.get(7)?),
+        completed_at: row.get(8)?,
+        assignee: row.get(9)?,
+        recurrence: row.get(10)?,
+        series_id: row.get(11)?,
+    })
+}
+
+/// `task` with its due date and recurrence in stored form and a blank
+/// assignee dropped.
+fn normalize_task(task: &Task) -> Result<Task> {
+    let mut task = task.clone();
+    task.due_date = match task.due_date.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(due) => Some(tasks::normalize_due(due).ok_or_else(|| {
+            AppError::validation("due_date", "must be a date (YYYY-MM-DD) or an RFC 3339 timestamp")
+        })?),
+    };
+    task.assignee = task
+        .assignee
+        .as_deref()
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(str::to_string);
+    task.recurrence = match task.recurrence.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(rule) => Some(
+            Recurrence::parse(rule)
+                .map_err(|message| AppError::validation("recurrence", message))?
+                .to_string(),
+        ),
+    };
+    Ok(task)
+}
+
+/// After recurring task `task_id` was completed, adds the next occurrence
+/// unless the series already has an open one, as happens when an occurrence
+/// is reopened and completed again. The new task does not get a checkbox.
+fn schedule_next_occurrence(conn: &Connection, task_id: &str) -> Result<()> {
+    let task = query_task(conn, task_id)?;
+    let Some(recurrence) = task.recurrence.as_deref().and_then(|rule| Recurrence::parse(rule).ok()) else {
+        return Ok(());
+    };
+    let series = task.series_id.clone().unwrap_or_else(|| task.id.clone());
+    let open: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM tasks WHERE COALESCE(series_id, id) = ?1 AND NOT completed",
+        [&series],
+        |row| row.get(0),
+    )?;
+    if open > 0 {
+        return Ok(());
+    }
+
+    let next_due = recurrence
+        .next_due(task.due_date.as_deref().and_then(Due::parse), &Local::now())
+        .map_err(|message| AppError::validation("due_date", message))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute("UPDATE tasks SET series_id = ?1 WHERE id = ?2", (&series, &task.id))?;
+    conn.execute(
+        "INSERT INTO tasks (id, content, completed, note_id, created_at, updated_at, 
+         due_date, priority, assignee, recurrence, series_id) 
+         VALUES (?1, ?2, FALSE, ?3, ?4, ?4, ?5, ?6, ?7, ?8, ?9)",
+        (
+            uuid::Uuid::new_v4().to_string(),
+            &task.content,
+            &task.note_id,
+            &now,
+            next_due.to_string(),
+            task.priority.rank(),
+            &task.assignee,
+            &task.recurrence,
+            &series,
+        ),
+    )?;
+    Ok(())
+}
+
+/// Refreshes what is derived from a note's content: its links and the tasks
+/// bound to its checkboxes.
+fn index_content(conn: &Connection, note_id: &str, content: &str) -> Result<()> {
+    index_links(conn, note_id, content)?;
+    sync_checkbox_tasks(conn, note_id, content)
+}
+
+/// Brings the tasks of `note_id` in line with the checkboxes in `content`.
+/// `updated_at` only moves for tasks whose text or state changed, not for
+/// those whose checkbox merely moved. Ticking the checkbox of a recurring
+/// task schedules its next occurrence as `update_task` does.
+fn sync_checkbox_tasks(conn: &Connection, note_id: &str, content: &str) -> Result<()> {
+    let tasks = {
+        let mut stmt = conn.prepare(
+            "SELECT id, content, completed, checkbox_index, checkbox_hash 
+             FROM tasks WHERE note_id = ? ORDER BY created_at, rowid",
+        )?;
+        let rows = stmt
+            .query_map([note_id], |row| {
+                Ok(SyncedTask {
+                    id: row.get(0)?,
+                    content: row.get(1)?,
+                    completed: row.get(2)?,
+                    index: row.get::<_, Option<i64>>(3)?.map(|i| i as usize),
+                    hash: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+
+    let now = Utc::now().to_rfc3339();
+    for change in checklist::plan_sync(&checklist::checkboxes(content), &tasks) {
+        match change {
+            TaskChange::Update { id, index, hash, content, completed } => {
+                conn.execute(
+                    "UPDATE tasks SET 
+                     updated_at = CASE WHEN content = ?1 AND completed = ?2 THEN updated_at ELSE ?3 END, 
+                     completed_at = CASE WHEN ?2 THEN COALESCE(completed_at, ?3) END, 
+                     content = ?1, completed = ?2, checkbox_index = ?4, checkbox_hash = ?5 
+                     WHERE id = ?6",
+                    (&content, completed, &now, index as i64, &hash, &id),
+                )?;
+                if completed && !tasks.iter().any(|task| task.id == id && task.completed) {
+                    schedule_next_occurrence(conn, &id)?;
+                }
+            }
+            TaskChange::Insert { index, hash, content, completed } => {
+                conn.execute(
+                    "INSERT INTO tasks (id, content, completed, note_id, created_at, updated_at, 
+                     completed_at, checkbox_index, checkbox_hash) 
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?5, CASE WHEN ?3 THEN ?5 END, ?6, ?7)",
+                    (
+                        uuid::Uuid::new_v4().to_string(),
+                        &content,
+                        completed,
+                        note_id,
+                        &now,
+                        index as i64,
+                        &hash,
+                    ),
+                )?;
+            }
+            TaskChange::Delete { id } => {
+                conn.execute("DELETE FROM tasks WHERE id = ?", [&id])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn query_task(conn: &Connection, id: &str) -> Result<Task> {
+    conn.query_row(&format!("SELECT {} FROM tasks t WHERE t.id = ?", TASK_COLUMNS), [id], task_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found("task", id))
+}
+
+/// The note of task `id` and, for a task bound to a checkbox, its position
+/// and text hash.
+fn task_binding(conn: &Connection, id: &str) -> Result<(String, Option<(usize, String)>)> {
+    let (note_id, index, hash): (String, Option<i64>, Option<String>) = conn
+        .query_row(
+            "SELECT note_id, checkbox_index, checkbox_hash FROM tasks WHERE id = ?",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found("task", id))?;
+    Ok((note_id, index.zip(hash).map(|(index, hash)| (index as usize, hash))))
+}
+
+/// Title of the note, which must not be in the trash.
+fn live_note_title(conn: &Connection, note_id: &str) -> Result<String> {
+    conn.query_row(
+        "SELECT title FROM notes WHERE id = ? AND deleted_at IS NULL",
+        [note_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("note", note_id))
+}
+
+/// Title and content of the note, `None` while it is in the trash.
+fn live_note_text(conn: &Connection, note_id: &str) -> Result<Option<(String, String)>> {
+    Ok(conn
+        .query_row(
+            "SELECT title, COALESCE(content, '') FROM notes WHERE id = ? AND deleted_at IS NULL",
+            [note_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?)
+}
+
+/// The checkbox of a bound task cannot be told apart any more: the note was
+/// edited so that several checkboxes carry its text and none sits at its
+/// position.
+fn checkbox_conflict(task_id: &str) -> AppError {
+    AppError::Conflict {
+        message: format!("the checkbox of task {} changed in its note; reload the note and try again", task_id),
+    }
+}
+
+/// Whether going from `old_title` to `new_title` breaks links. Links match
+/// titles case-insensitively, so a change of case alone does not.
+fn renames(old_title: &str, new_title: &str) -> bool {
+    !old_title.eq_ignore_ascii_case(new_title)
+}
+
+/// For each other note whose wiki links to `old_title` resolve to `note_id`,
+/// its content with those links pointed at `new_title`.
+fn plan_link_rewrites(
+    conn: &Connection,
+    note_id: &str,
+    old_title: &str,
+    new_title: &str,
+) -> Result<Vec<LinkRewrite>> {
+    if !renames(old_title, new_title) {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT r.source_id, s.title, COALESCE(s.content, '') 
+         FROM resolved_note_links r 
+         INNER JOIN notes s ON s.id = r.source_id 
+         WHERE r.kind = 'wiki' AND r.target = ?2 COLLATE NOCASE AND r.target_id = ?1 
+         AND r.source_id <> ?1 
+         ORDER BY s.title, r.source_id",
+    )?;
+    let sources = stmt
+        .query_map((note_id, old_title), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    if !sources.is_empty() && new_title.contains(links::RESERVED_TITLE_CHARS) {
+        return Err(AppError::validation(
+            "title",
+            "cannot contain [ ] | # ^ while other notes link to this note",
+        ));
+    }
+
+    Ok(sources
+        .into_iter()
+        .map(|(id, title, content)| {
+            let (rewritten, lines) = links::rewrite_wiki_target(&content, old_title, new_title);
+            LinkRewrite {
+                note_id: id,
+                title,
+                lines: lines
+                    .into_iter()
+                    .map(|line| RewrittenLine {
+                        line,
+                        before: links::line_text(&content, line).to_string(),
+                        after: links::line_text(&rewritten, line).to_string(),
+                    })
+                    .collect(),
+                content: rewritten,
+            }
+        })
+        .collect())
+}
+
+fn apply_link_rewrites(conn: &Connection, rewrites: &[LinkRewrite], now: &str) -> Result<()> {
+    for rewrite in rewrites {
+        conn.execute(
+            "UPDATE notes SET content = ?1, updated_at = ?2 WHERE id = ?3",
+            (&rewrite.content, now, &rewrite.note_id),
+        )?;
+        record_revision(conn, &rewrite.note_id, &rewrite.title, &rewrite.content, "edit")?;
+        index_content(conn, &rewrite.note_id, &rewrite.content)?;
+    }
+    Ok(())
+}
+
+fn link_from_row(row: &rusqlite::Row) -> rusqlite::Result<NoteLink> {
+    let kind = match row.get::<_, String>(2)?.as_str() {
+        "id" => LinkKind::Id,
+        _ => LinkKind::Wiki,
+    };
+    Ok(NoteLink {
+        source_id: row.get(0)?,
+        source_title: row.get(1)?,
+        kind,
+        target: row.get(3)?,
+        label: row.get(4)?,
+        line: row.get(5)?,
+        context: row.get(6)?,
+        target_id: row.get(7)?,
+        target_title: row.get(8)?,
+    })
+}
+
+/// `wanted` if no row of `table` uses it yet, otherwise a fresh id.
+fn free_id(conn: &Connection, table: &str, wanted: Option<String>) -> Result<String> {
+    if let Some(id) = wanted.filter(|id| !id.trim().is_empty()) {
+        let taken: bool = conn.query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?)", table),
+            [&id],
+            |row| row.get(0),
+        )?;
+        if !taken {
+            return Ok(id);
+        }
+    }
+    Ok(uuid::Uuid::new_v4().to_string())
+}
+
+fn read_workspace_snapshot(conn: &Connection, workspace_id: &str) -> Result<WorkspaceSnapshot> {
+    let workspace = query_workspace(conn, workspace_id)?;
+
+    let settings = conn
+        .query_row(
+            "SELECT workspace_id, dark_mode, split_view, privacy_level, auto_save 
+             FROM workspace_settings WHERE workspace_id = ?",
+            [workspace_id],
+            |row| {
+                Ok(WorkspaceSettings {
+                    workspace_id: row.get(0)?,
+                    dark_mode: row.get(1)?,
+                    split_view: row.get(2)?,
+                    privacy_level: row.get(3)?,
+                    auto_save: row.get(4)?,
+                })
+            },
+        )
+        .optional()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} WHERE t.workspace_id = ? ORDER BY t.position, t.rowid",
+        TAB_COLUMNS
+    ))?;
+    let tabs = stmt
+        .query_map([workspace_id], tab_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM workspace_profiles p WHERE p.workspace_id = ? ORDER BY p.position",
+        PROFILE_COLUMNS
+    ))?;
+    let profiles = stmt
+        .query_map([workspace_id], profile_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(WorkspaceSnapshot {
+        format_version: WORKSPACE_SNAPSHOT_VERSION,
+        workspace,
+        settings,
+        tabs,
+        profiles,
+    })
+}
+
+/// `theme` with its name trimmed and colors normalized, or the first field
+/// that is not valid.
+fn normalize_theme(theme: &CustomTheme) -> Result<CustomTheme> {
+    validate_name("name", &theme.name)?;
+    if !matches!(theme.type_.as_str(), "light" | "dark") {
+        return Err(AppError::validation("type_", "must be light or dark"));
+    }
+    Ok(CustomTheme {
+        id: theme.id.clone(),
+        name: theme.name.trim().to_string(),
+        type_: theme.type_.clone(),
+        primary_color: css_color("primary_color", &theme.primary_color)?,
+        secondary_color: css_color("secondary_color", &theme.secondary_color)?,
+        background_color: css_color("background_color", &theme.background_color)?,
+        surface_color: css_color("surface_color", &theme.surface_color)?,
+        text_color: css_color("text_color", &theme.text_color)?,
+        accent_color: css_color("accent_color", &theme.accent_color)?,
+    })
+}
+
+fn saved_theme(theme: CustomTheme) -> SavedTheme {
+    SavedTheme {
+        warnings: themes::contrast_warnings(&theme),
+        theme,
+    }
+}
+
+fn insert_theme(conn: &Connection, theme: &CustomTheme) -> Result<()> {
+    conn.execute(
+        "INSERT INTO custom_themes 
+        (id, name, type, primary_color, secondary_color, background_color, 
+        surface_color, text_color, accent_color) 
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        (
+            &theme.id,
+            &theme.name,
+            &theme.type_,
+            &theme.primary_color,
+            &theme.secondary_color,
+            &theme.background_color,
+            &theme.surface_color,
+            &theme.text_color,
+            &theme.accent_color,
+        ),
+    )?;
+    Ok(())
+}
+
+fn theme_from_row(row: &rusqlite::Row) -> rusqlite::Result<CustomTheme> {
+    Ok(CustomTheme {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        type_: row.get(2)?,
+        primary_color: row.get(3)?,
+        secondary_color: row.get(4)?,
+        background_color: row.get(5)?,
+        surface_color: row.get(6)?,
+        text_color: row.get(7)?,
+        accent_color: row.get(8)?,
+    })
+}
+
+fn query_theme(conn: &Connection, id: &str) -> Result<CustomTheme> {
+    conn.query_row(
+        &format!("SELECT {} FROM custom_themes WHERE id = ?", THEME_COLUMNS),
+        [id],
+        theme_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("theme", id))
+}
+
+fn tab_from_row(row: &rusqlite::Row) -> rusqlite::Result<Tab> {
+    Ok(Tab {
+        id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        type_: row.get(4)?,
+        position: row.get(5)?,
+        is_active: row.get(6)?,
+        note_id: row.get(7)?,
+    })
+}
+
+fn query_tab(conn: &Connection, id: &str) -> Result<Tab> {
+    conn.query_row(&format!("SELECT {} WHERE t.id = ?", TAB_COLUMNS), [id], tab_from_row)
+        .optional()?
+        .ok_or_else(|| AppError::not_found("tab", id))
+}
+
+fn closed_tab_from_row(row: &rusqlite::Row) -> rusqlite::Result<ClosedTab> {
+    Ok(ClosedTab {
+        tab_id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        type_: row.get(4)?,
+        note_id: row.get(5)?,
+        position: row.get(6)?,
+        closed_at: row.get(7)?,
+    })
+}
+
+/// Ids of the workspace's tabs in display order.
+fn tab_ids(conn: &Connection, workspace_id: &str) -> Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT id FROM tabs WHERE workspace_id = ? ORDER BY position, rowid")?;
+    let ids = stmt
+        .query_map([workspace_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(ids)
+}
+
+/// Fails when `ids`, an order of tabs or projects, lists an id twice.
+fn ensure_distinct_ids(ids: &[String]) -> Result<()> {
+    let mut seen = HashSet::new();
+    match ids.iter().find(|id| !seen.insert(id.as_str())) {
+        Some(id) => Err(AppError::validation("ids", format!("{} is listed more than once", id))),
+        None => Ok(()),
+    }
+}
+
+/// Gives the tabs positions 0, 1, … in the order of `ids`.
+fn renumber_tabs(conn: &Connection, ids: &[String]) -> Result<()> {
+    for (position, id) in ids.iter().enumerate() {
+        conn.execute(
+            "UPDATE tabs SET position = ?1 WHERE id = ?2 AND position <> ?1",
+            (position as i64, id),
+        )?;
+    }
+    Ok(())
+}
+
+/// At most one tab per workspace is active, which a unique index enforces,
+/// so the current one is cleared before another is set.
+fn clear_active_tab(conn: &Connection, workspace_id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE tabs SET is_active = 0 WHERE workspace_id = ? AND is_active",
+        [workspace_id],
+    )?;
+    Ok(())
+}
+
+fn set_active_tab(conn: &Connection, tab_id: &str) -> Result<()> {
+    let tab = query_tab(conn, tab_id)?;
+    if !tab.is_active {
+        clear_active_tab(conn, &tab.workspace_id)?;
+        conn.execute("UPDATE tabs SET is_active = 1 WHERE id = ?", [tab_id])?;
+    }
+    Ok(())
+}
+
+fn ensure_live_note(conn: &Connection, note_id: &str) -> Result<()> {
+    let live: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM notes WHERE id = ? AND deleted_at IS NULL)",
+        [note_id],
+        |row| row.get(0),
+    )?;
+    if live {
+        Ok(())
+    } else {
+        Err(AppError::not_found("note", note_id))
+    }
+}
+
+/// Opens the database at `path`, creating it and its directory if needed,
+/// and brings its schema up to date.
+fn open_database(path: &Path) -> Result<Connection> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut conn = Connection::open(path)?;
+
+    // Bring the schema up to date, refusing databases from newer builds
+    migrations::run(&mut conn)?;
+    // Purging the trash relies on the ON DELETE clauses to remove a note's
+    // tasks, tags, revisions and links. The bundled SQLite is built with
+    // foreign keys on by default; this keeps them on whatever the build, and
+    // `migrations::run` has checked that no existing row breaks one. Cannot
+    // be toggled inside the migration transaction.
+    conn.pragma_update(None, "foreign_keys", true)?;
+    Ok(conn)
+}
+
+/// Inserts the workspace with a default profile as its current one.
+fn insert_workspace(conn: &mut Connection, workspace: &Workspace) -> Result<()> {
+    let tx = conn.transaction()?;
+    register_workspace(&tx, workspace)?;
+    let profile = WorkspaceProfile {
+        id: uuid::Uuid::new_v4().to_string(),
+        workspace_id: workspace.id.clone(),
+        name: DEFAULT_PROFILE_NAME.to_string(),
+        theme: Default::default(),
+        settings: Default::default(),
+        position: 0,
+        created_at: String::new(),
+        updated_at: String::new(),
+    };
+    insert_profile(&tx, &profile)?;
+    tx.execute(
+        "UPDATE workspaces SET current_profile_id = ?1 WHERE id = ?2",
+        (&profile.id, &workspace.id),
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Inserts only the workspace's row: what the main database keeps of a
+/// workspace with its own database.
+fn register_workspace(conn: &Connection, workspace: &Workspace) -> Result<()> {
+    conn.execute(
+        "INSERT INTO workspaces (id, name, type, theme_id, path, database_path) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            &workspace.id,
+            &workspace.name,
+            &workspace.type_,
+            &workspace.theme_id,
+            &workspace.path,
+            &workspace.database_path,
+        ),
+    )?;
+    Ok(())
+}
+
+fn ensure_workspace_id_free(conn: &Connection, id: &str) -> Result<()> {
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM workspaces WHERE id = ?)",
+        [id],
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(AppError::Conflict {
+            message: format!("workspace {} already exists", id),
+        });
+    }
+    Ok(())
+}
+
+/// Brings the workspace's row in its own database in line with the main
+/// database's, creating it with a default profile if the file lacks it.
+fn sync_workspace_row(conn: &mut Connection, registered: &Workspace) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE workspaces SET name = ?1, database_path = ?2 WHERE id = ?3",
+        (&registered.name, &registered.database_path, &registered.id),
+    )?;
+    if changed == 0 {
+        insert_workspace(conn, registered)?;
+    }
+    Ok(())
+}
+
+fn workspace_from_row(row: &rusqlite::Row) -> rusqlite::Result<Workspace> {
+    Ok(Workspace {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        type_: row.get(2)?,
+        theme_id: row.get(3)?,
+        path: row.get(4)?,
+        current_profile_id: row.get(5)?,
+        database_path: row.get(6)?,
+    })
+}
+
+fn query_workspace(conn: &Connection, id: &str) -> Result<Workspace> {
+    conn.query_row(
+        &format!("SELECT {} FROM workspaces w WHERE w.id = ?", WORKSPACE_COLUMNS),
+        [id],
+        workspace_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("workspace", id))
+}
+
+/// Theme and settings that no longer parse read as the defaults rather than
+/// making the profile unreadable.
+fn profile_from_row(row: &rusqlite::Row) -> rusqlite::Result<WorkspaceProfile> {
+    let theme: String = row.get(3)?;
+    let settings: String = row.get(4)?;
+    Ok(WorkspaceProfile {
+        id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        name: row.get(2)?,
+        theme: serde_json::from_str(&theme).unwrap_or_default(),
+        settings: serde_json::from_str(&settings).unwrap_or_default(),
+        position: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+fn query_profile(conn: &Connection, id: &str) -> Result<WorkspaceProfile> {
+    conn.query_row(
+        &format!("SELECT {} FROM workspace_profiles p WHERE p.id = ?", PROFILE_COLUMNS),
+        [id],
+        profile_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("workspace profile", id))
+}
+
+/// Inserts `profile` at the end of its workspace's list and returns its id.
+fn insert_profile(conn: &Connection, profile: &WorkspaceProfile) -> Result<String> {
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO workspace_profiles 
+         (id, workspace_id, name, position, theme, settings, created_at, updated_at) 
+         VALUES (?1, ?2, ?3, 
+            (SELECT COALESCE(MAX(position) + 1, 0) FROM workspace_profiles WHERE workspace_id = ?2), 
+            ?4, ?5, ?6, ?6)",
+        (
+            &profile.id,
+            &profile.workspace_id,
+            &profile.name,
+            to_json(&profile.theme)?,
+            to_json(&profile.settings)?,
+            &now,
+        ),
+    )?;
+    Ok(profile.id.clone())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| AppError::validation("json", e.to_string()))
+}
+
+fn insert_backup(
+    conn: &Connection,
+    workspace_id: &str,
+    snapshot: &WorkspaceSnapshot,
+    kind: &str,
+) -> Result<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let data = serde_json::to_string(snapshot)
+        .map_err(|e| AppError::validation("data", e.to_string()))?;
+    conn.execute(
+        "INSERT INTO backups (id, workspace_id, data, kind) VALUES (?1, ?2, ?3, ?4)",
+        (&id, workspace_id, &data, kind),
+    )?;
+    Ok(id)
+}
+
+fn query_backup_info(conn: &Connection, id: &str) -> Result<BackupInfo> {
+    conn.query_row(
+        "SELECT id, workspace_id, kind, length(CAST(data AS BLOB)), created_at 
+         FROM backups WHERE id = ?",
+        [id],
+        |row| {
+            Ok(BackupInfo {
+                id: row.get(0)?,
+                workspace_id: row.get(1)?,
+                kind: row.get(2)?,
+                size_bytes: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("backup", id))
+}
+
+fn validate_name(field: &'static str, name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(AppError::validation(field, "must not be empty"));
+    }
+    Ok(())
+}
+
+/// `name` as a tag path: segments trimmed, empty ones and a leading `#`
+/// dropped, so ` #work / clients/` becomes `work/clients`.
+fn tag_path(name: &str) -> Result<String> {
+    let segments: Vec<&str> = name
+        .trim()
+        .trim_start_matches('#')
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if segments.is_empty() {
+        return Err(AppError::validation("name", "must not be empty"));
+    }
+    Ok(segments.join("/"))
+}
+
+/// `value` as `themes::normalize_color` stores it, the one color check
+/// shared by themes, tags and projects.
+fn css_color(field: &'static str, value: &str) -> Result<String> {
+    themes::normalize_color(value).ok_or_else(|| AppError::validation(field, "must be a hex, rgb() or hsl() color"))
+}
+
+/// Like `css_color`, where blank means no color.
+fn optional_color(field: &'static str, color: Option<&str>) -> Result<Option<String>> {
+    color
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| css_color(field, c))
+        .transpose()
+}
+
+/// `project` with its color checked and blank description and icon
+/// dropped.
+fn normalize_project(project: &Project) -> Result<Project> {
+    let blank_to_none = |value: &Option<String>| {
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+    };
+    Ok(Project {
+        color: optional_color("color", project.color.as_deref())?,
+        description: blank_to_none(&project.description),
+        icon: blank_to_none(&project.icon),
+        ..project.clone()
+    })
+}
+
+fn next_project_position(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("SELECT COALESCE(MAX(position) + 1, 0) FROM projects", [], |row| row.get(0))?)
+}
+
+fn project_from_row(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        description: row.get(4)?,
+        color: row.get(5)?,
+        icon: row.get(6)?,
+        position: row.get(7)?,
+        archived: row.get(8)?,
+    })
+}
+
+fn query_project(conn: &Connection, id: &str) -> Result<Project> {
+    conn.query_row(
+        &format!("SELECT {} FROM projects p WHERE p.id = ? AND p.deleted_at IS NULL", PROJECT_COLUMNS),
+        [id],
+        project_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("project", id))
+}
+
+fn tag_from_row(row: &rusqlite::Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        color: row.get(3)?,
+        description: row.get(4)?,
+        parent_id: row.get(5)?,
+    })
+}
+
+fn query_tag(conn: &Connection, id: &str) -> Result<Tag> {
+    conn.query_row(
+        &format!("SELECT {} FROM tags t WHERE t.id = ? AND t.deleted_at IS NULL", TAG_COLUMNS),
+        [id],
+        tag_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("tag", id))
+}
+
+fn live_tag_id(conn: &Connection, name: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT id FROM tags WHERE name = ? COLLATE NOCASE AND deleted_at IS NULL",
+            [name],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+fn ensure_tag_name_free(conn: &Connection, name: &str, except_id: Option<&str>) -> Result<()> {
+    match live_tag_id(conn, name)? {
+        Some(id) if except_id != Some(id.as_str()) => Err(AppError::Conflict {
+            message: format!("a tag named {} already exists", name),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Id of the parent of tag path `name`, reviving it from the trash or
+/// creating it (and its own parents) if needed. `None` at the top level.
+fn tag_parent(conn: &Connection, name: &str) -> Result<Option<String>> {
+    let Some((parent, _)) = name.rsplit_once('/') else {
+        return Ok(None);
+    };
+    if let Some(id) = live_tag_id(conn, parent)? {
+        return Ok(Some(id));
+    }
+
+    let grandparent_id = tag_parent(conn, parent)?;
+    let trashed: Option<String> = conn
+        .query_row(
+            "SELECT id FROM tags WHERE name = ? COLLATE NOCASE AND deleted_at IS NOT NULL 
+             ORDER BY deleted_at DESC LIMIT 1",
+            [parent],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let id = match trashed {
+        Some(id) => {
+            conn.execute(
+                "UPDATE tags SET deleted_at = NULL, name = ?1, parent_id = ?2 WHERE id = ?3",
+                (parent, &grandparent_id, &id),
+            )?;
+            id
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO tags (id, name, created_at, parent_id) VALUES (?1, ?2, ?3, ?4)",
+                (&id, parent, Utc::now().to_rfc3339(), &grandparent_id),
+            )?;
+            id
+        }
+    };
+    Ok(Some(id))
+}
+
+/// Gives tag `id` the path `name` and moves the paths of the tags below it
+/// along.
+fn rename_tag_subtree(conn: &Connection, id: &str, name: &str) -> Result<()> {
+    let old_name: String = conn
+        .query_row("SELECT name FROM tags WHERE id = ?", [id], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::not_found("tag", id))?;
+    if old_name == name {
+        return Ok(());
+    }
+    let inside = name
+        .get(..old_name.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}/", old_name)));
+    if inside {
+        return Err(AppError::validation("name", "cannot move a tag below itself"));
+    }
+    ensure_tag_name_free(conn, name, Some(id))?;
+
+    let parent_id = tag_parent(conn, name)?;
+    conn.execute(
+        &format!(
+            "{} UPDATE tags SET name = ?2 || substr(name, ?3) WHERE id IN subtree AND id <> ?1",
+            TAG_SUBTREE
+        ),
+        // substr() counts characters, not bytes
+        (id, name, old_name.chars().count() as i64 + 1),
+    )?;
+    conn.execute(
+        "UPDATE tags SET name = ?1, parent_id = ?2 WHERE id = ?3",
+        (name, parent_id, id),
+    )?;
+    Ok(())
+}
+
+fn merge_tag(conn: &Connection, source_id: &str, target_id: &str) -> Result<()> {
+    let source = query_tag(conn, source_id)?;
+    let target = query_tag(conn, target_id)?;
+    let below_source: bool = conn.query_row(
+        &format!("{} SELECT ?2 IN subtree", TAG_SUBTREE),
+        (source_id, target_id),
+        |row| row.get(0),
+    )?;
+    if below_source {
+        return Err(AppError::validation("target_id", "cannot merge a tag into itself or a tag below it"));
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO note_tags (note_id, tag_id) 
+         SELECT note_id, ?2 FROM note_tags WHERE tag_id = ?1",
+        (source_id, target_id),
+    )?;
+    conn.execute("DELETE FROM note_tags WHERE tag_id = ?", [source_id])?;
+
+    let children = {
+        let mut stmt = conn.prepare("SELECT id, name, deleted_at IS NULL FROM tags WHERE parent_id = ?")?;
+        let rows = stmt
+            .query_map([source_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+    for (child_id, child_name, live) in children {
+        // Children are direct ones, so their leaf is what follows the last
+        // slash however the parent's part of the path is spelled
+        let leaf = child_name.rsplit('/').next().unwrap_or_default();
+        let path = format!("{}/{}", target.name, leaf);
+        match live_tag_id(conn, &path)? {
+            Some(existing) if live => merge_tag(conn, &child_id, &existing)?,
+            _ if live => rename_tag_subtree(conn, &child_id, &path)?,
+            _ => {
+                conn.execute(
+                    "UPDATE tags SET parent_id = ?1 WHERE id = ?2",
+                    (target_id, &child_id),
+                )?;
+            }
+        }
+    }
+
+    conn.execute(
+        "UPDATE tags SET color = COALESCE(color, ?1), description = COALESCE(description, ?2) 
+         WHERE id = ?3",
+        (&source.color, &source.description, target_id),
+    )?;
+    conn.execute("DELETE FROM tags WHERE id = ?", [source_id])?;
+    Ok(())
+}
+
+/// Brings tag `id` back with the tags trashed along with it, and revives or
+/// recreates its parents.
+fn restore_tag(conn: &Connection, id: &str) -> Result<()> {
+    let (name, deleted_at): (String, Option<String>) = conn
+        .query_row("SELECT name, deleted_at FROM tags WHERE id = ?", [id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?
+        .ok_or_else(|| AppError::not_found("tag", id))?;
+    let Some(deleted_at) = deleted_at else {
+        return Err(AppError::not_found("tag", id));
+    };
+    ensure_tag_name_free(conn, &name, None)?;
+
+    let parent_id = tag_parent(conn, &name)?;
+    conn.execute(
+        &format!(
+            "{} UPDATE tags SET deleted_at = NULL WHERE id IN subtree AND deleted_at = ?2",
+            TAG_SUBTREE
+        ),
+        (id, &deleted_at),
+    )?;
+    conn.execute("UPDATE tags SET parent_id = ?1 WHERE id = ?2", (parent_id, id))?;
+    Ok(())
+}
+
+fn restore_project(conn: &Connection, id: &str) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE projects SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+        [id],
+    )?;
+    expect_changed(changed, "project", id)?;
+    conn.execute(
+        "UPDATE notes SET deleted_at = NULL, deleted_with_project = NULL 
+         WHERE deleted_with_project = ?",
+        [id],
+    )?;
+    Ok(())
+}
+
+fn read_setting<T: std::str::FromStr>(conn: &Connection, key: &str, default: T) -> Result<T> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM app_settings WHERE key = ?", [key], |row| row.get(0))
+        .optional()?;
+    Ok(value.and_then(|v| v.parse().ok()).unwrap_or(default))
+}
+
+fn write_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        (key, value),
+    )?;
+    Ok(())
+}
+
+fn trash_retention_days(conn: &Connection) -> Result<u32> {
+    read_setting(conn, TRASH_RETENTION_KEY, 30)
+}
+
+fn snapshot_policy(conn: &Connection) -> Result<SnapshotPolicy> {
+    let defaults = SnapshotPolicy::default();
+    Ok(SnapshotPolicy {
+        keep_daily: read_setting(conn, SNAPSHOT_KEEP_DAILY_KEY, defaults.keep_daily)?,
+        keep_weekly: read_setting(conn, SNAPSHOT_KEEP_WEEKLY_KEY, defaults.keep_weekly)?,
+        compress: read_setting(conn, SNAPSHOT_COMPRESS_KEY, defaults.compress)?,
+    })
+}
+
+/// Hard-deletes trashed rows, all of them or only those trashed before
+/// `cutoff`. Returns the number of notes, projects and tags removed.
+fn purge_trash_before(conn: &Connection, cutoff: Option<&str>) -> Result<usize> {
+    let mut purged = 0;
+    for table in ["notes", "projects", "tags"] {
+        purged += conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)",
+                table
+            ),
+            [cutoff],
+        )?;
+    }
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::DiffKind;
+
+    /// A fresh database in its own temporary directory.
+    fn test_db() -> DbState {
+        let dir = std::env::temp_dir().join(format!("markdown-editor-db-{}", uuid::Uuid::new_v4()));
+        DbState::new(dir).expect("failed to open test database")
+    }
+
+    fn project(id: &str) -> Project {
+        Project {
+            id: id.into(),
+            name: id.into(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            description: None,
+            color: None,
+            icon: None,
+            position: 0,
+            archived: false,
+        }
+    }
+
+    fn note(id: &str, title: &str, content: &str, project_id: &str) -> Note {
+        Note {
+            id: id.into(),
+            title: title.into(),
+            content: content.into(),
+            project_id: project_id.into(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            is_pinned: false,
+        }
+    }
+
+    fn trash_names(db: &DbState) -> Vec<(TrashKind, String)> {
+        db.get_trash().unwrap().into_iter().map(|item| (item.kind, item.name)).collect()
+    }
+
+    // Revisions
+
+    #[test]
+    fn autosaves_within_the_window_share_a_revision() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "Plan", "a", "p")).unwrap();
+        db.update_note(&note("n", "Plan", "a\nb", "p")).unwrap();
+        db.update_note(&note("n", "Plan", "a\nb\nc", "p")).unwrap();
+
+        let revisions = db.get_note_revisions("n").unwrap();
+        let kinds: Vec<&str> = revisions.iter().map(|r| r.kind.as_str()).collect();
+        assert_eq!(kinds, ["edit", "create"]);
+        assert_eq!(db.get_note_revision(&revisions[0].id).unwrap().content, "a\nb\nc");
+
+        // Saving the same content again records nothing
+        db.update_note(&note("n", "Plan", "a\nb\nc", "p")).unwrap();
+        assert_eq!(db.get_note_revisions("n").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn edits_after_the_window_start_a_revision() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "Plan", "a", "p")).unwrap();
+        db.update_note(&note("n", "Plan", "b", "p")).unwrap();
+        let old = (Utc::now() - Duration::seconds(REVISION_COALESCE_WINDOW_SECS + 1)).to_rfc3339();
+        db.connection
+            .lock()
+            .unwrap()
+            .execute("UPDATE note_revisions SET created_at = ? WHERE kind = 'edit'", [&old])
+            .unwrap();
+
+        db.update_note(&note("n", "Plan", "c", "p")).unwrap();
+        assert_eq!(db.get_note_revisions("n").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn restoring_a_revision_is_itself_a_revision() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "Plan", "one\ntwo", "p")).unwrap();
+        db.update_note(&note("n", "Plan v2", "one\nthree", "p")).unwrap();
+        let revisions = db.get_note_revisions("n").unwrap();
+        let (edit, create) = (&revisions[0].id, &revisions[1].id);
+
+        let diff = db.diff_note_revisions(create, edit).unwrap();
+        let changed: Vec<(DiffKind, &str)> = diff
+            .iter()
+            .filter(|line| line.kind != DiffKind::Equal)
+            .map(|line| (line.kind, line.text.as_str()))
+            .collect();
+        assert_eq!(changed, [(DiffKind::Delete, "two"), (DiffKind::Insert, "three")]);
+
+        let restored = db.restore_note_revision(create).unwrap();
+        assert_eq!((restored.title.as_str(), restored.content.as_str()), ("Plan", "one\ntwo"));
+        assert_eq!(db.get_note_revisions("n").unwrap()[0].kind, "restore");
+        assert!(db.get_note_revision("missing").is_err());
+    }
+
+    // Trash
+
+    #[test]
+    fn trashed_project_takes_its_notes_and_brings_them_back() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("a", "A", "", "p")).unwrap();
+        db.create_note(&note("b", "B", "", "p")).unwrap();
+        db.delete_note("b").unwrap();
+        db.delete_project("p").unwrap();
+
+        // The note trashed on its own stays a separate item
+        assert_eq!(trash_names(&db), [(TrashKind::Project, "p".into()), (TrashKind::Note, "B".into())]);
+        assert_eq!(db.get_trash().unwrap()[0].cascaded_notes, 1);
+        assert!(db.get_note("a").is_err());
+
+        db.restore_from_trash(TrashKind::Project, "p").unwrap();
+        assert!(db.get_note("a").is_ok());
+        assert_eq!(trash_names(&db), [(TrashKind::Note, "B".into())]);
+    }
+
+    #[test]
+    fn restoring_a_note_leaves_its_project_siblings_in_the_trash() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("a", "A", "", "p")).unwrap();
+        db.create_note(&note("b", "B", "", "p")).unwrap();
+        db.delete_project("p").unwrap();
+
+        db.restore_from_trash(TrashKind::Note, "a").unwrap();
+        assert!(db.get_project("p").is_ok());
+        assert!(db.get_note("a").is_ok());
+        assert!(db.get_note("b").is_err());
+        assert_eq!(trash_names(&db), [(TrashKind::Note, "B".into())]);
+        assert!(db.restore_from_trash(TrashKind::Note, "a").is_err());
+    }
+
+    #[test]
+    fn emptying_the_trash_purges_dependent_rows() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "- [ ] call back", "p")).unwrap();
+        db.create_tag(&Tag {
+            id: "t".into(),
+            name: "work".into(),
+            created_at: String::new(),
+            color: None,
+            description: None,
+            parent_id: None,
+        })
+        .unwrap();
+        db.add_tag_to_note("n", "t").unwrap();
+        db.delete_project("p").unwrap();
+
+        assert_eq!(db.empty_trash().unwrap(), 2);
+        let conn = db.connection.lock().unwrap();
+        for table in ["tasks", "note_tags", "note_revisions"] {
+            let count: i64 = conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 0, "{}", table);
+        }
+    }
+
+    #[test]
+    fn expired_trash_is_purged() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("old", "Old", "", "p")).unwrap();
+        db.create_note(&note("new", "New", "", "p")).unwrap();
+        db.delete_note("old").unwrap();
+        db.delete_note("new").unwrap();
+        let long_ago = (Utc::now() - Duration::days(31)).to_rfc3339();
+        db.connection
+            .lock()
+            .unwrap()
+            .execute("UPDATE notes SET deleted_at = ? WHERE id = 'old'", [&long_ago])
+            .unwrap();
+
+        assert_eq!(db.purge_expired_trash().unwrap(), 1);
+        assert_eq!(trash_names(&db), [(TrashKind::Note, "New".into())]);
+        db.set_trash_retention_days(0).unwrap();
+        assert_eq!(db.purge_expired_trash().unwrap(), 0);
+    }
+
+    #[test]
+    fn saved_notes_match_the_stored_row() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "first", "p")).unwrap();
+        let saved = db.update_note(&note("n", "N", "second", "p")).unwrap();
+        assert_eq!(db.get_note("n").unwrap().updated_at, saved.updated_at);
+
+        db.delete_note("n").unwrap();
+        db.restore_from_trash(TrashKind::Note, "n").unwrap();
+        assert_eq!(db.get_note("n").unwrap().updated_at, saved.updated_at);
+    }
+
+    #[test]
+    fn notes_are_not_created_in_trashed_projects() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.delete_project("p").unwrap();
+        assert!(matches!(
+            db.create_note(&note("n", "N", "", "p")),
+            Err(AppError::NotFound { entity: "project", .. })
+        ));
+        assert!(matches!(
+            db.create_note(&note("n", "N", "", "missing")),
+            Err(AppError::NotFound { entity: "project", .. })
+        ));
+    }
+
+    #[test]
+    fn trashed_notes_cannot_be_edited() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "first", "p")).unwrap();
+        let revision = db.get_note_revisions("n").unwrap()[0].id.clone();
+        db.delete_note("n").unwrap();
+
+        let not_found = |result: Result<Note>| matches!(result, Err(AppError::NotFound { .. }));
+        assert!(not_found(db.update_note(&note("n", "N", "second", "p"))));
+        assert!(not_found(db.restore_note_revision(&revision)));
+
+        db.restore_from_trash(TrashKind::Note, "n").unwrap();
+        assert_eq!(db.update_note(&note("n", "N", "second", "p")).unwrap().content, "second");
+    }
+
+    // Tasks
+
+    #[test]
+    fn task_updates_must_carry_the_version_they_read() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "- [ ] Draft", "p")).unwrap();
+        let read = db.get_tasks("n").unwrap().remove(0);
+
+        let mut blind = read.clone();
+        blind.updated_at.clear();
+        assert!(matches!(db.update_task(&blind), Err(AppError::Validation { field: "updated_at", .. })));
+
+        let mut done = read.clone();
+        done.completed = true;
+        let saved = db.update_task(&done).unwrap();
+        assert!(matches!(db.update_task(&done), Err(AppError::Conflict { .. })));
+        assert!(db.update_task(&saved).is_ok());
+    }
+
+    #[test]
+    fn tasks_of_trashed_notes_leave_the_note_alone() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "- [ ] Draft\n- [ ] Review", "p")).unwrap();
+        let mut tasks = db.get_tasks("n").unwrap();
+        db.delete_note("n").unwrap();
+        let revisions = db.get_note_revisions("n").unwrap().len();
+
+        let mut draft = tasks.remove(0);
+        draft.completed = true;
+        assert!(db.update_task(&draft).unwrap().completed);
+        db.delete_task(&tasks[0].id).unwrap();
+
+        db.restore_from_trash(TrashKind::Note, "n").unwrap();
+        assert_eq!(db.get_note("n").unwrap().content, "- [ ] Draft\n- [ ] Review");
+        assert_eq!(db.get_note_revisions("n").unwrap().len(), revisions);
+        // Draft keeps its checkbox; Review's gets a new task
+        db.update_note(&note("n", "N", "- [ ] Draft\n- [ ] Review", "p")).unwrap();
+        let ids: Vec<String> = db.get_tasks("n").unwrap().into_iter().map(|task| task.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[0], draft.id);
+    }
+
+    #[test]
+    fn ticking_a_recurring_checkbox_schedules_the_next_occurrence() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "- [ ] Water the plants", "p")).unwrap();
+        let mut task = db.get_tasks("n").unwrap().remove(0);
+        task.due_date = Some("2024-11-20".into());
+        task.recurrence = Some("FREQ=WEEKLY".into());
+        db.update_task(&task).unwrap();
+
+        db.update_note(&note("n", "N", "- [x] Water the plants", "p")).unwrap();
+        let occurrences = db.get_task_occurrences(&task.id).unwrap();
+        assert_eq!(occurrences.len(), 2);
+        assert!(occurrences[0].completed);
+        assert!(!occurrences[1].completed);
+        assert_eq!(occurrences[1].recurrence.as_deref(), Some("FREQ=WEEKLY"));
+
+        // Saving the note again does not schedule another one
+        db.update_note(&note("n", "N", "- [x] Water the plants!", "p")).unwrap();
+        assert_eq!(db.get_task_occurrences(&task.id).unwrap().len(), 2);
+    }
+
+    // Tags
+
+    fn tag(id: &str, name: &str) -> Tag {
+        Tag {
+            id: id.into(),
+            name: name.into(),
+            created_at: String::new(),
+            color: None,
+            description: None,
+            parent_id: None,
+        }
+    }
+
+    fn tag_names(db: &DbState) -> Vec<String> {
+        db.get_tags().unwrap().into_iter().map(|tag| tag.name).collect()
+    }
+
+    #[test]
+    fn renaming_a_tag_rewrites_its_subtree() {
+        let db = test_db();
+        db.create_tag(&tag("acme", "équipe/acme")).unwrap();
+        db.create_tag(&tag("q1", "équipe/acme/q1")).unwrap();
+        let parent = db.get_tags().unwrap().into_iter().find(|t| t.name == "équipe").unwrap();
+
+        db.rename_tag(&parent.id, "team").unwrap();
+        assert_eq!(tag_names(&db), ["team", "team/acme", "team/acme/q1"]);
+        assert!(matches!(
+            db.rename_tag(&parent.id, "team/acme/inner"),
+            Err(AppError::Validation { .. })
+        ));
+    }
+
+    #[test]
+    fn moving_a_tag_takes_its_children() {
+        let db = test_db();
+        db.create_tag(&tag("later", "later")).unwrap();
+        db.create_tag(&tag("q1", "café/q1")).unwrap();
+        let cafe = db.get_tags().unwrap().into_iter().find(|t| t.name == "café").unwrap();
+
+        let moved = db.move_tag(&cafe.id, Some("later")).unwrap();
+        assert_eq!(moved.parent_id.as_deref(), Some("later"));
+        assert_eq!(tag_names(&db), ["later", "later/café", "later/café/q1"]);
+        db.move_tag(&cafe.id, None).unwrap();
+        assert_eq!(tag_names(&db), ["café", "café/q1", "later"]);
+    }
+
+    #[test]
+    fn tagging_needs_a_live_note_and_tag() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "", "p")).unwrap();
+        db.create_tag(&tag("t", "t")).unwrap();
+        let missing = |result: Result<()>| match result {
+            Err(AppError::NotFound { entity, id }) => (entity, id),
+            other => panic!("expected NotFound, got {:?}", other),
+        };
+
+        assert_eq!(missing(db.add_tag_to_note("x", "t")), ("note", "x".into()));
+        assert_eq!(missing(db.add_tag_to_note("n", "x")), ("tag", "x".into()));
+        db.delete_tag("t").unwrap();
+        assert_eq!(missing(db.add_tag_to_note("n", "t")), ("tag", "t".into()));
+        db.restore_from_trash(TrashKind::Tag, "t").unwrap();
+        db.add_tag_to_note("n", "t").unwrap();
+        assert_eq!(db.get_note_tags("n").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn merging_tags_moves_notes_and_children() {
+        let db = test_db();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "", "p")).unwrap();
+        db.create_tag(&tag("old", "ancien")).unwrap();
+        db.create_tag(&tag("old-q1", "ancien/q1")).unwrap();
+        db.create_tag(&tag("new", "nouveau")).unwrap();
+        db.create_tag(&tag("new-q1", "nouveau/q1")).unwrap();
+        db.add_tag_to_note("n", "old").unwrap();
+        db.add_tag_to_note("n", "old-q1").unwrap();
+
+        db.merge_tags("old", "new").unwrap();
+        assert_eq!(tag_names(&db), ["nouveau", "nouveau/q1"]);
+        let tagged = |id: &str| db.get_notes_by_tag(id, false).unwrap().len();
+        assert_eq!((tagged("new"), tagged("new-q1")), (1, 1));
+    }
+
+    #[test]
+    fn merging_keeps_child_leaves_whatever_the_parent_spelling() {
+        let db = test_db();
+        db.create_tag(&tag("old", "équipe")).unwrap();
+        db.create_tag(&tag("new", "team")).unwrap();
+        // A child whose path spells the parent differently, as older rows may
+        db.connection
+            .lock()
+            .unwrap()
+            .execute("INSERT INTO tags (id, name, parent_id) VALUES ('child', 'Equipe/acme', 'old')", [])
+            .unwrap();
+
+        db.merge_tags("old", "new").unwrap();
+        assert_eq!(tag_names(&db), ["team", "team/acme"]);
+    }
+
+    // Projects
+
+    #[test]
+    fn new_projects_are_not_archived() {
+        let db = test_db();
+        let mut archived = project("p");
+        archived.archived = true;
+        assert!(!db.create_project(&archived).unwrap().archived);
+        assert_eq!(db.get_projects(false).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn projects_and_tags_take_theme_colors() {
+        let db = test_db();
+        let mut colored = project("p");
+        colored.color = Some(" RGB(59, 130, 246) ".into());
+        assert_eq!(db.create_project(&colored).unwrap().color.as_deref(), Some("rgb(59, 130, 246)"));
+        let mut colored = tag("t", "t");
+        colored.color = Some("#3B82F6".into());
+        assert_eq!(db.create_tag(&colored).unwrap().color.as_deref(), Some("#3b82f6"));
+        colored.color = Some("blue-ish".into());
+        assert!(matches!(db.update_tag(&colored), Err(AppError::Validation { field: "color", .. })));
+    }
+
+    #[test]
+    fn missing_rows_name_what_was_not_found() {
+        let db = test_db();
+        let missing = |result: Result<()>| match result {
+            Err(AppError::NotFound { entity, id }) => (entity, id),
+            other => panic!("expected NotFound, got {:?}", other),
+        };
+        assert_eq!(missing(db.get_note("n").map(drop)), ("note", "n".into()));
+        assert_eq!(missing(db.get_backup("b").map(drop)), ("backup", "b".into()));
+        assert_eq!(missing(db.get_note_revision("r").map(drop)), ("revision", "r".into()));
+        assert_eq!(missing(db.get_task_occurrences("t").map(drop)), ("task", "t".into()));
+    }
+
+    #[test]
+    fn project_orders_list_each_project_once() {
+        let db = test_db();
+        for id in ["a", "b", "c"] {
+            db.create_project(&project(id)).unwrap();
+        }
+        let ids = |db: &DbState| -> Vec<String> {
+            db.get_projects(false).unwrap().into_iter().map(|summary| summary.project.id).collect()
+        };
+
+        db.reorder_projects(&["c".to_string(), "a".to_string()]).unwrap();
+        assert_eq!(ids(&db), ["c", "a", "b"]);
+        let twice = ["b".to_string(), "b".to_string()];
+        assert!(matches!(db.reorder_projects(&twice), Err(AppError::Validation { field: "ids", .. })));
+        assert_eq!(ids(&db), ["c", "a", "b"]);
+    }
+
+    // Backups
+
+    fn workspace(id: &str) -> Workspace {
+        Workspace {
+            id: id.into(),
+            name: id.to_uppercase(),
+            type_: "local".into(),
+            theme_id: "light".into(),
+            path: None,
+            current_profile_id: None,
+            database_path: None,
+        }
+    }
+
+    fn tab(id: &str, workspace_id: &str, position: i32, is_active: bool) -> Tab {
+        Tab {
+            id: id.into(),
+            workspace_id: workspace_id.into(),
+            title: id.to_uppercase(),
+            content: Some(format!("{} content", id)),
+            type_: "markdown".into(),
+            position,
+            is_active,
+            note_id: None,
+        }
+    }
+
+    fn open_tab_ids(db: &DbState, workspace_id: &str) -> Vec<String> {
+        db.get_workspace_tabs(workspace_id).unwrap().into_iter().map(|tab| tab.id).collect()
+    }
+
+    #[test]
+    fn restoring_a_backup_can_be_undone() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        db.create_tab(&tab("a", "w", 0, true)).unwrap();
+        db.create_tab(&tab("b", "w", 1, false)).unwrap();
+        let backup = db.create_workspace_backup("w").unwrap();
+        assert_eq!(backup.kind, "snapshot");
+
+        db.rename_workspace("w", "Renamed").unwrap();
+        db.delete_tab("a").unwrap();
+
+        let safety = db.restore_backup(&backup.id).unwrap();
+        assert_eq!(safety.kind, "pre-restore");
+        assert_eq!(db.get_workspaces().unwrap()[0].name, "W");
+        assert_eq!(open_tab_ids(&db, "w"), ["a", "b"]);
+        assert!(db.get_workspace_tabs("w").unwrap()[0].is_active);
+
+        db.restore_backup(&safety.id).unwrap();
+        assert_eq!(db.get_workspaces().unwrap()[0].name, "Renamed");
+        assert_eq!(open_tab_ids(&db, "w"), ["b"]);
+        assert_eq!(db.list_backups("w").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn restored_tabs_keep_the_content_of_purged_notes() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "kept", "p")).unwrap();
+        db.create_tab(&Tab { note_id: Some("n".into()), ..tab("a", "w", 0, true) }).unwrap();
+        let backup = db.create_workspace_backup("w").unwrap();
+
+        db.delete_tab("a").unwrap();
+        db.delete_note("n").unwrap();
+        db.empty_trash().unwrap();
+        db.restore_backup(&backup.id).unwrap();
+
+        let restored = db.get_workspace_tabs("w").unwrap().remove(0);
+        assert_eq!((restored.note_id, restored.content.as_deref()), (None, Some("kept")));
+    }
+
+    #[test]
+    fn backups_that_are_not_snapshots_are_refused() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        db.create_backup("w", "not json").unwrap();
+        let backup = db.list_backups("w").unwrap().remove(0);
+        assert!(matches!(db.restore_backup(&backup.id), Err(AppError::Validation { field: "data", .. })));
+        assert!(db.get_backup(&backup.id).unwrap().snapshot.is_none());
+    }
+
+    #[test]
+    fn a_backup_only_restores_its_own_workspace() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        db.create_workspace(&workspace("v"), None).unwrap();
+        let of_v = db.create_workspace_backup("v").unwrap();
+        db.create_tab(&tab("a", "v", 0, true)).unwrap();
+
+        // A snapshot of `v` filed under `w`
+        db.create_backup("w", &db.get_backup(&of_v.id).unwrap().data).unwrap();
+        let misfiled = db.list_backups("w").unwrap().remove(0);
+        assert!(matches!(db.restore_backup(&misfiled.id), Err(AppError::Validation { field: "workspace", .. })));
+        assert_eq!(open_tab_ids(&db, "v"), ["a"]);
+        assert_eq!(db.list_backups("w").unwrap().len(), 1);
+    }
+
+    // Tabs
+
+    fn active_tab_ids(db: &DbState, workspace_id: &str) -> Vec<String> {
+        let tabs = db.get_workspace_tabs(workspace_id).unwrap();
+        tabs.into_iter().filter(|tab| tab.is_active).map(|tab| tab.id).collect()
+    }
+
+    #[test]
+    fn reordering_puts_the_listed_tabs_first() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        for (position, id) in ["a", "b", "c"].into_iter().enumerate() {
+            db.create_tab(&tab(id, "w", position as i32, false)).unwrap();
+        }
+
+        db.reorder_tabs("w", &["c".to_string()]).unwrap();
+        assert_eq!(open_tab_ids(&db, "w"), ["c", "a", "b"]);
+        let positions: Vec<i32> = db.get_workspace_tabs("w").unwrap().iter().map(|tab| tab.position).collect();
+        assert_eq!(positions, [0, 1, 2]);
+        assert!(matches!(db.reorder_tabs("w", &["x".to_string()]), Err(AppError::NotFound { .. })));
+        let twice = ["a".to_string(), "a".to_string()];
+        assert!(matches!(db.reorder_tabs("w", &twice), Err(AppError::Validation { field: "ids", .. })));
+        assert_eq!(open_tab_ids(&db, "w"), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn a_workspace_has_one_active_tab() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        db.create_tab(&tab("a", "w", 0, true)).unwrap();
+        db.create_tab(&tab("b", "w", 1, true)).unwrap();
+        assert_eq!(active_tab_ids(&db, "w"), ["b"]);
+
+        db.activate_tab("a").unwrap();
+        assert_eq!(active_tab_ids(&db, "w"), ["a"]);
+        db.update_tab_active_state("a", false).unwrap();
+        assert!(active_tab_ids(&db, "w").is_empty());
+    }
+
+    #[test]
+    fn closed_tabs_reopen_where_they_were() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        for (position, id) in ["a", "b", "c"].into_iter().enumerate() {
+            db.create_tab(&tab(id, "w", position as i32, id == "b")).unwrap();
+        }
+
+        db.delete_tab("b").unwrap();
+        assert_eq!(open_tab_ids(&db, "w"), ["a", "c"]);
+        assert_eq!(active_tab_ids(&db, "w"), ["c"]);
+
+        let reopened = db.reopen_closed_tab("w").unwrap().unwrap();
+        assert_eq!((reopened.id.as_str(), reopened.position), ("b", 1));
+        assert_eq!(reopened.content.as_deref(), Some("b content"));
+        assert_eq!(open_tab_ids(&db, "w"), ["a", "b", "c"]);
+        assert_eq!(active_tab_ids(&db, "w"), ["b"]);
+        assert!(db.reopen_closed_tab("w").unwrap().is_none());
+    }
+
+    #[test]
+    fn the_reopen_stack_keeps_the_latest_tabs() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        for i in 0..CLOSED_TAB_LIMIT + 5 {
+            let id = format!("t{}", i);
+            db.create_tab(&tab(&id, "w", 0, false)).unwrap();
+            db.delete_tab(&id).unwrap();
+        }
+
+        let closed = db.get_closed_tabs("w").unwrap();
+        assert_eq!(closed.len(), CLOSED_TAB_LIMIT as usize);
+        assert_eq!(closed[0].tab_id, format!("t{}", CLOSED_TAB_LIMIT + 4));
+    }
+
+    #[test]
+    fn tabs_bound_to_a_note_show_its_content() {
+        let db = test_db();
+        db.create_workspace(&workspace("w"), None).unwrap();
+        db.create_project(&project("p")).unwrap();
+        db.create_note(&note("n", "N", "from the note", "p")).unwrap();
+        db.create_tab(&tab("a", "w", 0, true)).unwrap();
+
+        let bound = db.update_tab(&Tab { note_id: Some("n".into()), ..tab("a", "w", 0, true) }).unwrap();
+        assert_eq!(bound.content.as_deref(), Some("from the note"));
+        db.update_note(&note("n", "N", "edited", "p")).unwrap();
+        assert_eq!(db.get_workspace_tabs("w").unwrap()[0].content.as_deref(), Some("edited"));
+
+        db.delete_note("n").unwrap();
+        let rebound = Tab { note_id: Some("n".into()), ..tab("a", "w", 0, true) };
+        assert!(matches!(db.update_tab(&rebound), Err(AppError::NotFound { .. })));
+    }
+
+    // Workspace databases
+
+    fn project_ids(db: &DbState) -> Vec<String> {
+        db.get_projects(true).unwrap().into_iter().map(|summary| summary.project.id).collect()
+    }
+
+    /// `db` with workspace `home` in the main database, holding project
+    /// `main-p`, and workspace `own` in its own database.
+    fn db_with_own_workspace() -> (DbState, PathBuf) {
+        let db = test_db();
+        let dir = db.data_dir.join("own");
+        db.create_workspace(&workspace("home"), None).unwrap();
+        db.create_workspace(&workspace("own"), Some(&dir)).unwrap();
+        db.create_project(&project("main-p")).unwrap();
+        (db, dir)
+    }
+
+    #[test]
+    fn switching_routes_commands_to_the_workspace_database() {
+        let (db, dir) = db_with_own_workspace();
+        let own = db.switch_workspace("own").unwrap();
+        assert_eq!(own.database_path, Some(dir.join(WORKSPACE_DATABASE_FILE).to_string_lossy().into_owned()));
+        assert!(project_ids(&db).is_empty());
+        db.create_project(&project("own-p")).unwrap();
+        // The workspace list stays in the main database
+        assert_eq!(db.get_workspaces().unwrap().len(), 2);
+        assert_eq!(db.rename_workspace("own", "Own").unwrap().name, "Own");
+        assert_eq!(db.get_active_workspace().unwrap().unwrap().name, "Own");
+
+        db.switch_workspace("home").unwrap();
+        assert_eq!(project_ids(&db), ["main-p"]);
+        db.switch_workspace("own").unwrap();
+        assert_eq!(project_ids(&db), ["own-p"]);
+    }
+
+    #[test]
+    fn databases_switched_away_from_stay_open() {
+        let (db, _) = db_with_own_workspace();
+        db.switch_workspace("own").unwrap();
+        // Temporary tables live as long as their connection
+        db.connection.lock().unwrap().execute_batch("CREATE TEMP TABLE marker (x)").unwrap();
+        db.switch_workspace("home").unwrap();
+        db.switch_workspace("own").unwrap();
+        let marked = db.connection.lock().unwrap().prepare("SELECT x FROM temp.marker").is_ok();
+        assert!(marked);
+    }
+
+    #[test]
+    fn deleting_the_active_workspace_falls_back_to_the_main_database() {
+        let (db, dir) = db_with_own_workspace();
+        db.switch_workspace("own").unwrap();
+        db.create_project(&project("own-p")).unwrap();
+
+        let kept = db.delete_workspace("own").unwrap().unwrap();
+        assert_eq!(kept, dir.join(WORKSPACE_DATABASE_FILE));
+        assert!(kept.is_file());
+        assert!(db.get_active_workspace().unwrap().is_none());
+        assert_eq!(project_ids(&db), ["main-p"]);
+
+        // The kept file can be opened again, with its contents
+        assert_eq!(db.open_workspace_database(&dir).unwrap().id, "own");
+        db.switch_workspace("own").unwrap();
+        assert_eq!(project_ids(&db), ["own-p"]);
+        assert_eq!(db.delete_workspace("home").unwrap(), None);
+        assert_eq!(db.get_workspaces().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn a_missing_workspace_database_is_not_recreated() {
+        let (db, dir) = db_with_own_workspace();
+        fs::remove_file(dir.join(WORKSPACE_DATABASE_FILE)).unwrap();
+        assert!(matches!(db.switch_workspace("own"), Err(AppError::NotFound { .. })));
+        assert!(!dir.join(WORKSPACE_DATABASE_FILE).exists());
+        assert_eq!(project_ids(&db), ["main-p"]);
+    }
+
+    #[test]
+    fn switching_while_other_commands_run_does_not_deadlock() {
+        let (db, _) = db_with_own_workspace();
+        let db = std::sync::Arc::new(db);
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let db = db.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        match i % 2 {
+                            0 => {
+                                db.switch_workspace(if i == 0 { "own" } else { "home" }).unwrap();
+                                db.get_workspaces().unwrap();
+                            }
+                            _ => {
+                                db.get_projects(false).unwrap();
+                                db.get_active_workspace().unwrap();
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
     }
 }
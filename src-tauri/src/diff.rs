@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// One line of a diff. Line numbers are 1-based; `old_line` is absent for
+/// inserted lines and `new_line` for deleted ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+/// Upper bound on the LCS table for the changed region. Past it the region
+/// is reported as a single block replace instead of a minimal diff.
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+/// Line-level diff of `old` against `new` based on the longest common
+/// subsequence. The common head and tail are stripped first, so the
+/// quadratic table only covers the region that actually changed; when that
+/// region is still too large, its old lines are all deleted and its new
+/// lines all inserted.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let fits = (a_mid.len() + 1)
+        .checked_mul(b_mid.len() + 1)
+        .is_some_and(|cells| cells <= MAX_TABLE_CELLS);
+
+    // lcs[i][j] = LCS length of a_mid[i..] and b_mid[j..]
+    let mut lcs = if fits {
+        vec![vec![0usize; b_mid.len() + 1]; a_mid.len() + 1]
+    } else {
+        Vec::new()
+    };
+    for i in (0..lcs.len().saturating_sub(1)).rev() {
+        for j in (0..b_mid.len()).rev() {
+            lcs[i][j] = if a_mid[i] == b_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::with_capacity(a.len().max(b.len()));
+    let equal = |out: &mut Vec<DiffLine>, i: usize, j: usize, text: &str| {
+        out.push(DiffLine {
+            kind: DiffKind::Equal,
+            old_line: Some(i + 1),
+            new_line: Some(j + 1),
+            text: text.to_string(),
+        })
+    };
+
+    for (k, line) in a[..prefix].iter().enumerate() {
+        equal(&mut out, k, k, line);
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a_mid.len() || j < b_mid.len() {
+        if fits && i < a_mid.len() && j < b_mid.len() && a_mid[i] == b_mid[j] {
+            equal(&mut out, prefix + i, prefix + j, a_mid[i]);
+            i += 1;
+            j += 1;
+        } else if j < b_mid.len()
+            && (i == a_mid.len() || (fits && lcs[i][j + 1] > lcs[i + 1][j]))
+        {
+            out.push(DiffLine {
+                kind: DiffKind::Insert,
+                old_line: None,
+                new_line: Some(prefix + j + 1),
+                text: b_mid[j].to_string(),
+            });
+            j += 1;
+        } else {
+            out.push(DiffLine {
+                kind: DiffKind::Delete,
+                old_line: Some(prefix + i + 1),
+                new_line: None,
+                text: a_mid[i].to_string(),
+            });
+            i += 1;
+        }
+    }
+
+    for k in 0..suffix {
+        let old_index = a.len() - suffix + k;
+        let new_index = b.len() - suffix + k;
+        equal(&mut out, old_index, new_index, a[old_index]);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diff: &[DiffLine]) -> Vec<String> {
+        diff.iter()
+            .map(|l| {
+                let sign = match l.kind {
+                    DiffKind::Equal => ' ',
+                    DiffKind::Insert => '+',
+                    DiffKind::Delete => '-',
+                };
+                format!("{}{}", sign, l.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_text_is_all_equal() {
+        let diff = diff_lines("a\nb", "a\nb");
+        assert!(diff.iter().all(|l| l.kind == DiffKind::Equal));
+        assert_eq!(diff.len(), 2);
+    }
+
+    #[test]
+    fn reports_changes_with_line_numbers() {
+        let diff = diff_lines("# Title\nold line\nkeep\ntail", "# Title\nkeep\nnew line\ntail");
+        assert_eq!(
+            render(&diff),
+            vec![" # Title", "-old line", " keep", "+new line", " tail"]
+        );
+        assert_eq!(diff[1].old_line, Some(2));
+        assert_eq!(diff[3].new_line, Some(3));
+        assert_eq!((diff[4].old_line, diff[4].new_line), (Some(4), Some(4)));
+    }
+
+    #[test]
+    fn handles_empty_sides() {
+        assert_eq!(render(&diff_lines("", "a\nb")), vec!["+a", "+b"]);
+        assert_eq!(render(&diff_lines("a", "")), vec!["-a"]);
+    }
+
+    #[test]
+    fn oversized_changes_fall_back_to_a_block_replace() {
+        let old: Vec<String> = (0..3000).map(|n| format!("old {}", n % 7)).collect();
+        let new: Vec<String> = (0..3000).map(|n| format!("new {}", n % 5)).collect();
+        let old = format!("head\n{}\ntail", old.join("\n"));
+        let new = format!("head\n{}\ntail", new.join("\n"));
+
+        let diff = diff_lines(&old, &new);
+        assert_eq!(diff.len(), 6002);
+        assert_eq!(diff[0].kind, DiffKind::Equal);
+        assert!(diff[1..3001].iter().all(|l| l.kind == DiffKind::Delete));
+        assert!(diff[3001..6001].iter().all(|l| l.kind == DiffKind::Insert));
+        assert_eq!(diff[3001].new_line, Some(2));
+        assert_eq!((diff[6001].old_line, diff[6001].new_line), (Some(3002), Some(3002)));
+    }
+}
@@ -0,0 +1,136 @@
+use rusqlite::ErrorCode as SqliteCode;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::migrations::MigrationError;
+
+/// Errors returned by `DbState` and every command. Serialized for the
+/// frontend as `{ code, message, details }`, where `code` is one of the
+/// variant names and never changes meaning.
+#[derive(Debug)]
+pub enum AppError {
+    NotFound { entity: &'static str, id: String },
+    Conflict { message: String },
+    Validation { field: &'static str, message: String },
+    Storage { message: String, sqlite_code: Option<String>, busy: bool },
+    Io { message: String },
+}
+
+pub type Result<T, E = AppError> = std::result::Result<T, E>;
+
+impl AppError {
+    pub fn not_found(entity: &'static str, id: impl Into<String>) -> Self {
+        AppError::NotFound { entity, id: id.into() }
+    }
+
+    pub fn validation(field: &'static str, message: impl Into<String>) -> Self {
+        AppError::Validation { field, message: message.into() }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound { .. } => "NotFound",
+            AppError::Conflict { .. } => "Conflict",
+            AppError::Validation { .. } => "Validation",
+            AppError::Storage { .. } => "Storage",
+            AppError::Io { .. } => "Io",
+        }
+    }
+
+    fn details(&self) -> Value {
+        match self {
+            AppError::NotFound { entity, id } => json!({ "entity": entity, "id": id }),
+            AppError::Conflict { .. } => Value::Null,
+            AppError::Validation { field, .. } => json!({ "field": field }),
+            AppError::Storage { sqlite_code, busy, .. } => {
+                json!({ "sqlite_code": sqlite_code, "busy": busy })
+            }
+            AppError::Io { .. } => Value::Null,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound { entity, id } => write!(f, "{} '{}' not found", entity, id),
+            AppError::Conflict { message } => write!(f, "{}", message),
+            AppError::Validation { field, message } => write!(f, "{}: {}", field, message),
+            AppError::Storage { message, .. } => write!(f, "{}", message),
+            AppError::Io { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match &e {
+            rusqlite::Error::SqliteFailure(failure, _) => match failure.code {
+                SqliteCode::ConstraintViolation => {
+                    // Extended codes: 787 FOREIGNKEY, 1299 NOTNULL, 275 CHECK
+                    match failure.extended_code {
+                        787 => AppError::validation("reference", e.to_string()),
+                        1299 | 275 => AppError::validation("value", e.to_string()),
+                        _ => AppError::Conflict { message: e.to_string() },
+                    }
+                }
+                code => AppError::Storage {
+                    message: e.to_string(),
+                    sqlite_code: Some(format!("{:?}", code)),
+                    busy: matches!(code, SqliteCode::DatabaseBusy | SqliteCode::DatabaseLocked),
+                },
+            },
+            // Including QueryReturnedNoRows: lookups report the missing
+            // entity themselves, so one getting here is a query that should
+            // always have found a row.
+            _ => AppError::Storage {
+                message: e.to_string(),
+                sqlite_code: None,
+                busy: false,
+            },
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io { message: e.to_string() }
+    }
+}
+
+impl From<MigrationError> for AppError {
+    fn from(e: MigrationError) -> Self {
+        match e {
+            MigrationError::Sqlite(e) => e.into(),
+            MigrationError::DatabaseTooNew { .. } | MigrationError::ForeignKeyViolation { .. } => {
+                AppError::Storage {
+                    message: e.to_string(),
+                    sqlite_code: None,
+                    busy: false,
+                }
+            }
+        }
+    }
+}
+
+/// Turns "no row was touched" into `NotFound` for updates and deletes.
+pub fn expect_changed(changed: usize, entity: &'static str, id: &str) -> Result<()> {
+    if changed == 0 {
+        Err(AppError::not_found(entity, id))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const DEFAULT_DEPTH: u32 = 2;
+const DEFAULT_MAX_NODES: usize = 500;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphQuery {
+    /// Limits the graph to one project; otherwise the whole workspace.
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Only keeps notes within `depth` links of this note.
+    #[serde(default)]
+    pub focus_note_id: Option<String>,
+    #[serde(default)]
+    pub depth: Option<u32>,
+    /// Tag ids; when not empty, notes need at least one of them.
+    #[serde(default)]
+    pub include_tags: Vec<String>,
+    /// Tag ids; notes with any of them are left out.
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    #[serde(default)]
+    pub max_nodes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Note,
+    Tag,
+    Project,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    /// Note to note, following the direction of the link.
+    Link,
+    /// Note to tag.
+    Tag,
+    /// Note to project.
+    Project,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    /// `<kind>:<entity id>`, unique across kinds.
+    pub id: String,
+    pub kind: NodeKind,
+    pub entity_id: String,
+    pub label: String,
+    /// Hops from the focal note; absent without a focus and for tag and
+    /// project nodes.
+    pub distance: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    /// Number of links from source to target; 1 for membership edges.
+    pub weight: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GraphData {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Some notes matched but were dropped to stay within `max_nodes`.
+    pub truncated: bool,
+}
+
+/// Everything the graph is computed from, already limited to live rows.
+#[derive(Default)]
+pub struct GraphInput {
+    /// `(id, title, project_id)`, most recently updated first.
+    pub notes: Vec<(String, String, String)>,
+    /// `(id, name)`
+    pub projects: Vec<(String, String)>,
+    /// `(id, name)`
+    pub tags: Vec<(String, String)>,
+    /// `(note_id, tag_id)`
+    pub note_tags: Vec<(String, String)>,
+    /// `(source note id, target note id)`, one entry per link.
+    pub links: Vec<(String, String)>,
+}
+
+fn node_id(kind: NodeKind, id: &str) -> String {
+    let prefix = match kind {
+        NodeKind::Note => "note",
+        NodeKind::Tag => "tag",
+        NodeKind::Project => "project",
+    };
+    format!("{}:{}", prefix, id)
+}
+
+/// Picks the notes matching `query`, closest to the focus first (or best
+/// connected first without one), and adds notes with their tag and project
+/// nodes until `max_nodes` would be exceeded.
+pub fn build(input: &GraphInput, query: &GraphQuery) -> GraphData {
+    let max_nodes = query.max_nodes.unwrap_or(DEFAULT_MAX_NODES);
+
+    let mut tags_of: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (note_id, tag_id) in &input.note_tags {
+        tags_of.entry(note_id).or_default().push(tag_id);
+    }
+    let matches = |note_id: &str, project_id: &str| {
+        let tags = tags_of.get(note_id).map(Vec::as_slice).unwrap_or_default();
+        query.project_id.as_deref().is_none_or(|p| p == project_id)
+            && (query.include_tags.is_empty() || tags.iter().any(|t| query.include_tags.iter().any(|i| i == t)))
+            && !tags.iter().any(|t| query.exclude_tags.iter().any(|e| e == t))
+    };
+    let candidates: HashMap<&str, (&str, &str)> = input
+        .notes
+        .iter()
+        .filter(|(id, _, project_id)| matches(id, project_id))
+        .map(|(id, title, project_id)| (id.as_str(), (title.as_str(), project_id.as_str())))
+        .collect();
+
+    let mut neighbours: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (source, target) in &input.links {
+        if candidates.contains_key(source.as_str()) && candidates.contains_key(target.as_str()) {
+            neighbours.entry(source).or_default().push(target);
+            neighbours.entry(target).or_default().push(source);
+        }
+    }
+
+    // Notes in the order they are admitted, with their distance to the focus
+    let ordered: Vec<(&str, Option<u32>)> = match &query.focus_note_id {
+        Some(focus) => {
+            let depth = query.depth.unwrap_or(DEFAULT_DEPTH);
+            let mut seen = HashSet::new();
+            let mut order = Vec::new();
+            let mut queue = VecDeque::new();
+            if let Some((&focus, _)) = candidates.get_key_value(focus.as_str()) {
+                seen.insert(focus);
+                queue.push_back((focus, 0));
+            }
+            while let Some((id, distance)) = queue.pop_front() {
+                order.push((id, Some(distance)));
+                if distance == depth {
+                    continue;
+                }
+                let mut next: Vec<&str> = neighbours.get(id).cloned().unwrap_or_default();
+                next.sort_by_key(|n| std::cmp::Reverse(neighbours.get(n).map_or(0, Vec::len)));
+                for n in next {
+                    if seen.insert(n) {
+                        queue.push_back((n, distance + 1));
+                    }
+                }
+            }
+            order
+        }
+        None => {
+            let mut order: Vec<(usize, &str)> = input
+                .notes
+                .iter()
+                .enumerate()
+                .filter(|(_, (id, _, _))| candidates.contains_key(id.as_str()))
+                .map(|(recency, (id, _, _))| (recency, id.as_str()))
+                .collect();
+            // Best connected first; ties keep the most recently updated first
+            order.sort_by_key(|(recency, id)| (std::cmp::Reverse(neighbours.get(id).map_or(0, Vec::len)), *recency));
+            order.into_iter().map(|(_, id)| (id, None)).collect()
+        }
+    };
+
+    let project_names: HashMap<&str, &str> =
+        input.projects.iter().map(|(id, name)| (id.as_str(), name.as_str())).collect();
+    let tag_names: HashMap<&str, &str> = input.tags.iter().map(|(id, name)| (id.as_str(), name.as_str())).collect();
+
+    let mut graph = GraphData::default();
+    let mut present: HashSet<String> = HashSet::new();
+    let mut included: HashSet<&str> = HashSet::new();
+
+    for (note_id, distance) in &ordered {
+        let (title, project_id) = candidates[note_id];
+        let note_tags: Vec<&str> = tags_of
+            .get(note_id)
+            .map(|tags| tags.iter().copied().filter(|t| tag_names.contains_key(t)).collect())
+            .unwrap_or_default();
+
+        let mut new_nodes = vec![GraphNode {
+            id: node_id(NodeKind::Note, note_id),
+            kind: NodeKind::Note,
+            entity_id: note_id.to_string(),
+            label: title.to_string(),
+            distance: *distance,
+        }];
+        if let Some(name) = project_names.get(project_id) {
+            new_nodes.push(GraphNode {
+                id: node_id(NodeKind::Project, project_id),
+                kind: NodeKind::Project,
+                entity_id: project_id.to_string(),
+                label: name.to_string(),
+                distance: None,
+            });
+        }
+        for tag_id in &note_tags {
+            new_nodes.push(GraphNode {
+                id: node_id(NodeKind::Tag, tag_id),
+                kind: NodeKind::Tag,
+                entity_id: tag_id.to_string(),
+                label: tag_names[tag_id].to_string(),
+                distance: None,
+            });
+        }
+        new_nodes.retain(|n| !present.contains(&n.id));
+
+        if graph.nodes.len() + new_nodes.len() > max_nodes {
+            graph.truncated = true;
+            break;
+        }
+        included.insert(note_id);
+        let note_node = node_id(NodeKind::Note, note_id);
+        if project_names.contains_key(project_id) {
+            graph.edges.push(GraphEdge {
+                source: note_node.clone(),
+                target: node_id(NodeKind::Project, project_id),
+                kind: EdgeKind::Project,
+                weight: 1,
+            });
+        }
+        for tag_id in note_tags {
+            graph.edges.push(GraphEdge {
+                source: note_node.clone(),
+                target: node_id(NodeKind::Tag, tag_id),
+                kind: EdgeKind::Tag,
+                weight: 1,
+            });
+        }
+        for node in new_nodes {
+            present.insert(node.id.clone());
+            graph.nodes.push(node);
+        }
+    }
+
+    let mut link_weights: HashMap<(&str, &str), u32> = HashMap::new();
+    let mut link_order = Vec::new();
+    for (source, target) in &input.links {
+        if included.contains(source.as_str()) && included.contains(target.as_str()) {
+            let weight = link_weights.entry((source, target)).or_insert(0);
+            if *weight == 0 {
+                link_order.push((source.as_str(), target.as_str()));
+            }
+            *weight += 1;
+        }
+    }
+    for (source, target) in link_order {
+        graph.edges.push(GraphEdge {
+            source: node_id(NodeKind::Note, source),
+            target: node_id(NodeKind::Note, target),
+            kind: EdgeKind::Link,
+            weight: link_weights[&(source, target)],
+        });
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() -> GraphInput {
+        let s = |v: &str| v.to_string();
+        GraphInput {
+            notes: vec![
+                (s("a"), s("A"), s("p1")),
+                (s("b"), s("B"), s("p1")),
+                (s("c"), s("C"), s("p1")),
+                (s("d"), s("D"), s("p2")),
+                (s("e"), s("E"), s("p1")),
+            ],
+            projects: vec![(s("p1"), s("One")), (s("p2"), s("Two"))],
+            tags: vec![(s("t1"), s("work")), (s("t2"), s("private"))],
+            note_tags: vec![(s("a"), s("t1")), (s("b"), s("t1")), (s("c"), s("t2"))],
+            // a -> b -> c -> d, a -> b twice
+            links: vec![(s("a"), s("b")), (s("a"), s("b")), (s("b"), s("c")), (s("c"), s("d"))],
+        }
+    }
+
+    fn note_ids(graph: &GraphData) -> Vec<&str> {
+        graph
+            .nodes
+            .iter()
+            .filter(|n| n.kind == NodeKind::Note)
+            .map(|n| n.entity_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn whole_workspace_orders_by_connections() {
+        let graph = build(&input(), &GraphQuery::default());
+        assert_eq!(note_ids(&graph), vec!["b", "a", "c", "d", "e"]);
+        assert_eq!(graph.nodes.len(), 5 + 2 + 2);
+        let ab = graph.edges.iter().find(|e| e.kind == EdgeKind::Link && e.source == "note:a").unwrap();
+        assert_eq!((ab.target.as_str(), ab.weight), ("note:b", 2));
+        assert!(!graph.truncated);
+    }
+
+    #[test]
+    fn focus_limits_depth() {
+        let query = GraphQuery { focus_note_id: Some("a".into()), depth: Some(1), ..Default::default() };
+        let graph = build(&input(), &query);
+        assert_eq!(note_ids(&graph), vec!["a", "b"]);
+        assert_eq!(graph.nodes[0].distance, Some(0));
+        assert_eq!(graph.edges.iter().filter(|e| e.kind == EdgeKind::Link).count(), 1);
+    }
+
+    #[test]
+    fn filters_by_project_and_tags() {
+        let query = GraphQuery { project_id: Some("p1".into()), exclude_tags: vec!["t2".into()], ..Default::default() };
+        assert_eq!(note_ids(&build(&input(), &query)), vec!["a", "b", "e"]);
+
+        let query = GraphQuery { include_tags: vec!["t2".into()], ..Default::default() };
+        let graph = build(&input(), &query);
+        assert_eq!(note_ids(&graph), vec!["c"]);
+        assert!(graph.edges.iter().all(|e| e.kind != EdgeKind::Link));
+    }
+
+    #[test]
+    fn stops_at_max_nodes() {
+        let query = GraphQuery { max_nodes: Some(4), ..Default::default() };
+        let graph = build(&input(), &query);
+        // b + One + work, then a (tag and project already present); c would add private
+        assert_eq!(note_ids(&graph), vec!["b", "a"]);
+        assert!(graph.truncated);
+        assert!(graph.nodes.len() <= 4);
+    }
+}
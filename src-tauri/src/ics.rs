@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::db::TaskListItem;
+use crate::tasks::{Due, TaskPriority};
+
+const PRODUCT_ID: &str = "-//Markdown Editor//Tasks//EN";
+/// Appended to task ids so UIDs are globally unique, as RFC 5545 asks.
+const UID_DOMAIN: &str = "markdown-editor";
+const MAX_LINE_OCTETS: usize = 75;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IcsComponent {
+    /// To-dos, for apps with a task list.
+    #[default]
+    Todo,
+    /// Events on the due date, for calendars that ignore to-dos.
+    Event,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IcsExportOptions {
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub tag_id: Option<String>,
+    /// Leaves completed tasks out.
+    #[serde(default)]
+    pub open_only: bool,
+    #[serde(default)]
+    pub component: IcsComponent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IcsExport {
+    pub path: PathBuf,
+    pub tasks: usize,
+}
+
+/// An iCalendar document with one component per task that has a due date.
+/// Recurring tasks are exported occurrence by occurrence, without RRULE,
+/// since the app creates each occurrence itself.
+pub fn render(items: &[TaskListItem], component: IcsComponent, now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    let mut line = |text: String| fold(&mut out, &text);
+    line("BEGIN:VCALENDAR".into());
+    line("VERSION:2.0".into());
+    line(format!("PRODID:{}", PRODUCT_ID));
+    line("CALSCALE:GREGORIAN".into());
+
+    for item in items {
+        let Some(due) = item.task.due_date.as_deref().and_then(Due::parse) else {
+            continue;
+        };
+        let task = &item.task;
+        let name = match component {
+            IcsComponent::Todo => "VTODO",
+            IcsComponent::Event => "VEVENT",
+        };
+        line(format!("BEGIN:{}", name));
+        line(format!("UID:{}@{}", task.id, UID_DOMAIN));
+        line(format!("DTSTAMP:{}", utc(&now)));
+        line(format!("SUMMARY:{}", escape(&task.content)));
+        let property = match component {
+            IcsComponent::Todo => "DUE",
+            IcsComponent::Event => "DTSTART",
+        };
+        line(match due {
+            Due::Day(day) => format!("{};VALUE=DATE:{}", property, day.format("%Y%m%d")),
+            Due::At(at) => format!("{}:{}", property, utc(&at.with_timezone(&Utc))),
+        });
+
+        let mut description = format!("Note: {}", item.note_title);
+        match component {
+            IcsComponent::Todo => {
+                line(format!("STATUS:{}", if task.completed { "COMPLETED" } else { "NEEDS-ACTION" }));
+                let completed_at = task
+                    .completed_at
+                    .as_deref()
+                    .and_then(|value| DateTime::parse_from_rfc3339(value).ok());
+                if let (true, Some(at)) = (task.completed, completed_at) {
+                    line(format!("COMPLETED:{}", utc(&at.with_timezone(&Utc))));
+                }
+            }
+            IcsComponent::Event if task.completed => description.push_str("\nCompleted"),
+            IcsComponent::Event => {}
+        }
+        if let Some(priority) = priority(task.priority) {
+            line(format!("PRIORITY:{}", priority));
+        }
+        line(format!("DESCRIPTION:{}", escape(&description)));
+        line(format!("END:{}", name));
+    }
+
+    line("END:VCALENDAR".into());
+    out
+}
+
+fn utc(at: &DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// RFC 5545 priorities run from 1 (highest) to 9; 0 means undefined and is
+/// left out.
+fn priority(priority: TaskPriority) -> Option<u8> {
+    match priority {
+        TaskPriority::None => None,
+        TaskPriority::High => Some(1),
+        TaskPriority::Medium => Some(5),
+        TaskPriority::Low => Some(9),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends `line` with CRLF, folded so no line exceeds 75 octets, without
+/// splitting a UTF-8 character.
+fn fold(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space counts towards the continuation line
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Task;
+
+    fn item(id: &str, content: &str, due: &str, completed: bool) -> TaskListItem {
+        TaskListItem {
+            task: Task {
+                id: id.into(),
+                content: content.into(),
+                completed,
+                note_id: "n1".into(),
+                created_at: String::new(),
+                updated_at: String::new(),
+                due_date: Some(due.into()).filter(|d: &String| !d.is_empty()),
+                priority: TaskPriority::High,
+                completed_at: completed.then(|| "2024-11-21T08:00:00+01:00".into()),
+                assignee: None,
+                recurrence: None,
+                series_id: None,
+            },
+            note_title: "Plan, v2".into(),
+            project_id: "p1".into(),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-11-20T09:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn renders_todos() {
+        let items = [
+            item("t1", "Ship; then rest", "2024-11-23", false),
+            item("t2", "Done", "2024-11-22T23:00:00+01:00", true),
+            item("t3", "Someday", "", false),
+        ];
+        let ics = render(&items, IcsComponent::Todo, now());
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VTODO").count(), 2);
+        for expected in [
+            "UID:t1@markdown-editor\r\n",
+            "DTSTAMP:20241120T090000Z\r\n",
+            "SUMMARY:Ship\\; then rest\r\n",
+            "DUE;VALUE=DATE:20241123\r\n",
+            "STATUS:NEEDS-ACTION\r\n",
+            "PRIORITY:1\r\n",
+            "DESCRIPTION:Note: Plan\\, v2\r\n",
+            "DUE:20241122T220000Z\r\n",
+            "STATUS:COMPLETED\r\nCOMPLETED:20241121T070000Z\r\n",
+        ] {
+            assert!(ics.contains(expected), "{}", expected);
+        }
+        assert!(!ics.contains("Someday"));
+    }
+
+    #[test]
+    fn renders_events() {
+        let ics = render(&[item("t1", "Done", "2024-11-23", true)], IcsComponent::Event, now());
+        assert!(ics.contains("BEGIN:VEVENT\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20241123\r\n"));
+        assert!(ics.contains("DESCRIPTION:Note: Plan\\, v2\\nCompleted\r\n"));
+        assert!(!ics.contains("STATUS"));
+    }
+
+    #[test]
+    fn folds_long_lines_on_char_boundaries() {
+        let mut out = String::new();
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        fold(&mut out, &line);
+        let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.len() <= MAX_LINE_OCTETS));
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines.concat().replace(" é", "é"), line);
+    }
+}
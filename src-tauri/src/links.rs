@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+const ID_SCHEME: &str = "note:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    /// `[[Note Title]]`, resolved by title.
+    Wiki,
+    /// `[text](note:<id>)`, resolved by id.
+    Id,
+}
+
+impl LinkKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::Wiki => "wiki",
+            LinkKind::Id => "id",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedLink {
+    pub kind: LinkKind,
+    /// Title for wiki links, note id for id links.
+    pub target: String,
+    /// `alias` in `[[Title|alias]]`, `text` in `[text](note:id)`.
+    pub label: Option<String>,
+    /// 1-based line number.
+    pub line: usize,
+    /// Byte range of the target inside the content, for rewriting it.
+    pub target_span: Range<usize>,
+}
+
+/// Links in `content`, in document order. Links inside fenced code blocks
+/// and inline code spans are ignored. For wiki links, a `#heading` or
+/// `^block` suffix is not part of the target.
+pub fn parse_links(content: &str) -> Vec<ParsedLink> {
+    let mut links = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len();
+
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = match fence {
+                Some(open) if open == marker => None,
+                None => Some(marker),
+                other => other,
+            };
+            continue;
+        }
+        if fence.is_none() {
+            parse_line(line, line_start, index + 1, &mut links);
+        }
+    }
+    links
+}
+
+fn parse_line(line: &str, line_start: usize, line_number: usize, links: &mut Vec<ParsedLink>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => {
+                // Skip the code span up to the matching run of backticks
+                let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+                let closing = "`".repeat(run);
+                i = match line[i + run..].find(&closing) {
+                    Some(end) => i + run + end + run,
+                    None => i + run,
+                };
+            }
+            b'[' if bytes.get(i + 1) == Some(&b'[') => match wiki_link(line, i) {
+                Some((link, end)) => {
+                    links.push(ParsedLink {
+                        line: line_number,
+                        target_span: link.target_span.start + line_start..link.target_span.end + line_start,
+                        ..link
+                    });
+                    i = end;
+                }
+                None => i += 2,
+            },
+            b'[' => match id_link(line, i) {
+                Some((link, end)) => {
+                    links.push(ParsedLink {
+                        line: line_number,
+                        target_span: link.target_span.start + line_start..link.target_span.end + line_start,
+                        ..link
+                    });
+                    i = end;
+                }
+                None => i += 1,
+            },
+            _ => i += 1,
+        }
+    }
+}
+
+/// `[[target#heading|label]]` starting at `start`; returns the link and the
+/// byte index just past it.
+fn wiki_link(line: &str, start: usize) -> Option<(ParsedLink, usize)> {
+    let inner_start = start + 2;
+    let inner_len = line[inner_start..].find("]]")?;
+    let inner = &line[inner_start..inner_start + inner_len];
+    if inner.contains('[') || inner.contains('\n') {
+        return None;
+    }
+
+    let (target_part, label) = match inner.split_once('|') {
+        Some((target, label)) => (target, Some(label.trim().to_string())),
+        None => (inner, None),
+    };
+    let target_end = target_part.find(['#', '^']).unwrap_or(target_part.len());
+    let raw_target = &target_part[..target_end];
+    let target = raw_target.trim();
+    if target.is_empty() {
+        return None;
+    }
+    let leading = raw_target.len() - raw_target.trim_start().len();
+    let span_start = inner_start + leading;
+
+    Some((
+        ParsedLink {
+            kind: LinkKind::Wiki,
+            target: target.to_string(),
+            label: label.filter(|l| !l.is_empty()),
+            line: 0,
+            target_span: span_start..span_start + target.len(),
+        },
+        inner_start + inner_len + 2,
+    ))
+}
+
+/// `[label](note:<id>)` starting at `start`.
+fn id_link(line: &str, start: usize) -> Option<(ParsedLink, usize)> {
+    let label_len = line[start + 1..].find(']')?;
+    let label = &line[start + 1..start + 1 + label_len];
+    let after_label = start + 1 + label_len + 1;
+    let target_start = after_label + 1 + ID_SCHEME.len();
+    if !line[after_label..].starts_with(&format!("({}", ID_SCHEME)) {
+        return None;
+    }
+    let target_len = line[target_start..].find(')')?;
+    let target = &line[target_start..target_start + target_len];
+    if target.is_empty() || target.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((
+        ParsedLink {
+            kind: LinkKind::Id,
+            target: target.to_string(),
+            label: Some(label.trim().to_string()).filter(|l| !l.is_empty()),
+            line: 0,
+            target_span: target_start..target_start + target_len,
+        },
+        target_start + target_len + 1,
+    ))
+}
+
+/// Characters that would end or split a wiki link target.
+pub const RESERVED_TITLE_CHARS: &[char] = &['[', ']', '|', '#', '^'];
+
+/// Points every wiki link to `old_title` at `new_title` instead, keeping
+/// headings and aliases. Titles compare ASCII case-insensitively, as they do
+/// when links are resolved. Returns the new content and the 1-based lines
+/// that changed.
+pub fn rewrite_wiki_target(content: &str, old_title: &str, new_title: &str) -> (String, Vec<usize>) {
+    let mut rewritten = String::with_capacity(content.len());
+    let mut lines = Vec::new();
+    let mut copied = 0;
+
+    for link in parse_links(content) {
+        if link.kind != LinkKind::Wiki || !link.target.eq_ignore_ascii_case(old_title) {
+            continue;
+        }
+        rewritten.push_str(&content[copied..link.target_span.start]);
+        rewritten.push_str(new_title);
+        copied = link.target_span.end;
+        if lines.last() != Some(&link.line) {
+            lines.push(link.line);
+        }
+    }
+    rewritten.push_str(&content[copied..]);
+    (rewritten, lines)
+}
+
+/// The trimmed text of 1-based `line` in `content`.
+pub fn line_text(content: &str, line: usize) -> &str {
+    content
+        .lines()
+        .nth(line.saturating_sub(1))
+        .map(str::trim)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(content: &str) -> Vec<(LinkKind, String, Option<String>, usize)> {
+        parse_links(content)
+            .into_iter()
+            .map(|l| (l.kind, l.target, l.label, l.line))
+            .collect()
+    }
+
+    #[test]
+    fn parses_wiki_and_id_links() {
+        let content = "See [[Project Plan]] and [[Ideas#Later|the backlog]].\n\
+                       Also [the spec](note:abc-123), not [web](https://x.y).";
+        assert_eq!(
+            targets(content),
+            vec![
+                (LinkKind::Wiki, "Project Plan".into(), None, 1),
+                (LinkKind::Wiki, "Ideas".into(), Some("the backlog".into()), 1),
+                (LinkKind::Id, "abc-123".into(), Some("the spec".into()), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_point_at_targets() {
+        let content = "x\n[[ Plan |p]] [a](note:n1)";
+        let links = parse_links(content);
+        assert_eq!(&content[links[0].target_span.clone()], "Plan");
+        assert_eq!(&content[links[1].target_span.clone()], "n1");
+    }
+
+    #[test]
+    fn ignores_code_and_malformed_links() {
+        let content = "`[[inline]]` [[]] [[open\n```\n[[fenced]]\n```\n[[real]] ``[[double]]``";
+        assert_eq!(targets(content), vec![(LinkKind::Wiki, "real".into(), None, 5)]);
+    }
+
+    #[test]
+    fn rewrites_only_matching_wiki_targets() {
+        let content = "[[Old]] and [[old#Intro|see]]\n[[Older]] [x](note:Old)\n`[[Old]]` [[ Old ]]";
+        let (rewritten, lines) = rewrite_wiki_target(content, "Old", "New name");
+        assert_eq!(
+            rewritten,
+            "[[New name]] and [[New name#Intro|see]]\n[[Older]] [x](note:Old)\n`[[Old]]` [[ New name ]]"
+        );
+        assert_eq!(lines, vec![1, 3]);
+    }
+
+    #[test]
+    fn line_text_is_trimmed() {
+        assert_eq!(line_text("a\n  - see [[b]]  \nc", 2), "- see [[b]]");
+        assert_eq!(line_text("a", 9), "");
+    }
+}
@@ -4,12 +4,28 @@
 )]
 
 mod db;
+mod checklist;
 mod commands;
+mod diff;
+mod error;
+mod graph;
+mod ics;
+mod links;
+mod markdown;
+mod migrations;
+mod profiles;
+mod recurrence;
+mod scheduler;
+mod search;
+mod snapshot;
+mod tasks;
+mod theme_import;
+mod themes;
 
-use std::path::PathBuf;
 use tauri::api::path::app_data_dir;
 use db::DbState;
 use commands::*;
+use scheduler::{BackupScheduler, BackupTrigger};
 
 fn main() {
     let context = tauri::generate_context!();
@@ -18,19 +34,20 @@ fn main() {
     let db_state = DbState::new(app_data_dir)
         .expect("Failed to initialize database");
 
-    tauri::Builder::default()
+    let builder = tauri::Builder::default()
         .manage(db_state)
-        .invoke_handler(tauri::generate_handler![
-            create_workspace,
-            get_workspaces,
-            save_workspace_settings,
-            create_custom_theme,
-            create_tab,
-            get_workspace_tabs,
-            create_backup,
-            update_tab_active_state,
-            delete_tab,
-        ])
-        .run(context)
-        .expect("error while running tauri application");
+        .manage(BackupScheduler::default())
+        .setup(|app| {
+            scheduler::start(app.handle());
+            Ok(())
+        });
+    let app = register_commands(builder)
+        .build(context)
+        .expect("error while building tauri application");
+
+    app.run(|app_handle, event| {
+        if let tauri::RunEvent::Exit = event {
+            scheduler::run(app_handle, BackupTrigger::Exit);
+        }
+    });
 }
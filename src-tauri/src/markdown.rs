@@ -0,0 +1,565 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::db::{Note, Project, Tag, Task};
+use crate::error::{AppError, Result};
+use crate::tasks::TaskPriority;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+const MAX_SLUG_CHARS: usize = 80;
+const FENCE: &str = "---";
+
+/// Metadata written above each exported note.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrontMatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, alias = "tag", deserialize_with = "tag_list", skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default, alias = "created_at", skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(default, alias = "updated_at", alias = "modified", skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<FrontMatterTask>,
+}
+
+/// Vaults write tags as `tags: a, b`, `tags: [a, "#b"]` or a block list;
+/// all of them end up as plain names.
+fn tag_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tags {
+        Many(Vec<String>),
+        One(String),
+    }
+
+    let names = match Option::<Tags>::deserialize(deserializer)? {
+        Some(Tags::Many(names)) => names,
+        Some(Tags::One(text)) => text.split([',', ' ']).map(str::to_string).collect(),
+        None => Vec::new(),
+    };
+    Ok(names
+        .iter()
+        .map(|name| name.trim().trim_start_matches('#').to_string())
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrontMatterTask {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub content: String,
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "TaskPriority::is_none")]
+    pub priority: TaskPriority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+}
+
+/// Written next to the notes as `manifest.json`. Together with the front
+/// matter it holds everything needed to re-import the folder as it was.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub project: Project,
+    pub tags: Vec<Tag>,
+    pub notes: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub file: String,
+    pub title: String,
+}
+
+/// How the folders of an imported vault map onto the app's model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FolderMapping {
+    /// One project per folder that holds notes, named after its path.
+    #[default]
+    Projects,
+    /// A single project; notes in subfolders are tagged with the folder path.
+    Tags,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub folders: FolderMapping,
+    /// Runs the whole import and reports on it, then rolls it back.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub projects: Vec<Project>,
+    pub tags: Vec<Tag>,
+    pub notes: Vec<ImportedNote>,
+    pub errors: Vec<ImportError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedNote {
+    pub file: String,
+    pub note_id: String,
+    pub title: String,
+    pub project_id: String,
+    pub tags: Vec<String>,
+    pub tasks: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportError {
+    pub file: String,
+    pub message: String,
+}
+
+/// A Markdown file found by `scan`, parsed but not yet stored.
+pub struct ScannedFile {
+    /// Path relative to the vault root, always `/`-separated.
+    pub file: String,
+    /// Folders between the vault root and the file.
+    pub folders: Vec<String>,
+    pub note: std::result::Result<ParsedNote, String>,
+}
+
+pub struct ParsedNote {
+    pub id: Option<String>,
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub pinned: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    /// Tasks listed in the front matter. Checkboxes in the body become
+    /// tasks when the note is stored.
+    pub tasks: Vec<FrontMatterTask>,
+}
+
+/// A note with everything its front matter refers to.
+pub struct ExportNote {
+    pub note: Note,
+    pub tags: Vec<Tag>,
+    pub tasks: Vec<Task>,
+}
+
+/// Writes `notes` into `dir` as `<slug>.md` files followed by the manifest.
+/// Exporting again into the same folder keeps each note's file name as long
+/// as its title still yields the same slug, and removes files left over from
+/// notes that were renamed or deleted since, so the folder diffs cleanly
+/// under version control.
+pub fn export(dir: &Path, project: &Project, notes: &[ExportNote]) -> Result<ExportManifest> {
+    fs::create_dir_all(dir)?;
+    let previous = read_manifest(dir)?;
+    if let Some(previous) = &previous {
+        if previous.project.id != project.id {
+            return Err(AppError::Conflict {
+                message: format!(
+                    "{} already holds an export of project '{}'",
+                    dir.display(),
+                    previous.project.name
+                ),
+            });
+        }
+    }
+    let previous_files: HashMap<&str, &str> = previous
+        .iter()
+        .flat_map(|m| &m.notes)
+        .map(|entry| (entry.id.as_str(), entry.file.as_str()))
+        .collect();
+
+    // Reserve the names that stay the same first so a new collision can
+    // never take over an existing note's file.
+    let mut used = HashSet::new();
+    let mut files: Vec<Option<String>> = notes
+        .iter()
+        .map(|n| {
+            let file = previous_files.get(n.note.id.as_str())?;
+            keeps_file_name(file, &slugify(&n.note.title)).then(|| {
+                used.insert(file.to_lowercase());
+                file.to_string()
+            })
+        })
+        .collect();
+    for (file, n) in files.iter_mut().zip(notes) {
+        if file.is_none() {
+            *file = Some(unique_file_name(&slugify(&n.note.title), &mut used));
+        }
+    }
+
+    let mut tags = BTreeMap::new();
+    let mut entries = Vec::with_capacity(notes.len());
+    for (file, n) in files.into_iter().flatten().zip(notes) {
+        let front = FrontMatter {
+            id: Some(n.note.id.clone()),
+            title: Some(n.note.title.clone()),
+            tags: n.tags.iter().map(|t| t.name.clone()).collect(),
+            pinned: n.note.is_pinned,
+            created: Some(n.note.created_at.clone()),
+            updated: Some(n.note.updated_at.clone()),
+            tasks: n
+                .tasks
+                .iter()
+                .map(|t| FrontMatterTask {
+                    id: Some(t.id.clone()),
+                    content: t.content.clone(),
+                    completed: t.completed,
+                    created: Some(t.created_at.clone()),
+                    updated: Some(t.updated_at.clone()),
+                    due: t.due_date.clone(),
+                    priority: t.priority,
+                    done: t.completed_at.clone(),
+                    assignee: t.assignee.clone(),
+                    recurrence: t.recurrence.clone(),
+                })
+                .collect(),
+        };
+        fs::write(dir.join(&file), render(&front, &n.note.content))?;
+
+        for tag in &n.tags {
+            tags.entry(tag.id.clone()).or_insert_with(|| tag.clone());
+        }
+        entries.push(ManifestEntry {
+            id: n.note.id.clone(),
+            file,
+            title: n.note.title.clone(),
+        });
+    }
+
+    let written: HashSet<&str> = entries.iter().map(|e| e.file.as_str()).collect();
+    // The manifest is user-editable; never follow it outside `dir`.
+    let stale = previous_files
+        .values()
+        .filter(|f| !written.contains(*f) && !f.contains(['/', '\\']) && f.ends_with(".md"));
+    for stale in stale {
+        remove_if_exists(&dir.join(stale))?;
+    }
+
+    let mut tags: Vec<Tag> = tags.into_values().collect();
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+    let manifest = ExportManifest {
+        format_version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        project: project.clone(),
+        tags,
+        notes: entries,
+    };
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| AppError::Io {
+        message: e.to_string(),
+    })?;
+    fs::write(dir.join(MANIFEST_FILE), json)?;
+    Ok(manifest)
+}
+
+pub fn read_manifest(dir: &Path) -> Result<Option<ExportManifest>> {
+    let json = match fs::read_to_string(dir.join(MANIFEST_FILE)) {
+        Ok(json) => json,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| AppError::validation("manifest", e.to_string()))
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Every `.md` file under `dir`, in path order. Hidden entries such as
+/// `.obsidian`, `.git` or `.trash` are skipped, and so are symlinks so a
+/// vault linking to itself cannot loop.
+pub fn scan(dir: &Path) -> Result<Vec<ScannedFile>> {
+    let mut files = Vec::new();
+    scan_folder(dir, &mut Vec::new(), &mut files)?;
+    Ok(files)
+}
+
+fn scan_folder(dir: &Path, folders: &mut Vec<String>, files: &mut Vec<ScannedFile>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_type = entry.file_type()?;
+        if name.starts_with('.') || file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            folders.push(name);
+            scan_folder(&entry.path(), folders, files)?;
+            folders.pop();
+        } else if let Some(stem) = name.strip_suffix(".md") {
+            let mut file = folders.join("/");
+            if !file.is_empty() {
+                file.push('/');
+            }
+            file.push_str(&name);
+            files.push(ScannedFile {
+                file,
+                folders: folders.clone(),
+                note: read_note(&entry.path(), stem),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn read_note(path: &Path, stem: &str) -> std::result::Result<ParsedNote, String> {
+    let document = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(|t| DateTime::<Utc>::from(t).to_rfc3339());
+    let (front, body) = parse(&document).map_err(|e| format!("invalid front matter: {}", e))?;
+    let front = front.unwrap_or_default();
+
+    Ok(ParsedNote {
+        id: front.id,
+        title: front
+            .title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| stem.to_string()),
+        content: body.to_string(),
+        tags: front.tags,
+        pinned: front.pinned,
+        created_at: front.created.as_deref().and_then(normalize_timestamp).or(modified.clone()),
+        updated_at: front.updated.as_deref().and_then(normalize_timestamp).or(modified),
+        tasks: front.tasks,
+    })
+}
+
+/// RFC 3339 form of the timestamp shapes found in front matter: full
+/// RFC 3339, date and time without a zone (taken as UTC), or a bare date.
+pub fn normalize_timestamp(value: &str) -> Option<String> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc).to_rfc3339());
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .map(|t| t.and_utc().to_rfc3339())
+}
+
+/// Whether `file` is `<slug>.md` or one of its collision suffixes.
+fn keeps_file_name(file: &str, slug: &str) -> bool {
+    let Some(stem) = file.strip_suffix(".md") else {
+        return false;
+    };
+    match stem.strip_prefix(slug) {
+        Some("") => true,
+        Some(suffix) => suffix
+            .strip_prefix('-')
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())),
+        None => false,
+    }
+}
+
+/// A file name stem safe on every platform: lowercase letters and digits
+/// joined by single dashes, never empty and never a reserved device name.
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= MAX_SLUG_CHARS {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-').to_string();
+
+    if slug.is_empty() {
+        return "untitled".to_string();
+    }
+    if is_reserved(&slug) {
+        return format!("{}-note", slug);
+    }
+    slug
+}
+
+/// Windows refuses these as file names whatever the extension.
+fn is_reserved(slug: &str) -> bool {
+    matches!(slug, "con" | "prn" | "aux" | "nul")
+        || ((slug.starts_with("com") || slug.starts_with("lpt"))
+            && slug.len() == 4
+            && slug[3..].chars().all(|c| ('1'..='9').contains(&c)))
+}
+
+/// `<slug>.md`, suffixed `-2`, `-3`, ... until it differs from every name in
+/// `used`. Names are compared case-insensitively because the export may land
+/// on a case-insensitive file system.
+pub fn unique_file_name(slug: &str, used: &mut HashSet<String>) -> String {
+    let mut candidate = format!("{}.md", slug);
+    let mut n = 2;
+    while used.contains(&candidate.to_lowercase()) {
+        candidate = format!("{}-{}.md", slug, n);
+        n += 1;
+    }
+    used.insert(candidate.to_lowercase());
+    candidate
+}
+
+pub fn render(front: &FrontMatter, body: &str) -> String {
+    let yaml = serde_yaml::to_string(front).unwrap_or_default();
+    format!("{}\n{}{}\n\n{}", FENCE, yaml, FENCE, body)
+}
+
+/// Splits a document into its front matter block and body. Documents
+/// without a leading `---` fence have no front matter; a block that is not
+/// valid YAML for `FrontMatter` is reported as an error message.
+pub fn parse(document: &str) -> Result<(Option<FrontMatter>, &str), String> {
+    let Some(rest) = strip_fence_line(document) else {
+        return Ok((None, document));
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']) == FENCE {
+            let yaml = &rest[..offset];
+            let mut body = &rest[offset + line.len()..];
+            body = body
+                .strip_prefix("\r\n")
+                .or_else(|| body.strip_prefix('\n'))
+                .unwrap_or(body);
+
+            let front = if yaml.trim().is_empty() {
+                FrontMatter::default()
+            } else {
+                serde_yaml::from_str(yaml).map_err(|e| e.to_string())?
+            };
+            return Ok((Some(front), body));
+        }
+        offset += line.len();
+    }
+    Ok((None, document))
+}
+
+fn strip_fence_line(document: &str) -> Option<&str> {
+    let rest = document.strip_prefix(FENCE)?;
+    rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_are_file_name_safe() {
+        assert_eq!(slugify("Meeting notes: Q3/Q4 *draft*"), "meeting-notes-q3-q4-draft");
+        assert_eq!(slugify("  Café déjà vu  "), "café-déjà-vu");
+        assert_eq!(slugify("../../etc/passwd"), "etc-passwd");
+        assert_eq!(slugify("???"), "untitled");
+        assert_eq!(slugify("CON"), "con-note");
+        assert_eq!(slugify("com1"), "com1-note");
+        assert_eq!(slugify(&"a".repeat(200)).len(), MAX_SLUG_CHARS);
+    }
+
+    #[test]
+    fn colliding_titles_get_suffixes() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_file_name("plan", &mut used), "plan.md");
+        assert_eq!(unique_file_name("plan", &mut used), "plan-2.md");
+        assert_eq!(unique_file_name("plan", &mut used), "plan-3.md");
+        used.insert("ideas.md".into());
+        assert_eq!(unique_file_name("Ideas", &mut used), "Ideas-2.md");
+    }
+
+    #[test]
+    fn reexport_keeps_names_while_the_slug_holds() {
+        assert!(keeps_file_name("plan.md", "plan"));
+        assert!(keeps_file_name("plan-3.md", "plan"));
+        assert!(!keeps_file_name("plan-b.md", "plan"));
+        assert!(!keeps_file_name("planning.md", "plan"));
+    }
+
+    #[test]
+    fn front_matter_round_trips() {
+        let front = FrontMatter {
+            id: Some("n1".into()),
+            title: Some("Plan: v2 \"final\"".into()),
+            tags: vec!["work".into(), "q3".into()],
+            pinned: true,
+            created: Some("2024-11-23T09:00:00+00:00".into()),
+            updated: Some("2024-11-24T10:30:00+00:00".into()),
+            tasks: vec![FrontMatterTask {
+                id: Some("t1".into()),
+                content: "Ship it".into(),
+                completed: false,
+                created: None,
+                updated: None,
+                due: Some("2024-11-30".into()),
+                priority: TaskPriority::High,
+                done: None,
+                assignee: None,
+                recurrence: Some("FREQ=WEEKLY;BYDAY=MO".into()),
+            }],
+        };
+        let body = "# Plan\n\n---\n\nSecond section\n";
+        let document = render(&front, body);
+        assert!(document.starts_with("---\nid: n1\n"));
+
+        let (parsed, parsed_body) = parse(&document).unwrap();
+        assert_eq!(parsed, Some(front));
+        assert_eq!(parsed_body, body);
+    }
+
+    #[test]
+    fn vault_front_matter_is_accepted() {
+        let (front, body) = parse("---\ntags: work, q3\ncreated: 2024-11-23\nmodified: 2024-11-24 10:30\naliases: [x]\n---\nText").unwrap();
+        let front = front.unwrap();
+        assert_eq!(front.tags, vec!["work", "q3"]);
+        assert_eq!(body, "Text");
+        assert_eq!(normalize_timestamp(front.created.as_deref().unwrap()).unwrap(), "2024-11-23T00:00:00+00:00");
+        assert_eq!(normalize_timestamp(front.updated.as_deref().unwrap()).unwrap(), "2024-11-24T10:30:00+00:00");
+
+        let (front, _) = parse("---\ntags:\n  - \"#a\"\n  - b\n---\n").unwrap();
+        assert_eq!(front.unwrap().tags, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn documents_without_front_matter_are_all_body() {
+        assert_eq!(parse("# Hello").unwrap(), (None, "# Hello"));
+        assert_eq!(parse("---\nno closing fence").unwrap(), (None, "---\nno closing fence"));
+        assert!(parse("---\ntags: [unclosed\n---\nbody").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use rusqlite::{Connection, OptionalExtension};
+use std::fmt;
+
+/// A single schema step. Versions start at 1 and must be contiguous; the
+/// applied version is tracked in `PRAGMA user_version`.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "init",
+        sql: include_str!("../migrations/001_init.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "notes_fts",
+        sql: include_str!("../migrations/002_notes_fts.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "note_revisions",
+        sql: include_str!("../migrations/003_note_revisions.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "trash",
+        sql: include_str!("../migrations/004_trash.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "backup_kind",
+        sql: include_str!("../migrations/005_backup_kind.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "note_links",
+        sql: include_str!("../migrations/006_note_links.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "task_checkboxes",
+        sql: include_str!("../migrations/007_task_checkboxes.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "task_details",
+        sql: include_str!("../migrations/008_task_details.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "task_recurrence",
+        sql: include_str!("../migrations/009_task_recurrence.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "tag_details",
+        sql: include_str!("../migrations/010_tag_details.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "project_details",
+        sql: include_str!("../migrations/011_project_details.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "workspace_profiles",
+        sql: include_str!("../migrations/012_workspace_profiles.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "tab_sessions",
+        sql: include_str!("../migrations/013_tab_sessions.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "workspace_databases",
+        sql: include_str!("../migrations/014_workspace_databases.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "foreign_key_cleanup",
+        sql: include_str!("../migrations/015_foreign_key_cleanup.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "note_timestamps",
+        sql: include_str!("../migrations/016_note_timestamps.sql"),
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    DatabaseTooNew { found: u32, supported: u32 },
+    /// A row still references a missing parent once the steps have run.
+    ForeignKeyViolation { table: String, rowid: i64, parent: String },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "{}", e),
+            MigrationError::DatabaseTooNew { found, supported } => write!(
+                f,
+                "database schema version {} is newer than the latest supported version {}",
+                found, supported
+            ),
+            MigrationError::ForeignKeyViolation { table, rowid, parent } => write!(
+                f,
+                "row {} of {} references a missing {} row",
+                rowid, table, parent
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Brings the schema up to `latest_version()`. All pending steps run in a
+/// single transaction, so a failing step leaves the database untouched.
+/// Returns the version the database is at afterwards.
+pub fn run(conn: &mut Connection) -> Result<u32, MigrationError> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(MigrationError::DatabaseTooNew {
+            found: current,
+            supported: latest,
+        });
+    }
+    if current == latest {
+        return Ok(current);
+    }
+
+    let tx = conn.transaction()?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        tx.execute_batch(migration.sql)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+    }
+    // Connections enforce foreign keys once migrated, so leave none broken
+    if let Some((table, rowid, parent)) = tx
+        .query_row("PRAGMA foreign_key_check", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .optional()?
+    {
+        return Err(MigrationError::ForeignKeyViolation { table, rowid, parent });
+    }
+    tx.commit()?;
+
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_FIXTURE: &str = include_str!("../migrations/fixtures/v1.sql");
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+            [name],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn versions_are_contiguous() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as u32 + 1, "{}", migration.name);
+        }
+    }
+
+    #[test]
+    fn fresh_database_reaches_head() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run(&mut conn).unwrap(), latest_version());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(table_exists(&conn, "notes"));
+    }
+
+    #[test]
+    fn running_twice_is_a_no_op() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        assert_eq!(run(&mut conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn v1_fixture_upgrades_to_head_keeping_data() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(V1_FIXTURE).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 1);
+
+        assert_eq!(run(&mut conn).unwrap(), latest_version());
+
+        let title: String = conn
+            .query_row("SELECT title FROM notes WHERE id = 'note-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(title, "Welcome");
+        let tags: i64 = conn
+            .query_row("SELECT COUNT(*) FROM note_tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tags, 1);
+    }
+
+    #[test]
+    fn orphaned_rows_are_repaired() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(V1_FIXTURE).unwrap();
+        // As written by a build that did not enforce foreign keys
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO notes (id, title, content, project_id, created_at, updated_at) 
+             VALUES ('stray', 'Stray', '', 'gone', '', '');
+             INSERT INTO tasks (id, content, note_id, created_at, updated_at) 
+             VALUES ('t-stray', 'x', 'gone', '', '');
+             INSERT INTO note_tags (note_id, tag_id) VALUES ('note-1', 'gone');
+             INSERT INTO tabs (id, workspace_id, title, type, position) VALUES ('tab-stray', 'gone', 'x', 'md', 0);",
+        )
+        .unwrap();
+
+        run(&mut conn).unwrap();
+
+        let project: String = conn
+            .query_row("SELECT project_id FROM notes WHERE id = 'stray'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(project, "recovered-notes");
+        let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM tasks WHERE id = 't-stray'"), 0);
+        assert_eq!(count("SELECT COUNT(*) FROM note_tags"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM tabs WHERE id = 'tab-stray'"), 0);
+        assert_eq!(count("SELECT COUNT(*) FROM pragma_foreign_key_check"), 0);
+    }
+
+    #[test]
+    fn unversioned_legacy_database_is_adopted() {
+        // Databases created before versioning ran init.sql directly and were
+        // left at user_version 0.
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        assert_eq!(run(&mut conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        match run(&mut conn) {
+            Err(MigrationError::DatabaseTooNew { found, supported }) => {
+                assert_eq!(found, latest_version() + 1);
+                assert_eq!(supported, latest_version());
+            }
+            other => panic!("expected DatabaseTooNew, got {:?}", other),
+        }
+    }
+}
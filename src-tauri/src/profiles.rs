@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{AppError, Result};
+
+/// Type and theme given to workspaces imported from localStorage, which had
+/// neither; the same defaults the workspace manager offers.
+pub const LEGACY_WORKSPACE_TYPE: &str = "work";
+pub const LEGACY_WORKSPACE_THEME: &str = "light";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorView {
+    Split,
+    #[default]
+    Single,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitOrientation {
+    Horizontal,
+    #[default]
+    Vertical,
+}
+
+/// Editor settings of a workspace profile. Missing fields take the values
+/// the UI gives a new workspace's default profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    pub default_view: EditorView,
+    pub split_orientation: SplitOrientation,
+    /// Share of the editor pane, between 0 and 1.
+    pub split_ratio: f64,
+    pub show_line_numbers: bool,
+    pub auto_save: bool,
+    pub spell_check: bool,
+    pub font_size: u32,
+    pub font_family: String,
+    pub tab_size: u32,
+    pub use_soft_tabs: bool,
+    pub line_height: f64,
+    pub show_invisibles: bool,
+    pub word_wrap: bool,
+}
+
+impl Default for ProfileSettings {
+    fn default() -> Self {
+        ProfileSettings {
+            default_view: EditorView::Single,
+            split_orientation: SplitOrientation::Vertical,
+            split_ratio: 0.5,
+            show_line_numbers: true,
+            auto_save: true,
+            spell_check: true,
+            font_size: 14,
+            font_family: r#"Monaco, Menlo, "Ubuntu Mono", Consolas, source-code-pro, monospace"#.into(),
+            tab_size: 2,
+            use_soft_tabs: true,
+            line_height: 1.5,
+            show_invisibles: false,
+            word_wrap: true,
+        }
+    }
+}
+
+impl ProfileSettings {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.split_ratio > 0.0 && self.split_ratio < 1.0) {
+            return Err(AppError::validation("split_ratio", "must be between 0 and 1"));
+        }
+        if !(6..=72).contains(&self.font_size) {
+            return Err(AppError::validation("font_size", "must be between 6 and 72"));
+        }
+        if self.font_family.trim().is_empty() {
+            return Err(AppError::validation("font_family", "must not be empty"));
+        }
+        if !(1..=16).contains(&self.tab_size) {
+            return Err(AppError::validation("tab_size", "must be between 1 and 16"));
+        }
+        if !(0.5..=4.0).contains(&self.line_height) {
+            return Err(AppError::validation("line_height", "must be between 0.5 and 4"));
+        }
+        Ok(())
+    }
+}
+
+/// A workspace as the UI kept it in localStorage under
+/// `markdownEditor.workspaces`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyWorkspace {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub current_profile_id: Option<String>,
+    #[serde(default)]
+    pub profiles: Vec<LegacyProfile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LegacyProfile {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub theme: Map<String, Value>,
+    #[serde(default, deserialize_with = "legacy_settings")]
+    pub settings: ProfileSettings,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegacyImportReport {
+    /// The import already ran once; nothing was read this time.
+    pub already_migrated: bool,
+    pub workspaces: usize,
+    pub profiles: usize,
+    /// Ids of workspaces left out because one with that id already exists.
+    pub skipped: Vec<String>,
+}
+
+/// Parses the localStorage JSON. An empty string or `null` is no
+/// workspaces.
+pub fn parse_legacy(json: &str) -> Result<Vec<LegacyWorkspace>> {
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let workspaces: Option<Vec<LegacyWorkspace>> = serde_json::from_str(json)
+        .map_err(|e| AppError::validation("json", format!("not a workspace list: {}", e)))?;
+    Ok(workspaces.unwrap_or_default())
+}
+
+/// Takes each camelCase setting the UI stored that is valid on its own, and
+/// the default for the others, so one bad value doesn't lose the profile.
+fn legacy_settings<'de, D>(deserializer: D) -> std::result::Result<ProfileSettings, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let stored = Option::<Map<String, Value>>::deserialize(deserializer)?.unwrap_or_default();
+    let mut settings = ProfileSettings::default();
+    for (key, value) in stored {
+        let Ok(Value::Object(mut candidate)) = serde_json::to_value(&settings) else {
+            break;
+        };
+        candidate.insert(snake_case(&key), value);
+        if let Ok(parsed) = serde_json::from_value::<ProfileSettings>(Value::Object(candidate)) {
+            if parsed.validate().is_ok() {
+                settings = parsed;
+            }
+        }
+    }
+    Ok(settings)
+}
+
+fn snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(ProfileSettings::default().validate().is_ok());
+        let parsed: ProfileSettings = serde_json::from_str(r#"{ "tab_size": 4 }"#).unwrap();
+        assert_eq!(parsed.tab_size, 4);
+        assert_eq!(parsed.font_size, 14);
+    }
+
+    #[test]
+    fn rejects_out_of_range_settings() {
+        let settings = ProfileSettings { split_ratio: 1.0, ..Default::default() };
+        assert!(settings.validate().is_err());
+        let settings = ProfileSettings { tab_size: 0, ..Default::default() };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn parses_local_storage_workspaces() {
+        let json = r##"[{
+            "id": "k3j9x2a1b", "name": "Notes", "path": "/home/me/notes",
+            "currentProfileId": "p2",
+            "profiles": [
+                { "id": "p1", "name": "Default", "theme": { "isDarkMode": true } },
+                { "id": "p2", "name": "Writing", "theme": {}, "settings": {
+                    "defaultView": "split", "splitRatio": 0.6, "tabSize": 4,
+                    "fontSize": 400, "wordWrap": "yes", "somethingNew": 1
+                } }
+            ]
+        }]"##;
+        let workspaces = parse_legacy(json).unwrap();
+        assert_eq!(workspaces[0].current_profile_id.as_deref(), Some("p2"));
+        assert_eq!(workspaces[0].profiles[0].theme["isDarkMode"], Value::Bool(true));
+        assert_eq!(workspaces[0].profiles[0].settings, ProfileSettings::default());
+
+        let writing = &workspaces[0].profiles[1].settings;
+        assert_eq!(writing.default_view, EditorView::Split);
+        assert_eq!(writing.split_ratio, 0.6);
+        assert_eq!(writing.tab_size, 4);
+        // Out of range and mistyped values fall back to the defaults
+        assert_eq!(writing.font_size, 14);
+        assert!(writing.word_wrap);
+    }
+
+    #[test]
+    fn empty_storage_is_no_workspaces() {
+        assert!(parse_legacy("").unwrap().is_empty());
+        assert!(parse_legacy("null").unwrap().is_empty());
+        assert!(parse_legacy("{").is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Weekday};
+use std::fmt;
+
+use crate::tasks::Due;
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+/// Largest INTERVAL accepted, which keeps every next due date well within
+/// the range of dates chrono handles.
+const MAX_INTERVAL: u32 = 1000;
+
+/// How a task repeats, written as a subset of the iCalendar RRULE syntax:
+/// `FREQ=DAILY;INTERVAL=2`, `FREQ=WEEKLY;BYDAY=MO,TH`,
+/// `FREQ=MONTHLY;BYMONTHDAY=15`. `FREQ=DAILY;INTERVAL=3;X-FROM=COMPLETION`
+/// counts the days from when the task was completed instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+    Daily { interval: u32 },
+    /// Without weekdays, on the weekday of the due date.
+    Weekly { interval: u32, weekdays: Vec<Weekday> },
+    /// Without a day, on the day of month of the due date. Days past the end
+    /// of a month fall on its last day.
+    Monthly { interval: u32, day: Option<u32> },
+    AfterCompletion { days: u32 },
+}
+
+impl Recurrence {
+    pub fn parse(rule: &str) -> Result<Recurrence, String> {
+        let rule = rule.trim();
+        let rule = match rule.get(..6) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("RRULE:") => &rule[6..],
+            _ => rule,
+        };
+
+        let mut freq = None;
+        let mut interval = None;
+        let mut weekdays = None;
+        let mut month_day = None;
+        let mut from_completion = false;
+        for part in rule.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("`{}` is not KEY=VALUE", part))?;
+            let value = value.trim().to_ascii_uppercase();
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => freq = Some(value),
+                "INTERVAL" => {
+                    interval = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|n| (1..=MAX_INTERVAL).contains(n))
+                            .ok_or_else(|| format!("INTERVAL must be a number between 1 and {}", MAX_INTERVAL))?,
+                    )
+                }
+                "BYDAY" => {
+                    let mut days = value
+                        .split(',')
+                        .map(|day| WEEKDAYS.iter().find(|(code, _)| *code == day.trim()).map(|(_, d)| *d))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or("BYDAY takes days such as MO,WE,FR")?;
+                    days.sort_by_key(Weekday::num_days_from_monday);
+                    days.dedup();
+                    weekdays = Some(days);
+                }
+                "BYMONTHDAY" => {
+                    month_day = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|d| (1..=31).contains(d))
+                            .ok_or("BYMONTHDAY must be a day between 1 and 31")?,
+                    )
+                }
+                "X-FROM" if value == "COMPLETION" => from_completion = true,
+                _ => return Err(format!("`{}` is not supported", part)),
+            }
+        }
+
+        let interval_or_one = interval.unwrap_or(1);
+        let recurrence = match freq.as_deref() {
+            None => return Err("FREQ is required".into()),
+            Some("DAILY") if from_completion => Recurrence::AfterCompletion { days: interval_or_one },
+            Some("DAILY") => Recurrence::Daily { interval: interval_or_one },
+            Some("WEEKLY") => Recurrence::Weekly {
+                interval: interval_or_one,
+                weekdays: weekdays.take().unwrap_or_default(),
+            },
+            Some("MONTHLY") => Recurrence::Monthly {
+                interval: interval_or_one,
+                day: month_day.take(),
+            },
+            Some(_) => return Err("FREQ must be DAILY, WEEKLY or MONTHLY".into()),
+        };
+        if from_completion && !matches!(recurrence, Recurrence::AfterCompletion { .. }) {
+            return Err("X-FROM=COMPLETION only goes with FREQ=DAILY".into());
+        }
+        if weekdays.is_some() || month_day.is_some() {
+            return Err("BYDAY needs FREQ=WEEKLY and BYMONTHDAY needs FREQ=MONTHLY".into());
+        }
+        Ok(recurrence)
+    }
+
+    /// Due date of the occurrence following one due at `due` and completed
+    /// at `completed_at`. Scheduled rules give the first occurrence after
+    /// both the due day and the completion day, so a late completion does
+    /// not leave the next occurrence overdue already. A due time of day is
+    /// kept. Fails when the next occurrence is past the last supported date.
+    pub fn next_due<Tz: TimeZone>(&self, due: Option<Due>, completed_at: &DateTime<Tz>) -> Result<Due, String> {
+        let done_day = completed_at.naive_local().date();
+        let anchor = match due {
+            Some(Due::Day(day)) => day,
+            Some(Due::At(at)) => at.date_naive(),
+            None => done_day,
+        };
+        let next = match self {
+            Recurrence::AfterCompletion { days } => add_days(done_day, i64::from(*days))?,
+            _ => self.next_day(anchor, anchor.max(done_day))?,
+        };
+        match due {
+            Some(Due::At(at)) => at.checked_add_signed(next - anchor).map(Due::At).ok_or_else(out_of_range),
+            _ => Ok(Due::Day(next)),
+        }
+    }
+
+    /// First day after `after` on which the rule, started at `anchor`, falls.
+    fn next_day(&self, anchor: NaiveDate, after: NaiveDate) -> Result<NaiveDate, String> {
+        match self {
+            Recurrence::Daily { interval } => {
+                let step = i64::from(*interval);
+                let periods = (after - anchor).num_days() / step + 1;
+                add_days(anchor, periods * step)
+            }
+            Recurrence::Weekly { interval, weekdays } => {
+                let monday = |day: NaiveDate| day - Duration::days(i64::from(day.weekday().num_days_from_monday()));
+                let first_week = monday(anchor);
+                let mut day = after;
+                loop {
+                    day = day.succ_opt().ok_or_else(out_of_range)?;
+                    let week = (monday(day) - first_week).num_days() / 7;
+                    let on_day = if weekdays.is_empty() {
+                        day.weekday() == anchor.weekday()
+                    } else {
+                        weekdays.contains(&day.weekday())
+                    };
+                    if week % i64::from(*interval) == 0 && on_day {
+                        return Ok(day);
+                    }
+                }
+            }
+            Recurrence::Monthly { interval, day } => {
+                let target = day.unwrap_or(anchor.day());
+                let month_index = |date: NaiveDate| i64::from(date.year()) * 12 + i64::from(date.month0());
+                let step = i64::from(*interval);
+                let mut months = (month_index(after) - month_index(anchor)) / step * step;
+                loop {
+                    let candidate = day_of_month(month_index(anchor) + months, target).ok_or_else(out_of_range)?;
+                    if candidate > after {
+                        return Ok(candidate);
+                    }
+                    months += step;
+                }
+            }
+            Recurrence::AfterCompletion { days } => add_days(after, i64::from(*days)),
+        }
+    }
+}
+
+fn add_days(day: NaiveDate, days: i64) -> Result<NaiveDate, String> {
+    Duration::try_days(days)
+        .and_then(|days| day.checked_add_signed(days))
+        .ok_or_else(out_of_range)
+}
+
+fn out_of_range() -> String {
+    "the next occurrence is past the last supported date".into()
+}
+
+/// `day` of the month at `month_index` (year * 12 + zero-based month),
+/// clamped to the month's last day. `None` past the supported years.
+fn day_of_month(month_index: i64, day: u32) -> Option<NaiveDate> {
+    let year = i32::try_from(month_index / 12).ok()?;
+    let month = (month_index % 12) as u32 + 1;
+    let first_of_next = match month {
+        12 => NaiveDate::from_ymd_opt(year.checked_add(1)?, 1, 1),
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1),
+    }?;
+    let last_day = first_of_next.pred_opt()?.day();
+    NaiveDate::from_ymd_opt(year, month, day.min(last_day))
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let interval = |f: &mut fmt::Formatter<'_>, n: u32| match n {
+            1 => Ok(()),
+            n => write!(f, ";INTERVAL={}", n),
+        };
+        match self {
+            Recurrence::Daily { interval: n } => {
+                write!(f, "FREQ=DAILY")?;
+                interval(f, *n)
+            }
+            Recurrence::Weekly { interval: n, weekdays } => {
+                write!(f, "FREQ=WEEKLY")?;
+                interval(f, *n)?;
+                if !weekdays.is_empty() {
+                    let codes: Vec<&str> = weekdays
+                        .iter()
+                        .filter_map(|day| WEEKDAYS.iter().find(|(_, d)| d == day).map(|(code, _)| *code))
+                        .collect();
+                    write!(f, ";BYDAY={}", codes.join(","))?;
+                }
+                Ok(())
+            }
+            Recurrence::Monthly { interval: n, day } => {
+                write!(f, "FREQ=MONTHLY")?;
+                interval(f, *n)?;
+                match day {
+                    Some(day) => write!(f, ";BYMONTHDAY={}", day),
+                    None => Ok(()),
+                }
+            }
+            Recurrence::AfterCompletion { days } => write!(f, "FREQ=DAILY;INTERVAL={};X-FROM=COMPLETION", days),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn day(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(rule: &str, due: &str, completed_at: &str) -> Due {
+        Recurrence::parse(rule)
+            .unwrap()
+            .next_due(Due::parse(due), &at(completed_at))
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_and_prints_canonical_rules() {
+        for (rule, canonical) in [
+            ("freq=daily", "FREQ=DAILY"),
+            ("RRULE:FREQ=WEEKLY;BYDAY=fr,mo,MO", "FREQ=WEEKLY;BYDAY=MO,FR"),
+            ("FREQ=MONTHLY;INTERVAL=2;BYMONTHDAY=31", "FREQ=MONTHLY;INTERVAL=2;BYMONTHDAY=31"),
+            ("FREQ=DAILY;INTERVAL=3;X-FROM=COMPLETION", "FREQ=DAILY;INTERVAL=3;X-FROM=COMPLETION"),
+        ] {
+            assert_eq!(Recurrence::parse(rule).unwrap().to_string(), canonical);
+        }
+        for rule in [
+            "",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;BYDAY=MO",
+            "FREQ=WEEKLY;X-FROM=COMPLETION",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=DAILY;COUNT=3",
+        ] {
+            assert!(Recurrence::parse(rule).is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn daily_and_after_completion() {
+        assert_eq!(next("FREQ=DAILY", "2024-11-20", "2024-11-20T09:00:00Z"), Due::Day(day("2024-11-21")));
+        // Completed late: the next occurrence is after the completion day
+        assert_eq!(
+            next("FREQ=DAILY;INTERVAL=2", "2024-11-20", "2024-11-23T09:00:00Z"),
+            Due::Day(day("2024-11-24"))
+        );
+        assert_eq!(
+            next("FREQ=DAILY;INTERVAL=3;X-FROM=COMPLETION", "2024-11-20", "2024-11-23T09:00:00Z"),
+            Due::Day(day("2024-11-26"))
+        );
+    }
+
+    #[test]
+    fn weekly_on_given_days() {
+        // 2024-11-20 is a Wednesday
+        let rule = "FREQ=WEEKLY;BYDAY=MO,WE";
+        assert_eq!(next(rule, "2024-11-20", "2024-11-20T09:00:00Z"), Due::Day(day("2024-11-25")));
+        assert_eq!(next(rule, "2024-11-25", "2024-11-25T09:00:00Z"), Due::Day(day("2024-11-27")));
+        assert_eq!(
+            next("FREQ=WEEKLY;INTERVAL=2", "2024-11-20", "2024-11-20T09:00:00Z"),
+            Due::Day(day("2024-12-04"))
+        );
+    }
+
+    #[test]
+    fn monthly_clamps_to_the_last_day() {
+        let rule = "FREQ=MONTHLY;BYMONTHDAY=31";
+        assert_eq!(next(rule, "2024-01-31", "2024-01-31T09:00:00Z"), Due::Day(day("2024-02-29")));
+        assert_eq!(next(rule, "2024-02-29", "2024-02-29T09:00:00Z"), Due::Day(day("2024-03-31")));
+        assert_eq!(next("FREQ=MONTHLY;INTERVAL=3", "2024-11-15", "2024-11-16T09:00:00Z"), Due::Day(day("2025-02-15")));
+    }
+
+    #[test]
+    fn keeps_the_time_of_day() {
+        assert_eq!(
+            next("FREQ=DAILY", "2024-11-20T08:30:00+01:00", "2024-11-20T09:00:00Z"),
+            Due::parse("2024-11-21T08:30:00+01:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn undated_tasks_start_from_completion() {
+        let recurrence = Recurrence::parse("FREQ=WEEKLY").unwrap();
+        assert_eq!(recurrence.next_due(None, &at("2024-11-20T09:00:00Z")), Ok(Due::Day(day("2024-11-27"))));
+    }
+
+    #[test]
+    fn intervals_stay_within_the_calendar() {
+        assert!(Recurrence::parse("FREQ=DAILY;INTERVAL=1000").is_ok());
+        assert!(Recurrence::parse("FREQ=DAILY;INTERVAL=1001").is_err());
+        assert!(Recurrence::parse("FREQ=MONTHLY;INTERVAL=4294967295").is_err());
+
+        let completed_at = NaiveDate::MAX.and_hms_opt(12, 0, 0).unwrap().and_utc();
+        for rule in ["FREQ=DAILY", "FREQ=WEEKLY", "FREQ=MONTHLY", "FREQ=DAILY;X-FROM=COMPLETION"] {
+            let recurrence = Recurrence::parse(rule).unwrap();
+            assert!(recurrence.next_due(Some(Due::Day(NaiveDate::MAX)), &completed_at).is_err(), "{}", rule);
+        }
+    }
+}
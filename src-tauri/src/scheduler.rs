@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::Notify;
+
+use crate::db::DbState;
+use crate::snapshot::{AutoBackupSettings, SnapshotInfo};
+
+pub const SUCCEEDED_EVENT: &str = "auto-backup://succeeded";
+pub const FAILED_EVENT: &str = "auto-backup://failed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupTrigger {
+    Interval,
+    Exit,
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupOutcome {
+    Succeeded,
+    Failed,
+    /// Automatic backups are off, or every workspace has auto-save disabled.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRun {
+    pub trigger: BackupTrigger,
+    pub outcome: BackupOutcome,
+    pub started_at: String,
+    pub finished_at: String,
+    pub snapshot: Option<SnapshotInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupSchedule {
+    #[serde(flatten)]
+    pub settings: AutoBackupSettings,
+    pub next_run_at: Option<String>,
+    pub last_run: Option<BackupRun>,
+}
+
+/// Managed state shared by the background task and the schedule commands.
+#[derive(Default)]
+pub struct BackupScheduler {
+    next_run_at: Mutex<Option<DateTime<Utc>>>,
+    last_run: Mutex<Option<BackupRun>>,
+    wake: Notify,
+}
+
+impl BackupScheduler {
+    pub fn schedule(&self, settings: AutoBackupSettings) -> BackupSchedule {
+        BackupSchedule {
+            settings,
+            next_run_at: self.next_run_at.lock().unwrap().map(|t| t.to_rfc3339()),
+            last_run: self.last_run.lock().unwrap().clone(),
+        }
+    }
+
+    /// Makes the background task re-read its settings right away.
+    pub fn reschedule(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Spawns the loop taking a snapshot every `interval_minutes`. The loop
+/// sleeps until the next run or until `reschedule` is called, so settings
+/// changes apply without a restart.
+pub fn start<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let settings = app
+                .state::<DbState>()
+                .get_auto_backup_settings()
+                .unwrap_or_default();
+            let scheduler = app.state::<BackupScheduler>();
+
+            if !settings.enabled {
+                *scheduler.next_run_at.lock().unwrap() = None;
+                scheduler.wake.notified().await;
+                continue;
+            }
+
+            let wait = Duration::from_secs(u64::from(settings.interval_minutes.max(1)) * 60);
+            *scheduler.next_run_at.lock().unwrap() =
+                chrono::Duration::from_std(wait).ok().map(|wait| Utc::now() + wait);
+
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {
+                    let handle = app.clone();
+                    let _ = tauri::async_runtime::spawn_blocking(move || {
+                        run(&handle, BackupTrigger::Interval)
+                    })
+                    .await;
+                }
+                _ = scheduler.wake.notified() => {}
+            }
+        }
+    });
+}
+
+/// Takes a snapshot now, records the outcome and notifies the frontend.
+/// Interval and exit runs are skipped when automatic backups are disabled
+/// or no workspace has auto-save on; manual runs always go ahead.
+pub fn run<R: Runtime>(app: &AppHandle<R>, trigger: BackupTrigger) -> BackupRun {
+    let db = app.state::<DbState>();
+    let started_at = Utc::now().to_rfc3339();
+
+    let enabled = db.get_auto_backup_settings().map(|s| s.enabled).unwrap_or(true);
+    let wanted = trigger == BackupTrigger::Manual
+        || (enabled && db.any_workspace_auto_saves().unwrap_or(true));
+
+    let (outcome, snapshot, error) = if !wanted {
+        (BackupOutcome::Skipped, None, None)
+    } else {
+        match db.create_snapshot() {
+            Ok(info) => (BackupOutcome::Succeeded, Some(info), None),
+            Err(e) => (BackupOutcome::Failed, None, Some(e.to_string())),
+        }
+    };
+
+    let run = BackupRun {
+        trigger,
+        outcome,
+        started_at,
+        finished_at: Utc::now().to_rfc3339(),
+        snapshot,
+        error,
+    };
+    *app.state::<BackupScheduler>().last_run.lock().unwrap() = Some(run.clone());
+
+    let event = match run.outcome {
+        BackupOutcome::Succeeded => Some(SUCCEEDED_EVENT),
+        BackupOutcome::Failed => Some(FAILED_EVENT),
+        BackupOutcome::Skipped => None,
+    };
+    if let Some(event) = event {
+        let _ = app.emit_all(event, run.clone());
+    }
+    run
+}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+/// Markers passed to FTS5 `highlight()`/`snippet()`; control characters so
+/// they cannot collide with anything a user types into a note.
+pub const MATCH_OPEN: char = '\u{2}';
+pub const MATCH_CLOSE: char = '\u{3}';
+
+/// A highlighted range, in UTF-16 code units so the frontend can feed it
+/// straight into `String.prototype.slice`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchOffset {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Term { text: String, prefix: bool },
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+/// Translates the search box syntax into an FTS5 query:
+///
+/// - `"exact phrase"` matches the words in order,
+/// - `word*` (or `"some phr"*`) matches by prefix,
+/// - `AND`, `OR`, `NOT` and parentheses combine terms; adjacent terms are ANDed.
+///
+/// Every term is quoted, so punctuation such as `c++` or `foo-bar` never
+/// reaches the FTS5 parser as syntax. Dangling operators and unbalanced
+/// parentheses are dropped instead of failing the whole query. Returns
+/// `None` when nothing searchable is left.
+pub fn to_fts_query(input: &str) -> Option<String> {
+    let tokens = sanitize(tokenize(input));
+    if !tokens.iter().any(|t| matches!(t, Token::Term { .. })) {
+        return None;
+    }
+
+    let parts: Vec<String> = tokens
+        .iter()
+        .map(|token| match token {
+            Token::Term { text, prefix } => {
+                let quoted = format!("\"{}\"", text.replace('"', "\"\""));
+                if *prefix {
+                    quoted + "*"
+                } else {
+                    quoted
+                }
+            }
+            Token::Op(op) => op.to_string(),
+            Token::Open => "(".to_string(),
+            Token::Close => ")".to_string(),
+        })
+        .collect();
+    Some(parts.join(" "))
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                text.push(c);
+            }
+            let mut prefix = false;
+            while chars.peek() == Some(&'*') {
+                chars.next();
+                prefix = true;
+            }
+            if !text.trim().is_empty() {
+                tokens.push(Token::Term { text, prefix });
+            }
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' || c == '(' || c == ')' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            match word.as_str() {
+                "AND" => tokens.push(Token::Op("AND")),
+                "OR" => tokens.push(Token::Op("OR")),
+                "NOT" => tokens.push(Token::Op("NOT")),
+                _ => {
+                    let prefix = word.ends_with('*');
+                    let text = word.trim_end_matches('*').to_string();
+                    if !text.is_empty() {
+                        tokens.push(Token::Term { text, prefix });
+                    }
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// Drops tokens FTS5 would reject: binary operators without an operand on
+/// both sides, unmatched or empty parentheses.
+fn sanitize(tokens: Vec<Token>) -> Vec<Token> {
+    let mut out: Vec<Token> = Vec::new();
+    let mut depth = 0usize;
+
+    for token in tokens {
+        match token {
+            Token::Op(_) => {
+                if matches!(out.last(), Some(Token::Term { .. }) | Some(Token::Close)) {
+                    out.push(token);
+                }
+            }
+            Token::Close => {
+                while matches!(out.last(), Some(Token::Op(_))) {
+                    out.pop();
+                }
+                if depth == 0 {
+                    continue;
+                }
+                depth -= 1;
+                if matches!(out.last(), Some(Token::Open)) {
+                    out.pop();
+                } else {
+                    out.push(Token::Close);
+                }
+            }
+            Token::Open => {
+                depth += 1;
+                out.push(Token::Open);
+            }
+            Token::Term { .. } => out.push(token),
+        }
+    }
+
+    loop {
+        match out.last() {
+            Some(Token::Op(_)) => {
+                out.pop();
+            }
+            Some(Token::Open) => {
+                out.pop();
+                depth -= 1;
+            }
+            _ => break,
+        }
+    }
+    out.extend((0..depth).map(|_| Token::Close));
+    out
+}
+
+/// Strips the match markers from FTS5 output, returning the plain text and
+/// the marked ranges within it.
+pub fn parse_marked(marked: &str) -> (String, Vec<MatchOffset>) {
+    let mut plain = String::with_capacity(marked.len());
+    let mut offsets = Vec::new();
+    let mut position = 0;
+    let mut start = None;
+
+    for c in marked.chars() {
+        match c {
+            MATCH_OPEN => start = Some(position),
+            MATCH_CLOSE => {
+                if let Some(start) = start.take() {
+                    offsets.push(MatchOffset { start, end: position });
+                }
+            }
+            _ => {
+                plain.push(c);
+                position += c.len_utf16();
+            }
+        }
+    }
+    (plain, offsets)
+}
+
+/// Renders FTS5 output as HTML-escaped text with matches wrapped in `<mark>`.
+pub fn marked_to_html(marked: &str) -> String {
+    let mut html = String::with_capacity(marked.len());
+    for c in marked.chars() {
+        match c {
+            MATCH_OPEN => html.push_str("<mark>"),
+            MATCH_CLOSE => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_plain_terms() {
+        assert_eq!(to_fts_query("rust sqlite").unwrap(), "\"rust\" \"sqlite\"");
+        assert_eq!(to_fts_query("c++").unwrap(), "\"c++\"");
+    }
+
+    #[test]
+    fn keeps_phrases_prefixes_and_operators() {
+        assert_eq!(
+            to_fts_query("\"release notes\" OR draft* NOT (old OR stale)").unwrap(),
+            "\"release notes\" OR \"draft\"* NOT ( \"old\" OR \"stale\" )"
+        );
+        assert_eq!(to_fts_query("\"meet\"*").unwrap(), "\"meet\"*");
+    }
+
+    #[test]
+    fn drops_dangling_syntax() {
+        assert_eq!(to_fts_query("OR rust AND").unwrap(), "\"rust\"");
+        assert_eq!(to_fts_query("(rust").unwrap(), "( \"rust\" )");
+        assert_eq!(to_fts_query("rust ) () sqlite").unwrap(), "\"rust\" \"sqlite\"");
+        assert_eq!(to_fts_query("  * \"\" AND "), None);
+    }
+
+    #[test]
+    fn parses_marked_ranges_in_utf16_units() {
+        let (plain, offsets) = parse_marked("é \u{2}note\u{3} 😀 \u{2}x\u{3}");
+        assert_eq!(plain, "é note 😀 x");
+        assert_eq!(
+            offsets,
+            vec![MatchOffset { start: 2, end: 6 }, MatchOffset { start: 10, end: 11 }]
+        );
+    }
+
+    #[test]
+    fn escapes_html_around_marks() {
+        assert_eq!(
+            marked_to_html("<b>\u{2}a&b\u{3}</b>"),
+            "&lt;b&gt;<mark>a&amp;b</mark>&lt;/b&gt;"
+        );
+    }
+}
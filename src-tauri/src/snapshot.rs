@@ -0,0 +1,297 @@
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::{AppError, Result};
+
+const FILE_PREFIX: &str = "app-";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+const RAW_EXTENSION: &str = ".db";
+const COMPRESSED_EXTENSION: &str = ".db.zst";
+const ZSTD_LEVEL: i32 = 3;
+
+/// How many snapshots survive a retention pass: the newest snapshot of each
+/// of the last `keep_daily` days and of each of the last `keep_weekly` ISO
+/// weeks. The most recent snapshot is always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotPolicy {
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+    pub compress: bool,
+}
+
+impl Default for SnapshotPolicy {
+    fn default() -> Self {
+        SnapshotPolicy {
+            keep_daily: 7,
+            keep_weekly: 4,
+            compress: true,
+        }
+    }
+}
+
+/// When the background scheduler takes snapshots on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AutoBackupSettings {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+}
+
+impl Default for AutoBackupSettings {
+    fn default() -> Self {
+        AutoBackupSettings {
+            enabled: true,
+            interval_minutes: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub file_name: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+    pub compressed: bool,
+}
+
+pub fn snapshot_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("snapshots")
+}
+
+/// Copies the live database into `dir` with SQLite's online backup API,
+/// checks the copy's integrity, then optionally compresses it. The file only
+/// gets its final name once it has been verified.
+pub fn create(conn: &Connection, dir: &Path, compress: bool) -> Result<SnapshotInfo> {
+    fs::create_dir_all(dir)?;
+    let stem = format!("{}{}", FILE_PREFIX, Utc::now().format(TIMESTAMP_FORMAT));
+    let partial = dir.join(format!("{}{}.partial", stem, RAW_EXTENSION));
+
+    let result = (|| {
+        {
+            let mut dst = Connection::open(&partial)?;
+            let backup = Backup::new(conn, &mut dst)?;
+            backup.run_to_completion(1024, Duration::ZERO, None)?;
+        }
+        check_integrity(&partial)?;
+
+        let final_path = if compress {
+            let path = dir.join(format!("{}{}", stem, COMPRESSED_EXTENSION));
+            let staged = dir.join(format!("{}{}.partial", stem, COMPRESSED_EXTENSION));
+            zstd::stream::copy_encode(File::open(&partial)?, File::create(&staged)?, ZSTD_LEVEL)?;
+            fs::rename(&staged, &path)?;
+            fs::remove_file(&partial)?;
+            path
+        } else {
+            let path = dir.join(format!("{}{}", stem, RAW_EXTENSION));
+            fs::rename(&partial, &path)?;
+            path
+        };
+        info(&final_path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Snapshots in `dir`, newest first. Files not named by `create` are ignored.
+pub fn list(dir: &Path) -> Result<Vec<SnapshotInfo>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Ok(snapshot) = info(&path) {
+            snapshots.push(snapshot);
+        }
+    }
+    snapshots.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    Ok(snapshots)
+}
+
+/// Re-checks a stored snapshot, decompressing it to a scratch file first
+/// when needed.
+pub fn verify(dir: &Path, file_name: &str) -> Result<SnapshotInfo> {
+    let path = resolve(dir, file_name)?;
+    let snapshot = info(&path)?;
+
+    if snapshot.compressed {
+        let scratch = dir.join(format!("{}.verify", file_name));
+        let result = zstd::stream::copy_decode(File::open(&path)?, File::create(&scratch)?)
+            .map_err(AppError::from)
+            .and_then(|_| check_integrity(&scratch));
+        let _ = fs::remove_file(&scratch);
+        result?;
+    } else {
+        check_integrity(&path)?;
+    }
+    Ok(snapshot)
+}
+
+pub fn delete(dir: &Path, file_name: &str) -> Result<()> {
+    let path = resolve(dir, file_name)?;
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Deletes the snapshots the policy no longer covers and returns their names.
+pub fn apply_retention(dir: &Path, policy: &SnapshotPolicy) -> Result<Vec<String>> {
+    let snapshots: Vec<(String, DateTime<Utc>)> = list(dir)?
+        .into_iter()
+        .filter_map(|s| parse_file_name(&s.file_name).map(|(time, _)| (s.file_name, time)))
+        .collect();
+
+    let expired = expired(&snapshots, policy);
+    for file_name in &expired {
+        fs::remove_file(dir.join(file_name))?;
+    }
+    Ok(expired)
+}
+
+/// `snapshots` must be ordered newest first.
+fn expired(snapshots: &[(String, DateTime<Utc>)], policy: &SnapshotPolicy) -> Vec<String> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut expired = Vec::new();
+
+    for (index, (file_name, time)) in snapshots.iter().enumerate() {
+        let mut keep = index == 0;
+
+        let day = time.date_naive();
+        if !days.contains(&day) && days.len() < policy.keep_daily as usize {
+            days.insert(day);
+            keep = true;
+        }
+        let week = (time.iso_week().year(), time.iso_week().week());
+        if !weeks.contains(&week) && weeks.len() < policy.keep_weekly as usize {
+            weeks.insert(week);
+            keep = true;
+        }
+
+        if !keep {
+            expired.push(file_name.clone());
+        }
+    }
+    expired
+}
+
+fn parse_file_name(file_name: &str) -> Option<(DateTime<Utc>, bool)> {
+    let rest = file_name.strip_prefix(FILE_PREFIX)?;
+    let (stamp, compressed) = if let Some(stamp) = rest.strip_suffix(COMPRESSED_EXTENSION) {
+        (stamp, true)
+    } else {
+        (rest.strip_suffix(RAW_EXTENSION)?, false)
+    };
+    let time = NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()?;
+    Some((time.and_utc(), compressed))
+}
+
+fn info(path: &Path) -> Result<SnapshotInfo> {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let (time, compressed) = parse_file_name(&file_name)
+        .ok_or_else(|| AppError::validation("file_name", "not a snapshot file"))?;
+
+    Ok(SnapshotInfo {
+        size_bytes: fs::metadata(path)?.len(),
+        file_name,
+        created_at: time.to_rfc3339(),
+        compressed,
+    })
+}
+
+/// Only bare snapshot file names are accepted, never paths.
+fn resolve(dir: &Path, file_name: &str) -> Result<PathBuf> {
+    if parse_file_name(file_name).is_none() || file_name.contains(['/', '\\']) {
+        return Err(AppError::validation("file_name", "not a snapshot file"));
+    }
+    let path = dir.join(file_name);
+    if !path.exists() {
+        return Err(AppError::not_found("snapshot", file_name));
+    }
+    Ok(path)
+}
+
+fn check_integrity(path: &Path) -> Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    if problems != ["ok"] {
+        return Err(AppError::Storage {
+            message: format!("snapshot failed integrity check: {}", problems.join("; ")),
+            sqlite_code: None,
+            busy: false,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(y: i32, m: u32, d: u32, h: u32) -> (String, DateTime<Utc>) {
+        let time = Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap();
+        (format!("{}{}{}", FILE_PREFIX, time.format(TIMESTAMP_FORMAT), RAW_EXTENSION), time)
+    }
+
+    #[test]
+    fn file_names_round_trip() {
+        let (name, time) = at(2024, 11, 23, 9);
+        assert_eq!(parse_file_name(&name), Some((time, false)));
+        assert_eq!(parse_file_name("app-20241123T090000000Z.db.zst"), Some((time, true)));
+        assert_eq!(parse_file_name("notes.db"), None);
+    }
+
+    #[test]
+    fn keeps_newest_per_day_and_week() {
+        // Newest first: two on Sat 23rd, one each on 22nd, 21st, then 13th, 6th
+        let snapshots = vec![
+            at(2024, 11, 23, 18),
+            at(2024, 11, 23, 9),
+            at(2024, 11, 22, 9),
+            at(2024, 11, 21, 9),
+            at(2024, 11, 13, 9),
+            at(2024, 11, 6, 9),
+        ];
+        let policy = SnapshotPolicy { keep_daily: 2, keep_weekly: 2, compress: false };
+
+        // Days: 23rd 18h, 22nd. Weeks: W47 (23rd 18h), W46 (13th).
+        let expired = expired(&snapshots, &policy);
+        assert_eq!(
+            expired,
+            vec![snapshots[1].0.clone(), snapshots[3].0.clone(), snapshots[5].0.clone()]
+        );
+    }
+
+    #[test]
+    fn always_keeps_latest() {
+        let snapshots = vec![at(2024, 11, 23, 18), at(2024, 11, 22, 9)];
+        let policy = SnapshotPolicy { keep_daily: 0, keep_weekly: 0, compress: false };
+        assert_eq!(expired(&snapshots, &policy), vec![snapshots[1].0.clone()]);
+    }
+
+    #[test]
+    fn rejects_paths() {
+        let dir = std::env::temp_dir();
+        assert!(matches!(
+            resolve(&dir, "../app-20241123T090000000Z.db"),
+            Err(AppError::Validation { .. })
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPriority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl TaskPriority {
+    /// Stored value; higher is more urgent.
+    pub fn rank(self) -> i64 {
+        match self {
+            TaskPriority::None => 0,
+            TaskPriority::Low => 1,
+            TaskPriority::Medium => 2,
+            TaskPriority::High => 3,
+        }
+    }
+
+    pub fn from_rank(rank: i64) -> Self {
+        match rank {
+            i64::MIN..=0 => TaskPriority::None,
+            1 => TaskPriority::Low,
+            2 => TaskPriority::Medium,
+            _ => TaskPriority::High,
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == TaskPriority::None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    #[default]
+    All,
+    Open,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DueRange {
+    /// Open tasks whose due date has passed.
+    Overdue,
+    Today,
+    /// From today to the end of the week (Monday to Sunday).
+    ThisWeek,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSort {
+    /// Soonest due first, undated tasks last, then by priority.
+    #[default]
+    Due,
+    /// Most urgent first, then soonest due.
+    Priority,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskQuery {
+    #[serde(default)]
+    pub status: TaskStatus,
+    #[serde(default)]
+    pub due: Option<DueRange>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub tag_id: Option<String>,
+    #[serde(default)]
+    pub sort: TaskSort,
+}
+
+/// A due date is either a whole day (`2024-11-23`) or a point in time
+/// (RFC 3339, as sent by `Date.toISOString()`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Due {
+    Day(NaiveDate),
+    At(DateTime<FixedOffset>),
+}
+
+impl Due {
+    pub fn parse(value: &str) -> Option<Due> {
+        let value = value.trim();
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(Due::Day)
+            .or_else(|_| DateTime::parse_from_rfc3339(value).map(Due::At))
+            .ok()
+    }
+
+    /// The day it falls on in `tz`, and the time of day unless it is a
+    /// whole day.
+    fn local<Tz: TimeZone>(&self, tz: &Tz) -> (NaiveDate, Option<NaiveTime>) {
+        match self {
+            Due::Day(day) => (*day, None),
+            Due::At(at) => {
+                let local = at.with_timezone(tz).naive_local();
+                (local.date(), Some(local.time()))
+            }
+        }
+    }
+}
+
+/// The stored form.
+impl fmt::Display for Due {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Due::Day(day) => write!(f, "{}", day.format("%Y-%m-%d")),
+            Due::At(at) => write!(f, "{}", at.to_rfc3339()),
+        }
+    }
+}
+
+/// The stored form of a due date, or `None` if `value` is not one.
+pub fn normalize_due(value: &str) -> Option<String> {
+    Due::parse(value).map(|due| due.to_string())
+}
+
+/// Whether a task due at `due` falls in `range` as seen at `now`.
+pub fn in_range<Tz: TimeZone>(due: Option<&str>, completed: bool, range: DueRange, now: &DateTime<Tz>) -> bool {
+    let Some(due) = due.and_then(Due::parse) else {
+        return false;
+    };
+    let today = now.naive_local().date();
+    let (day, _) = due.local(&now.timezone());
+    match range {
+        DueRange::Overdue => {
+            !completed
+                && match due {
+                    Due::Day(day) => day < today,
+                    Due::At(at) => at < *now,
+                }
+        }
+        DueRange::Today => day == today,
+        DueRange::ThisWeek => {
+            let sunday = today + Duration::days(6 - i64::from(today.weekday().num_days_from_monday()));
+            today <= day && day <= sunday
+        }
+    }
+}
+
+/// Orders two tasks given their due date and priority. Whole days sort
+/// before timed tasks of the same day.
+pub fn compare<Tz: TimeZone>(
+    sort: TaskSort,
+    a: (Option<&str>, TaskPriority),
+    b: (Option<&str>, TaskPriority),
+    tz: &Tz,
+) -> Ordering {
+    let due = |value: Option<&str>| value.and_then(Due::parse).map(|due| due.local(tz));
+    let by_due = || match (due(a.0), due(b.0)) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+    let by_priority = || b.1.cmp(&a.1);
+    match sort {
+        TaskSort::Due => by_due().then_with(by_priority),
+        TaskSort::Priority => by_priority().then_with(by_due),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn now() -> DateTime<FixedOffset> {
+        // Wednesday 20 November 2024, 10:00 in UTC+1
+        DateTime::parse_from_rfc3339("2024-11-20T10:00:00+01:00").unwrap()
+    }
+
+    #[test]
+    fn normalizes_days_and_instants() {
+        assert_eq!(normalize_due(" 2024-11-23 ").as_deref(), Some("2024-11-23"));
+        assert_eq!(
+            normalize_due("2024-11-22T23:00:00.000Z").as_deref(),
+            Some("2024-11-22T23:00:00+00:00")
+        );
+        assert_eq!(normalize_due("next friday"), None);
+        assert_eq!(normalize_due("2024-02-30"), None);
+    }
+
+    #[test]
+    fn ranges_use_the_local_day() {
+        let now = now();
+        // Midnight of the 21st in UTC+1
+        assert!(in_range(Some("2024-11-20T23:00:00Z"), false, DueRange::ThisWeek, &now));
+        assert!(!in_range(Some("2024-11-20T23:00:00Z"), false, DueRange::Today, &now));
+        assert!(in_range(Some("2024-11-20"), false, DueRange::Today, &now));
+        assert!(in_range(Some("2024-11-24"), false, DueRange::ThisWeek, &now));
+        assert!(!in_range(Some("2024-11-25"), false, DueRange::ThisWeek, &now));
+        assert!(!in_range(Some("2024-11-18"), false, DueRange::ThisWeek, &now));
+        assert!(!in_range(None, false, DueRange::Today, &now));
+    }
+
+    #[test]
+    fn overdue_needs_an_open_task_past_its_due() {
+        let now = now();
+        assert!(in_range(Some("2024-11-19"), false, DueRange::Overdue, &now));
+        assert!(!in_range(Some("2024-11-19"), true, DueRange::Overdue, &now));
+        // Due today as a whole day is not overdue yet, an hour ago is
+        assert!(!in_range(Some("2024-11-20"), false, DueRange::Overdue, &now));
+        assert!(in_range(Some("2024-11-20T08:00:00Z"), false, DueRange::Overdue, &now));
+    }
+
+    #[test]
+    fn sorts_by_due_or_priority() {
+        let mut tasks = vec![
+            (None, TaskPriority::High),
+            (Some("2024-11-21T08:00:00Z"), TaskPriority::None),
+            (Some("2024-11-21"), TaskPriority::Low),
+            (Some("2024-11-20"), TaskPriority::Low),
+            (Some("2024-11-20"), TaskPriority::Medium),
+        ];
+        tasks.sort_by(|a, b| compare(TaskSort::Due, *a, *b, &Utc));
+        assert_eq!(
+            tasks,
+            vec![
+                (Some("2024-11-20"), TaskPriority::Medium),
+                (Some("2024-11-20"), TaskPriority::Low),
+                (Some("2024-11-21"), TaskPriority::Low),
+                (Some("2024-11-21T08:00:00Z"), TaskPriority::None),
+                (None, TaskPriority::High),
+            ]
+        );
+        tasks.sort_by(|a, b| compare(TaskSort::Priority, *a, *b, &Utc));
+        assert_eq!(tasks[0], (None, TaskPriority::High));
+        assert_eq!(tasks[4], (Some("2024-11-21T08:00:00Z"), TaskPriority::None));
+    }
+
+    #[test]
+    fn priority_round_trips_through_its_rank() {
+        for priority in [TaskPriority::None, TaskPriority::Low, TaskPriority::Medium, TaskPriority::High] {
+            assert_eq!(TaskPriority::from_rank(priority.rank()), priority);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::db::CustomTheme;
+use crate::error::{AppError, Result};
+use crate::themes::{self, SavedTheme};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditorThemeFormat {
+    VsCode,
+    Base16,
+}
+
+/// Where a `CustomTheme` color came from: the source key, or `None` when
+/// the theme had none of the keys tried and a default was used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemeSource {
+    pub field: String,
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConvertedTheme {
+    pub theme: CustomTheme,
+    pub format: EditorThemeFormat,
+    pub sources: Vec<ThemeSource>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditorThemeImport {
+    #[serde(flatten)]
+    pub saved: SavedTheme,
+    pub format: EditorThemeFormat,
+    pub sources: Vec<ThemeSource>,
+}
+
+/// VS Code workbench keys tried for each field, best match first.
+const VS_CODE_KEYS: [(&str, &[&str]); 6] = [
+    ("background_color", &["editor.background"]),
+    (
+        "surface_color",
+        &["sideBar.background", "editorWidget.background", "panel.background", "activityBar.background"],
+    ),
+    ("text_color", &["editor.foreground", "foreground", "sideBar.foreground"]),
+    ("primary_color", &["button.background", "focusBorder", "activityBarBadge.background"]),
+    ("secondary_color", &["badge.background", "statusBar.background", "tab.activeBorder"]),
+    ("accent_color", &["editorCursor.foreground", "textLink.foreground", "activityBarBadge.background"]),
+];
+
+/// Base16 slots per field, after the styling guidelines: default and
+/// lighter background, default foreground, functions (blue), keywords
+/// (purple) and constants (orange).
+const BASE16_KEYS: [(&str, &str); 6] = [
+    ("background_color", "base00"),
+    ("surface_color", "base01"),
+    ("text_color", "base05"),
+    ("primary_color", "base0D"),
+    ("secondary_color", "base0E"),
+    ("accent_color", "base09"),
+];
+
+/// Fallbacks for colors a VS Code theme leaves out: the Dark+ and Light+
+/// defaults.
+fn vs_code_default(field: &str, dark: bool) -> &'static str {
+    match (field, dark) {
+        ("background_color", true) => "#1e1e1e",
+        ("background_color", false) => "#ffffff",
+        ("surface_color", true) => "#252526",
+        ("surface_color", false) => "#f3f3f3",
+        ("text_color", true) => "#d4d4d4",
+        ("text_color", false) => "#000000",
+        ("primary_color", _) => "#0e639c",
+        ("secondary_color", _) => "#68217a",
+        _ => "#007acc",
+    }
+}
+
+/// Reads a VS Code color theme (JSON with comments) or a Base16 scheme
+/// (YAML), chosen by the file extension or, failing that, by content.
+/// `fallback_name` names themes that have no name of their own.
+pub fn convert(file_name: &str, text: &str, fallback_name: &str) -> Result<ConvertedTheme> {
+    let extension = file_name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("json" | "jsonc") => convert_vs_code(text, fallback_name),
+        Some("yaml" | "yml") => convert_base16(text, fallback_name),
+        _ => convert_vs_code(text, fallback_name).or_else(|_| convert_base16(text, fallback_name)),
+    }
+}
+
+fn convert_vs_code(text: &str, fallback_name: &str) -> Result<ConvertedTheme> {
+    let theme: Map<String, Value> = serde_json::from_str(&strip_jsonc(text))
+        .map_err(|e| AppError::validation("path", format!("not a VS Code theme: {}", e)))?;
+    let Some(Value::Object(colors)) = theme.get("colors") else {
+        return Err(AppError::validation("path", "VS Code theme has no colors"));
+    };
+    let color = |key: &str| colors.get(key).and_then(Value::as_str).and_then(themes::normalize_color);
+
+    let mut picked = HashMap::new();
+    let mut sources = Vec::new();
+    for (field, keys) in VS_CODE_KEYS {
+        let found = keys.iter().find_map(|key| Some((*key, color(key)?)));
+        sources.push(ThemeSource {
+            field: field.to_string(),
+            key: found.as_ref().map(|(key, _)| key.to_string()),
+        });
+        if let Some((_, value)) = found {
+            picked.insert(field, value);
+        }
+    }
+
+    let dark = match theme.get("type").and_then(Value::as_str) {
+        Some(kind) => !matches!(kind, "light" | "hcLight" | "vs"),
+        None => picked.get("background_color").is_none_or(|bg| is_dark(bg)),
+    };
+    let mut pick = |field: &'static str| {
+        picked.remove(field).unwrap_or_else(|| vs_code_default(field, dark).to_string())
+    };
+    Ok(ConvertedTheme {
+        theme: CustomTheme {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name_or(theme.get("name").and_then(Value::as_str), fallback_name),
+            type_: if dark { "dark" } else { "light" }.to_string(),
+            primary_color: pick("primary_color"),
+            secondary_color: pick("secondary_color"),
+            background_color: pick("background_color"),
+            surface_color: pick("surface_color"),
+            text_color: pick("text_color"),
+            accent_color: pick("accent_color"),
+        },
+        format: EditorThemeFormat::VsCode,
+        sources,
+    })
+}
+
+fn convert_base16(text: &str, fallback_name: &str) -> Result<ConvertedTheme> {
+    let scheme: HashMap<String, serde_yaml::Value> = serde_yaml::from_str(text)
+        .map_err(|e| AppError::validation("path", format!("not a Base16 scheme: {}", e)))?;
+    // Newer schemes nest the slots under `palette`
+    let palette: HashMap<String, serde_yaml::Value> = match scheme.get("palette") {
+        Some(palette) => serde_yaml::from_value(palette.clone()).unwrap_or_default(),
+        None => scheme.clone(),
+    };
+    let slot = |key: &str| -> Result<String> {
+        let value = palette
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, v)| v.as_str())
+            .ok_or_else(|| AppError::validation("path", format!("Base16 scheme has no {}", key)))?;
+        let hex = format!("#{}", value.trim().trim_start_matches('#'));
+        themes::normalize_color(&hex)
+            .ok_or_else(|| AppError::validation("path", format!("{} is not a color", key)))
+    };
+
+    let mut colors = HashMap::new();
+    for (field, key) in BASE16_KEYS {
+        colors.insert(field, slot(key)?);
+    }
+    let name = scheme.get("scheme").or_else(|| scheme.get("name")).and_then(|v| v.as_str());
+    let mut pick = |field: &'static str| colors.remove(field).unwrap_or_default();
+    let background = pick("background_color");
+    Ok(ConvertedTheme {
+        theme: CustomTheme {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name_or(name, fallback_name),
+            type_: if is_dark(&background) { "dark" } else { "light" }.to_string(),
+            primary_color: pick("primary_color"),
+            secondary_color: pick("secondary_color"),
+            surface_color: pick("surface_color"),
+            text_color: pick("text_color"),
+            accent_color: pick("accent_color"),
+            background_color: background,
+        },
+        format: EditorThemeFormat::Base16,
+        sources: BASE16_KEYS
+            .iter()
+            .map(|(field, key)| ThemeSource { field: field.to_string(), key: Some(key.to_string()) })
+            .collect(),
+    })
+}
+
+fn name_or(name: Option<&str>, fallback: &str) -> String {
+    name.map(str::trim).filter(|n| !n.is_empty()).unwrap_or(fallback).to_string()
+}
+
+fn is_dark(color: &str) -> bool {
+    themes::parse_color(color).is_some_and(|c| c.luminance() < 0.18)
+}
+
+/// `text` with `//` and `/* */` comments and trailing commas removed, as VS
+/// Code accepts them in theme files.
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                out.push(' ');
+            }
+            _ => out.push(c),
+        }
+    }
+    remove_trailing_commas(&out)
+}
+
+/// Drops commas followed only by whitespace before a closing bracket.
+fn remove_trailing_commas(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in json.chars() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if matches!(c, '}' | ']') {
+            let kept = out.trim_end().len();
+            if out[..kept].ends_with(',') {
+                out.replace_range(kept - 1..kept, "");
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VS_CODE: &str = r##"{
+        // Gruvbox, trimmed
+        "name": "Gruvbox Dark",
+        "type": "dark",
+        "colors": {
+            "editor.background": "#282828", /* bg0 */
+            "editor.foreground": "#EBDBB2",
+            "sideBar.background": "#282828",
+            "focusBorder": "#458588",
+            "textLink.foreground": "#d3869b80",
+            "badge.background": "not a color",
+            "comment": "// not a comment",
+        },
+    }"##;
+
+    const BASE16: &str = "
+scheme: \"Tomorrow Night\"
+author: \"Chris Kempson\"
+base00: \"1d1f21\"
+base01: \"282a2e\"
+base02: \"373b41\"
+base03: \"969896\"
+base04: \"b4b7b4\"
+base05: \"c5c8c6\"
+base06: \"e0e0e0\"
+base07: \"ffffff\"
+base08: \"cc6666\"
+base09: \"de935f\"
+base0A: \"f0c674\"
+base0B: \"b5bd68\"
+base0C: \"8abeb7\"
+base0D: \"81a2be\"
+base0E: \"b294bb\"
+base0F: \"a3685a\"
+";
+
+    fn key(converted: &ConvertedTheme, field: &str) -> Option<String> {
+        converted.sources.iter().find(|s| s.field == field).unwrap().key.clone()
+    }
+
+    #[test]
+    fn strips_comments_and_trailing_commas() {
+        let json = strip_jsonc("{ \"a\": \"x // y\", /* c */ \"b\": [1, 2, ], // d\n }");
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["a"], "x // y");
+        assert_eq!(value["b"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn maps_vs_code_colors() {
+        let converted = convert("gruvbox.json", VS_CODE, "fallback").unwrap();
+        let theme = &converted.theme;
+        assert_eq!(converted.format, EditorThemeFormat::VsCode);
+        assert_eq!((theme.name.as_str(), theme.type_.as_str()), ("Gruvbox Dark", "dark"));
+        assert_eq!(theme.background_color, "#282828");
+        assert_eq!(theme.text_color, "#ebdbb2");
+        assert_eq!(theme.primary_color, "#458588");
+        assert_eq!(theme.accent_color, "#d3869b80");
+        assert_eq!(key(&converted, "primary_color").as_deref(), Some("focusBorder"));
+        // Invalid and missing colors fall back to the defaults
+        assert_eq!(key(&converted, "secondary_color"), None);
+        assert_eq!(theme.secondary_color, "#68217a");
+    }
+
+    #[test]
+    fn infers_the_type_from_the_background() {
+        let json = r##"{ "colors": { "editor.background": "#fdf6e3" } }"##;
+        let converted = convert("solarized", json, "Solarized").unwrap();
+        assert_eq!(converted.theme.type_, "light");
+        assert_eq!(converted.theme.name, "Solarized");
+        assert_eq!(converted.theme.text_color, "#000000");
+    }
+
+    #[test]
+    fn maps_base16_slots() {
+        let converted = convert("tomorrow-night.yaml", BASE16, "fallback").unwrap();
+        let theme = &converted.theme;
+        assert_eq!(converted.format, EditorThemeFormat::Base16);
+        assert_eq!((theme.name.as_str(), theme.type_.as_str()), ("Tomorrow Night", "dark"));
+        assert_eq!(theme.background_color, "#1d1f21");
+        assert_eq!(theme.surface_color, "#282a2e");
+        assert_eq!(theme.text_color, "#c5c8c6");
+        assert_eq!(theme.primary_color, "#81a2be");
+        assert_eq!(key(&converted, "accent_color").as_deref(), Some("base09"));
+    }
+
+    #[test]
+    fn reads_palette_schemes_and_reports_missing_slots() {
+        let yaml = "system: base16\nname: Mini\npalette:\n  base00: \"#ffffff\"\n  base01: \"#eeeeee\"\n  base05: \"#222222\"\n  base09: \"#ff8800\"\n  base0D: \"#0055ff\"\n  base0E: \"#aa00ff\"\n";
+        let converted = convert("mini.yml", yaml, "fallback").unwrap();
+        assert_eq!(converted.theme.type_, "light");
+        assert_eq!(converted.theme.secondary_color, "#aa00ff");
+
+        let err = convert("broken.yaml", "scheme: x\nbase00: \"000000\"\n", "x").unwrap_err();
+        assert!(err.to_string().contains("base01"), "{}", err);
+    }
+}
@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::db::CustomTheme;
+
+/// Written in theme files so other JSON is not mistaken for one.
+pub const THEME_FILE_FORMAT: &str = "markdown-editor-themes";
+pub const THEME_FILE_VERSION: u32 = 1;
+
+/// WCAG 2.1 minimum contrast for body text (level AA).
+const AA_NORMAL_TEXT: f64 = 4.5;
+/// WCAG 2.1 minimum contrast for large text (level AA).
+const AA_LARGE_TEXT: f64 = 3.0;
+
+/// A CSS color with components between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub alpha: f64,
+}
+
+impl Color {
+    /// WCAG relative luminance, ignoring alpha.
+    pub fn luminance(&self) -> f64 {
+        let linear = |c: f64| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    /// This color painted over `background`, taken as opaque.
+    fn over(&self, background: &Color) -> Color {
+        let mix = |fg: f64, bg: f64| fg * self.alpha + bg * (1.0 - self.alpha);
+        Color {
+            r: mix(self.r, background.r),
+            g: mix(self.g, background.g),
+            b: mix(self.b, background.b),
+            alpha: 1.0,
+        }
+    }
+}
+
+/// Parses `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()`/`rgba()` and
+/// `hsl()`/`hsla()`, with commas or the space-and-slash syntax.
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        return parse_hex(hex);
+    }
+
+    let (function, args) = value.strip_suffix(')')?.split_once('(')?;
+    let args = split_args(args)?;
+    let alpha = match args.get(3) {
+        Some(alpha) => parse_alpha(alpha)?,
+        None => 1.0,
+    };
+    match function.trim() {
+        "rgb" | "rgba" => Some(Color {
+            r: parse_channel(args[0])?,
+            g: parse_channel(args[1])?,
+            b: parse_channel(args[2])?,
+            alpha,
+        }),
+        "hsl" | "hsla" => {
+            let hue = parse_hue(args[0])?;
+            let saturation = parse_percent(args[1])?;
+            let lightness = parse_percent(args[2])?;
+            let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
+            Some(Color { r, g, b, alpha })
+        }
+        _ => None,
+    }
+}
+
+/// `value` as stored: trimmed and lowercased. `None` if it is not a color.
+pub fn normalize_color(value: &str) -> Option<String> {
+    parse_color(value).map(|_| value.trim().to_ascii_lowercase())
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits: Vec<u8> = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).map(|c| c as u8).collect(),
+        6 | 8 => hex.bytes().collect(),
+        _ => return None,
+    };
+    let byte = |i: usize| {
+        let pair = std::str::from_utf8(&digits[i * 2..i * 2 + 2]).ok()?;
+        u8::from_str_radix(pair, 16).ok().map(|v| f64::from(v) / 255.0)
+    };
+    Some(Color {
+        r: byte(0)?,
+        g: byte(1)?,
+        b: byte(2)?,
+        alpha: if digits.len() == 8 { byte(3)? } else { 1.0 },
+    })
+}
+
+/// Three or four arguments, separated by commas (`1, 2, 3, 0.5`) or by
+/// spaces with the alpha after a slash (`1 2 3 / 50%`).
+fn split_args(args: &str) -> Option<Vec<&str>> {
+    let parts: Vec<&str> = if args.contains(',') {
+        args.split(',').map(str::trim).collect()
+    } else {
+        let (channels, alpha) = match args.split_once('/') {
+            Some((channels, alpha)) => (channels, Some(alpha.trim())),
+            None => (args, None),
+        };
+        channels.split_whitespace().chain(alpha).collect()
+    };
+    matches!(parts.len(), 3 | 4).then_some(parts)
+}
+
+fn parse_percent(value: &str) -> Option<f64> {
+    let number: f64 = value.strip_suffix('%')?.trim().parse().ok()?;
+    (0.0..=100.0).contains(&number).then_some(number / 100.0)
+}
+
+fn parse_channel(value: &str) -> Option<f64> {
+    if value.ends_with('%') {
+        return parse_percent(value);
+    }
+    let number: f64 = value.parse().ok()?;
+    (0.0..=255.0).contains(&number).then_some(number / 255.0)
+}
+
+fn parse_alpha(value: &str) -> Option<f64> {
+    if value.ends_with('%') {
+        return parse_percent(value);
+    }
+    let number: f64 = value.parse().ok()?;
+    (0.0..=1.0).contains(&number).then_some(number)
+}
+
+fn parse_hue(value: &str) -> Option<f64> {
+    let number: f64 = value.strip_suffix("deg").unwrap_or(value).trim().parse().ok()?;
+    number.is_finite().then_some(number.rem_euclid(360.0))
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    (r + m, g + m, b + m)
+}
+
+/// WCAG contrast ratio of `foreground` over `background`, from 1 to 21.
+pub fn contrast_ratio(foreground: &Color, background: &Color) -> f64 {
+    let background = Color { alpha: 1.0, ..*background };
+    let a = foreground.over(&background).luminance();
+    let b = background.luminance();
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContrastRating {
+    /// Below 3:1, too low for any text.
+    Fail,
+    /// From 3:1 to 4.5:1, enough for large text only.
+    AaLargeText,
+}
+
+/// A pair of theme colors whose contrast is below the WCAG AA minimum for
+/// body text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContrastWarning {
+    pub foreground: String,
+    pub background: String,
+    /// Rounded to two decimals.
+    pub ratio: f64,
+    pub rating: ContrastRating,
+}
+
+/// Contrast warnings for the theme's text over its background and surface.
+/// Colors that do not parse are skipped; validation reports them.
+pub fn contrast_warnings(theme: &CustomTheme) -> Vec<ContrastWarning> {
+    let Some(text) = parse_color(&theme.text_color) else {
+        return Vec::new();
+    };
+    [("background_color", &theme.background_color), ("surface_color", &theme.surface_color)]
+        .into_iter()
+        .filter_map(|(field, value)| {
+            let ratio = contrast_ratio(&text, &parse_color(value)?);
+            let rating = if ratio < AA_LARGE_TEXT {
+                ContrastRating::Fail
+            } else if ratio < AA_NORMAL_TEXT {
+                ContrastRating::AaLargeText
+            } else {
+                return None;
+            };
+            Some(ContrastWarning {
+                foreground: "text_color".into(),
+                background: field.into(),
+                ratio: (ratio * 100.0).round() / 100.0,
+                rating,
+            })
+        })
+        .collect()
+}
+
+/// A theme as saved, with the contrast problems it has.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedTheme {
+    #[serde(flatten)]
+    pub theme: CustomTheme,
+    pub warnings: Vec<ContrastWarning>,
+}
+
+/// The JSON file `export_custom_themes` writes and `import_custom_themes`
+/// reads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeFile {
+    pub format: String,
+    pub format_version: u32,
+    pub themes: Vec<CustomTheme>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeExport {
+    pub path: PathBuf,
+    pub themes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeImportError {
+    pub name: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeImport {
+    pub themes: Vec<SavedTheme>,
+    /// Themes in the file that were left out, with the reason.
+    pub errors: Vec<ThemeImportError>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(value: &str) -> (u8, u8, u8, f64) {
+        let c = parse_color(value).unwrap();
+        let byte = |v: f64| (v * 255.0).round() as u8;
+        (byte(c.r), byte(c.g), byte(c.b), (c.alpha * 100.0).round() / 100.0)
+    }
+
+    #[test]
+    fn parses_css_colors() {
+        assert_eq!(rgb("#FA0"), (255, 170, 0, 1.0));
+        assert_eq!(rgb(" #ff880080 "), (255, 136, 0, 0.5));
+        assert_eq!(rgb("rgb(255, 0, 128)"), (255, 0, 128, 1.0));
+        assert_eq!(rgb("rgba(100%, 0%, 0%, 0.25)"), (255, 0, 0, 0.25));
+        assert_eq!(rgb("rgb(0 128 255 / 50%)"), (0, 128, 255, 0.5));
+        assert_eq!(rgb("hsl(120, 100%, 25%)"), (0, 128, 0, 1.0));
+        assert_eq!(rgb("hsla(-120deg 100% 50% / 1)"), (0, 0, 255, 1.0));
+    }
+
+    #[test]
+    fn rejects_non_colors() {
+        for value in ["", "red", "#ff000", "#ggg", "rgb(256, 0, 0)", "rgb(1, 2)", "hsl(0, 50, 50)", "rgba(0,0,0,2)"] {
+            assert!(parse_color(value).is_none(), "{}", value);
+        }
+        assert_eq!(normalize_color(" #ABCDEF ").as_deref(), Some("#abcdef"));
+    }
+
+    #[test]
+    fn computes_wcag_contrast() {
+        let black = parse_color("#000").unwrap();
+        let white = parse_color("#fff").unwrap();
+        assert!((contrast_ratio(&black, &white) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio(&white, &white) - 1.0).abs() < 1e-9);
+        // #767676 is the lightest grey passing AA on white
+        let grey = parse_color("#767676").unwrap();
+        assert!(contrast_ratio(&grey, &white) >= 4.5);
+        // Half-transparent black on white reads as mid grey
+        let faded = parse_color("rgba(0, 0, 0, 0.5)").unwrap();
+        assert!(contrast_ratio(&faded, &white) < 4.5);
+    }
+
+    #[test]
+    fn warns_about_low_contrast_text() {
+        let theme = CustomTheme {
+            id: "t".into(),
+            name: "Washed out".into(),
+            type_: "light".into(),
+            primary_color: "#1976d2".into(),
+            secondary_color: "#9c27b0".into(),
+            background_color: "#ffffff".into(),
+            surface_color: "#aaaaaa".into(),
+            text_color: "#888888".into(),
+            accent_color: "#ff4081".into(),
+        };
+        let warnings = contrast_warnings(&theme);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].background, "background_color");
+        assert_eq!(warnings[0].rating, ContrastRating::AaLargeText);
+        assert_eq!(warnings[0].ratio, 3.54);
+        assert_eq!(warnings[1].rating, ContrastRating::Fail);
+    }
+}
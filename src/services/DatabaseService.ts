@@ -17,12 +17,24 @@ export interface DBProject {
   name: string;
   created_at: string;
   updated_at: string;
+  description?: string | null;
+  color?: string | null;
+  icon?: string | null;
+  position?: number;
+  archived?: boolean;
+  // Only in get_projects results
+  note_count?: number;
+  task_count?: number;
+  open_task_count?: number;
 }
 
 export interface DBTag {
   id: string;
   name: string;
   created_at: string;
+  color?: string | null;
+  description?: string | null;
+  parent_id?: string | null;
 }
 
 export interface DBTask {
@@ -32,6 +44,12 @@ export interface DBTask {
   note_id: string;
   created_at: string;
   updated_at: string;
+  due_date?: string | null;
+  priority?: 'none' | 'low' | 'medium' | 'high';
+  completed_at?: string | null;
+  assignee?: string | null;
+  recurrence?: string | null;
+  series_id?: string | null;
 }
 
 export interface DBNoteTag {
@@ -92,10 +110,18 @@ class DatabaseService {
     return await invoke('create_note', { note });
   }
 
+  async getNote(id: string): Promise<DBNote> {
+    return await invoke('get_note', { id });
+  }
+
   async getNotes(projectId: string): Promise<DBNote[]> {
     return await invoke('get_notes', { projectId });
   }
 
+  async getNotesByTag(tagId: string): Promise<DBNote[]> {
+    return await invoke('get_notes_by_tag', { tagId });
+  }
+
   async updateNote(note: Partial<DBNote> & { id: string }): Promise<DBNote> {
     return await invoke('update_note', { note });
   }
@@ -109,6 +135,10 @@ class DatabaseService {
     return await invoke('create_project', { project });
   }
 
+  async getProject(id: string): Promise<DBProject> {
+    return await invoke('get_project', { id });
+  }
+
   async getProjects(): Promise<DBProject[]> {
     return await invoke('get_projects');
   }
@@ -130,6 +160,10 @@ class DatabaseService {
     return await invoke('get_tags');
   }
 
+  async updateTag(tag: DBTag): Promise<DBTag> {
+    return await invoke('update_tag', { tag });
+  }
+
   async deleteTag(id: string): Promise<void> {
     await invoke('delete_tag', { id });
   }
@@ -143,6 +177,10 @@ class DatabaseService {
     return await invoke('get_tasks', { noteId });
   }
 
+  async getAllTasks(): Promise<DBTask[]> {
+    return await invoke('get_all_tasks');
+  }
+
   async updateTask(task: Partial<DBTask> & { id: string }): Promise<DBTask> {
     return await invoke('update_task', { task });
   }